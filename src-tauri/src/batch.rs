@@ -0,0 +1,344 @@
+//! Batch conversion of whole directories.
+
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::cache::{self, Cache};
+use crate::convert;
+use crate::document::OutputFormat;
+use crate::error::ConversionError;
+use crate::jobs::{CancelToken, ConversionRegistry};
+use crate::journal::Journal;
+use crate::options::ConvertOptions;
+use crate::output::{self, WriteMode, WriteOutcome};
+use crate::{settings, tray};
+
+/// Outcome for one PDF of a batch. Exactly one of `output` and `error` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionResult {
+  pub source: String,
+  pub output: Option<String>,
+  /// Whether `output` was written, skipped or renamed; set along with it.
+  pub outcome: Option<WriteOutcome>,
+  pub error: Option<String>,
+}
+
+/// Payload of the `batch-progress` event, sent after each file.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgress {
+  /// How many files are done, counting the one just finished.
+  pub current: usize,
+  pub total: usize,
+  pub file: String,
+}
+
+/// Converts every PDF in `dir` to a file in `format`: `.md` for Markdown (the
+/// default), `.html` or `.txt`. It goes next to the PDF, or into the output
+/// directory of the settings if there is one, in the same subdirectory.
+///
+/// Up to `max_concurrency` files (by default, one per logical core) convert at
+/// the same time; 1 converts them one after the other. Results come back in
+/// file order whatever order they finish in.
+///
+/// Existing output files are overwritten unless `write_mode` says to skip
+/// their PDFs or to write beside them under a new name. Skipped PDFs aren't
+/// converted at all, so a batch can be re-run for the files added since.
+///
+/// A file that fails is reported in its [`ConversionResult`] and the batch
+/// carries on with the rest. Cancelling the job stops the whole batch.
+///
+/// Each file converted is noted in a journal in `dir`, removed once the
+/// batch is over. With `resume`, files a batch with the same options got
+/// through before it was cut short are skipped, their earlier results
+/// reported again.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn convert_directory(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  dir: String,
+  recursive: bool,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  max_concurrency: Option<usize>,
+  job_id: Option<String>,
+  write_mode: Option<WriteMode>,
+  resume: Option<bool>,
+) -> Result<Vec<ConversionResult>, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let write_mode = write_mode.unwrap_or_default();
+  let workers = max_concurrency
+    .filter(|&n| n > 0)
+    .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get));
+  let job = registry.start(job_id).announce(&app);
+  let cache = Cache::open(&app);
+  let out_dir = settings::load_settings(app.clone()).output_dir;
+  tauri::async_runtime::spawn_blocking(move || {
+    let dir = Path::new(&dir);
+    let out_dir = out_dir.as_deref().map(Path::new);
+    // Collect everything up front so `total` never changes mid-run.
+    let files = collect_pdfs(dir, recursive).map_err(|e| {
+      ConversionError::io(format_args!("cannot read directory {}", dir.display()), e)
+    })?;
+    let total = files.len();
+    let journal = Journal::open(dir, &(&options, format, recursive), resume.unwrap_or(false));
+
+    let convert = |file: &PathBuf| {
+      job.token().check()?;
+      if let Some(done) = journal.completed(file) {
+        return Ok(done);
+      }
+      let result = convert_to(
+        file,
+        &output::converted_path(file, dir, out_dir, format.extension()),
+        &options,
+        format,
+        write_mode,
+        cache.as_ref(),
+        job.token(),
+      )?;
+      if result.error.is_none() {
+        journal.record(&result);
+      }
+      Ok(result)
+    };
+    let report = |current: usize, file: &PathBuf| {
+      let progress = BatchProgress {
+        current,
+        total,
+        file: file.display().to_string(),
+      };
+      if let Err(err) = app.emit("batch-progress", progress) {
+        log::warn!("failed to emit batch progress: {err}");
+      }
+      tray::show_progress(&app, current, total);
+    };
+    tray::show_progress(&app, 0, total);
+    let results = run_pool(&files, workers, convert, report);
+    tray::show_idle(&app);
+    if results.is_ok() {
+      journal.finish();
+    }
+    results
+  })
+  .await?
+}
+
+/// Converts one file of a batch to `output`, creating its directory if need
+/// be. Only cancellation is an error; any other failure is recorded in the
+/// result.
+pub fn convert_to(
+  source: &Path,
+  output: &Path,
+  options: &ConvertOptions,
+  format: OutputFormat,
+  write_mode: WriteMode,
+  cache: Option<&Cache>,
+  cancel: &CancelToken,
+) -> Result<ConversionResult, ConversionError> {
+  let outcome = if write_mode == WriteMode::Skip && output.exists() {
+    Ok((output.to_path_buf(), WriteOutcome::Skipped))
+  } else {
+    // Shares entries with `convert_pdf_to_markdown`, which produces the same output.
+    let converted = cache::cached(cache, source, &("document", options), || {
+      convert::convert_file(source, None, options, cancel)
+    });
+    match converted {
+      Err(ConversionError::Cancelled) => return Err(ConversionError::Cancelled),
+      Err(err) => Err(err),
+      Ok(document) => output
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| output::write(output, document.render(format).as_bytes(), write_mode))
+        .map_err(|e| ConversionError::io(format_args!("cannot write {}", output.display()), e)),
+    }
+  };
+
+  let source = source.display().to_string();
+  Ok(match outcome {
+    Ok((output, outcome)) => ConversionResult {
+      source,
+      output: Some(output.display().to_string()),
+      outcome: Some(outcome),
+      error: None,
+    },
+    Err(error) => {
+      log::warn!("batch conversion of {source} failed: {error}");
+      ConversionResult {
+        source,
+        output: None,
+        outcome: None,
+        error: Some(error.to_string()),
+      }
+    }
+  })
+}
+
+/// Runs `work` on every item on up to `workers` threads and returns the
+/// results in item order. `done` is called as each item finishes, with the
+/// number finished so far, never concurrently and always counting up.
+///
+/// The first error stops the pool: items not yet started are skipped and the
+/// error is returned once running items are finished.
+fn run_pool<T, R, E>(
+  items: &[T],
+  workers: usize,
+  work: impl Fn(&T) -> Result<R, E> + Sync,
+  done: impl Fn(usize, &T) + Sync,
+) -> Result<Vec<R>, E>
+where
+  T: Sync,
+  R: Send,
+  E: Send,
+{
+  let next = AtomicUsize::new(0);
+  let finished = Mutex::new(0);
+  let failed = Mutex::new(None);
+  let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+  thread::scope(|scope| {
+    for _ in 0..workers.clamp(1, items.len().max(1)) {
+      scope.spawn(|| loop {
+        if failed.lock().unwrap_or_else(|e| e.into_inner()).is_some() {
+          return;
+        }
+        let index = next.fetch_add(1, Ordering::Relaxed);
+        let Some(item) = items.get(index) else {
+          return;
+        };
+        match work(item) {
+          Ok(result) => {
+            results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+            let mut finished = finished.lock().unwrap_or_else(|e| e.into_inner());
+            *finished += 1;
+            done(*finished, item);
+          }
+          Err(err) => {
+            failed
+              .lock()
+              .unwrap_or_else(|e| e.into_inner())
+              .get_or_insert(err);
+            return;
+          }
+        }
+      });
+    }
+  });
+
+  if let Some(err) = failed.into_inner().unwrap_or_else(|e| e.into_inner()) {
+    return Err(err);
+  }
+  Ok(
+    results
+      .into_inner()
+      .unwrap_or_else(|e| e.into_inner())
+      .into_iter()
+      .flatten()
+      .collect(),
+  )
+}
+
+/// Lists the `.pdf` files under `dir` in a stable (sorted) order.
+pub fn collect_pdfs(dir: &Path, recursive: bool) -> io::Result<Vec<PathBuf>> {
+  let mut files = Vec::new();
+  let mut pending = vec![dir.to_path_buf()];
+  while let Some(dir) = pending.pop() {
+    for entry in fs::read_dir(&dir)? {
+      let entry = entry?;
+      let path = entry.path();
+      let file_type = entry.file_type()?;
+      if file_type.is_dir() {
+        if recursive {
+          pending.push(path);
+        }
+      } else if is_pdf(&path) {
+        files.push(path);
+      }
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+pub fn is_pdf(path: &Path) -> bool {
+  path
+    .extension()
+    .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  #[test]
+  fn pool_caps_concurrency_and_keeps_item_order() {
+    let items: Vec<u64> = (0..12).collect();
+    let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    let reported = Mutex::new(Vec::new());
+    let results = run_pool(
+      &items,
+      3,
+      |&item| {
+        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+        peak.fetch_max(now, Ordering::SeqCst);
+        // Later items finish first.
+        thread::sleep(Duration::from_millis(12 - item));
+        running.fetch_sub(1, Ordering::SeqCst);
+        Ok::<_, ()>(item * 10)
+      },
+      |current, _| reported.lock().unwrap().push(current),
+    )
+    .unwrap();
+    assert_eq!(results, items.iter().map(|i| i * 10).collect::<Vec<_>>());
+    assert!(peak.load(Ordering::SeqCst) <= 3);
+    assert_eq!(reported.into_inner().unwrap(), (1..=12).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn one_worker_runs_items_in_order() {
+    let items = ["a", "b", "c"];
+    let order = Mutex::new(Vec::new());
+    run_pool(
+      &items,
+      1,
+      |&item| {
+        order.lock().unwrap().push(item);
+        Ok::<_, ()>(())
+      },
+      |_, _| {},
+    )
+    .unwrap();
+    assert_eq!(order.into_inner().unwrap(), items);
+  }
+
+  #[test]
+  fn an_error_stops_the_pool() {
+    let items: Vec<u32> = (0..100).collect();
+    let started = AtomicUsize::new(0);
+    let result = run_pool(
+      &items,
+      1,
+      |&item| {
+        started.fetch_add(1, Ordering::SeqCst);
+        if item == 2 {
+          Err("cancelled")
+        } else {
+          Ok(item)
+        }
+      },
+      |_, _| {},
+    );
+    assert_eq!(result, Err("cancelled"));
+    assert_eq!(started.load(Ordering::SeqCst), 3);
+  }
+}
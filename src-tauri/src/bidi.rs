@@ -0,0 +1,177 @@
+//! Right-to-left text. PDFs draw Arabic and Hebrew in visual order, left to
+//! right across the page, so lines are put back into the logical order
+//! Markdown stores text in.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+use unicode_bidi::{bidi_class, BidiClass, Level, ParagraphBidiInfo};
+
+use crate::extract::{TextLine, TextSpan};
+
+/// The base direction of lines, which decides where neutral characters such
+/// as punctuation go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TextDirection {
+  /// Right to left for lines mostly in a right-to-left script, left to
+  /// right otherwise.
+  #[default]
+  Auto,
+  Ltr,
+  Rtl,
+}
+
+/// `line` in logical order, split into spans where the reordering splits
+/// them. Lines without any right-to-left text are returned as they are.
+pub fn logical_order(line: &TextLine, direction: TextDirection) -> Cow<'_, TextLine> {
+  if !line.spans.iter().any(|span| span.text.chars().any(is_rtl)) {
+    return Cow::Borrowed(line);
+  }
+  let text = line.text();
+  // The span each byte of `text` comes from.
+  let owners: Vec<usize> = line
+    .spans
+    .iter()
+    .enumerate()
+    .flat_map(|(i, span)| std::iter::repeat(i).take(span.text.len()))
+    .collect();
+  let mut spans: Vec<TextSpan> = Vec::new();
+  let mut last = None;
+  for range in reorder(&text, direction) {
+    let owner = owners[range.start];
+    match spans.last_mut() {
+      Some(span) if last == Some(owner) => span.text.push_str(&text[range]),
+      _ => spans.push(TextSpan {
+        text: text[range].to_string(),
+        ..line.spans[owner].clone()
+      }),
+    }
+    last = Some(owner);
+  }
+  Cow::Owned(TextLine { spans })
+}
+
+/// The byte ranges of `text`, drawn in visual order, in logical order.
+///
+/// Reordering by the bidi algorithm's levels turns visual order back into
+/// logical order the same way it does the reverse, numbers and embedded
+/// left-to-right words included. Combining marks are moved with the letter
+/// before them rather than reversed on their own.
+fn reorder(text: &str, direction: TextDirection) -> Vec<Range<usize>> {
+  let rtl = match direction {
+    TextDirection::Auto => {
+      let count = |classes: &[BidiClass]| {
+        text
+          .chars()
+          .filter(|&c| classes.contains(&bidi_class(c)))
+          .count()
+      };
+      count(&[BidiClass::R, BidiClass::AL]) > count(&[BidiClass::L])
+    }
+    TextDirection::Ltr => false,
+    TextDirection::Rtl => true,
+  };
+  let base = if rtl { Level::rtl() } else { Level::ltr() };
+  let info = ParagraphBidiInfo::new(text, Some(base));
+  let (levels, runs) = info.visual_runs(0..text.len());
+  let mut out = Vec::with_capacity(text.len());
+  for run in runs {
+    let clusters = clusters(text, run.clone());
+    if levels[run.start].is_rtl() {
+      out.extend(clusters.into_iter().rev());
+    } else {
+      out.extend(clusters);
+    }
+  }
+  out
+}
+
+/// The characters of `text` within `run`, each with the combining marks
+/// after it.
+fn clusters(text: &str, run: Range<usize>) -> Vec<Range<usize>> {
+  let mut clusters: Vec<Range<usize>> = Vec::new();
+  for (offset, c) in text[run.clone()].char_indices() {
+    let start = run.start + offset;
+    let end = start + c.len_utf8();
+    match clusters.last_mut() {
+      Some(cluster) if bidi_class(c) == BidiClass::NSM => cluster.end = end,
+      _ => clusters.push(start..end),
+    }
+  }
+  clusters
+}
+
+fn is_rtl(c: char) -> bool {
+  matches!(bidi_class(c), BidiClass::R | BidiClass::AL)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn logical(visual: &str, direction: TextDirection) -> String {
+    reorder(visual, direction)
+      .into_iter()
+      .map(|range| &visual[range])
+      .collect()
+  }
+
+  #[test]
+  fn visual_order_becomes_logical() {
+    assert_eq!(logical("!םולש", TextDirection::Auto), "שלום!");
+    // Numbers and English inside right-to-left text keep their own order.
+    assert_eq!(logical("2024 ץרמב 15", TextDirection::Auto), "15 במרץ 2024");
+    assert_eq!(logical("ﻡﻼﺳ PDF ﻒﻠﻣ", TextDirection::Auto), "ﻣﻠﻒ PDF ﺳﻼﻡ");
+    assert_eq!(
+      logical("See םולש here", TextDirection::Auto),
+      "See שלום here"
+    );
+    assert_eq!(logical("abc ,םולש", TextDirection::Rtl), "שלום, abc");
+    assert_eq!(logical("hello", TextDirection::Rtl), "hello");
+  }
+
+  #[test]
+  fn combining_marks_stay_with_their_letters() {
+    // Shalom with its vowel points, each after the letter it belongs to.
+    let visual = "ם\u{05D5}\u{05B9}ל\u{05E9}\u{05C1}\u{05B8}";
+    assert_eq!(
+      logical(visual, TextDirection::Auto),
+      "\u{05E9}\u{05C1}\u{05B8}ל\u{05D5}\u{05B9}ם"
+    );
+  }
+
+  #[test]
+  fn spans_are_split_where_the_order_changes() {
+    let span = |text: &str, x: f32| TextSpan {
+      text: text.to_string(),
+      x,
+      y: 0.0,
+      width: 10.0,
+      font_size: 10.0,
+      font_name: "Arial".into(),
+      link: None,
+      style: Default::default(),
+      footnote: None,
+    };
+    let line = TextLine {
+      spans: vec![span("םלוע ", 0.0), span("םולש", 30.0)],
+    };
+    let logical = logical_order(&line, TextDirection::Auto);
+    let texts: Vec<(&str, f32)> = logical
+      .spans
+      .iter()
+      .map(|span| (span.text.as_str(), span.x))
+      .collect();
+    assert_eq!(texts, [("שלום", 30.0), (" עולם", 0.0)]);
+
+    let line = TextLine {
+      spans: vec![span("plain", 0.0)],
+    };
+    assert!(matches!(
+      logical_order(&line, TextDirection::Auto),
+      Cow::Borrowed(_)
+    ));
+  }
+}
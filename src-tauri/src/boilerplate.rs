@@ -0,0 +1,202 @@
+//! Running headers and footers.
+//!
+//! A line near the top or bottom of a page that recurs at the same height on
+//! most pages is boilerplate: a running title, a confidentiality notice, a
+//! page number. Digits are ignored when comparing lines, so `Page 3 of 40`
+//! on one page matches `Page 4 of 40` on the next.
+
+use crate::extract::{PageText, TextLine};
+
+/// How many lines at the top and at the bottom of a page may be boilerplate.
+const EDGE_LINES: usize = 3;
+
+/// How far apart, in points, the baselines of the same header on two pages
+/// may be.
+const Y_TOLERANCE: f32 = 2.0;
+
+/// A line that may recur, and on how many pages it does.
+#[derive(Debug)]
+struct Candidate {
+  text: String,
+  y: f32,
+  pages: usize,
+}
+
+/// The lines at the edges of pages, gathered a page at a time so the pages
+/// don't all have to be kept around.
+#[derive(Debug, Default)]
+pub struct Boilerplate {
+  pages: usize,
+  candidates: Vec<Candidate>,
+}
+
+impl Boilerplate {
+  pub fn add(&mut self, page: &PageText) {
+    self.pages += 1;
+    let mut seen = Vec::new();
+    let count = page.lines.len();
+    for (_, line) in page
+      .lines
+      .iter()
+      .enumerate()
+      .filter(|&(i, _)| is_edge(i, count))
+    {
+      let text = normalize(&line.text());
+      if text.is_empty() {
+        continue;
+      }
+      let y = line.y();
+      let i = match self.find(&text, y) {
+        Some(i) => i,
+        None => {
+          self.candidates.push(Candidate { text, y, pages: 0 });
+          self.candidates.len() - 1
+        }
+      };
+      // A line repeated on one page still counts once.
+      if !seen.contains(&i) {
+        seen.push(i);
+        self.candidates[i].pages += 1;
+      }
+    }
+  }
+
+  /// Removes the lines of `page` found at the same place on more than
+  /// `threshold` (a fraction) of the pages seen. Nothing is removed from a
+  /// single page: there is nothing to compare it with.
+  pub fn strip(&self, page: &mut PageText, threshold: f32) {
+    let count = page.lines.len();
+    let mut i = 0;
+    page.lines.retain(|line| {
+      let edge = is_edge(i, count);
+      i += 1;
+      !(edge && self.is_boilerplate(line, threshold))
+    });
+  }
+
+  fn is_boilerplate(&self, line: &TextLine, threshold: f32) -> bool {
+    let Some(i) = self.find(&normalize(&line.text()), line.y()) else {
+      return false;
+    };
+    let pages = self.candidates[i].pages;
+    pages >= 2 && pages as f32 > threshold * self.pages as f32
+  }
+
+  fn find(&self, text: &str, y: f32) -> Option<usize> {
+    self
+      .candidates
+      .iter()
+      .position(|c| c.text == text && (c.y - y).abs() <= Y_TOLERANCE)
+  }
+}
+
+/// Removes the running headers and footers from `pages`: lines found at the
+/// same place on more than `threshold` of them.
+pub fn strip(pages: &mut [(u32, PageText)], threshold: f32) {
+  let mut boilerplate = Boilerplate::default();
+  for (_, page) in pages.iter() {
+    boilerplate.add(page);
+  }
+  for (_, page) in pages.iter_mut() {
+    boilerplate.strip(page, threshold);
+  }
+}
+
+/// Whether the line at `index` of a page's `count` is one of the top or
+/// bottom lines, which is where headers and footers go.
+fn is_edge(index: usize, count: usize) -> bool {
+  index < EDGE_LINES || index + EDGE_LINES >= count
+}
+
+/// `text` with runs of digits as `#` and whitespace collapsed, so that page
+/// numbers and dates compare equal.
+fn normalize(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for word in text.split_whitespace() {
+    if !out.is_empty() {
+      out.push(' ');
+    }
+    let mut digits = false;
+    for c in word.chars() {
+      if c.is_ascii_digit() {
+        if !digits {
+          out.push('#');
+        }
+        digits = true;
+      } else {
+        out.push(c);
+        digits = false;
+      }
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::extract::TextSpan;
+
+  fn page(lines: &[(&str, f32)]) -> PageText {
+    PageText {
+      lines: lines
+        .iter()
+        .map(|&(text, y)| TextLine {
+          spans: vec![TextSpan {
+            text: text.to_string(),
+            x: 72.0,
+            y,
+            width: 100.0,
+            font_size: 10.0,
+            font_name: "Helvetica".into(),
+            link: None,
+            style: Default::default(),
+            footnote: None,
+          }],
+        })
+        .collect(),
+      images: Vec::new(),
+    }
+  }
+
+  fn texts(page: &PageText) -> Vec<String> {
+    page.lines.iter().map(TextLine::text).collect()
+  }
+
+  #[test]
+  fn recurring_headers_and_page_numbers_are_removed() {
+    let mut pages: Vec<(u32, PageText)> = (1..=4)
+      .map(|n| {
+        let footer = format!("Page {n} of 4");
+        let body = ["Summary", "Sales", "Costs", "Outlook"][n as usize - 1];
+        let lines = [
+          ("Quarterly Report", 760.0),
+          (body, 700.0),
+          (footer.as_str(), 40.0 + n as f32 * 0.5),
+        ];
+        (n, page(&lines))
+      })
+      .collect();
+    // The title page has no header.
+    pages[0].1.lines.remove(0);
+    strip(&mut pages, 0.6);
+    assert_eq!(texts(&pages[0].1), ["Summary"]);
+    assert_eq!(texts(&pages[3].1), ["Outlook"]);
+  }
+
+  #[test]
+  fn lines_on_few_pages_or_one_page_stay() {
+    let mut pages = vec![
+      (1, page(&[("Draft", 760.0), ("One", 700.0)])),
+      (2, page(&[("Draft", 760.0), ("Two", 700.0)])),
+      (3, page(&[("Three", 700.0)])),
+      (4, page(&[("Four", 700.0)])),
+    ];
+    strip(&mut pages, 0.6);
+    assert_eq!(texts(&pages[0].1), ["Draft", "One"]);
+
+    let mut single = vec![(1, page(&[("Title", 760.0), ("Text", 700.0)]))];
+    strip(&mut single, 0.6);
+    assert_eq!(texts(&single[0].1), ["Title", "Text"]);
+  }
+}
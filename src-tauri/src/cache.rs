@@ -0,0 +1,219 @@
+//! On-disk cache of conversion results.
+//!
+//! Entries live in the app's cache directory, one file per source path and
+//! set of options. Each entry records the SHA-256 of the PDF it was made
+//! from; when the file's content changes the entry is discarded and the
+//! conversion runs again.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::error::ConversionError;
+
+/// Bump when the output of a conversion changes, so old entries are ignored.
+const CACHE_VERSION: u32 = 16;
+
+const CACHE_SUBDIR: &str = "conversions";
+
+/// Handle to the cache directory.
+#[derive(Debug, Clone)]
+pub struct Cache {
+  dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry<H, T> {
+  source_hash: H,
+  output: T,
+}
+
+impl Cache {
+  /// Opens the app's cache, or `None` (with a warning) if it has no cache dir.
+  pub fn open(app: &AppHandle) -> Option<Cache> {
+    match app.path().app_cache_dir() {
+      Ok(dir) => Some(Cache {
+        dir: dir.join(CACHE_SUBDIR),
+      }),
+      Err(err) => {
+        log::warn!("conversion cache disabled: {err}");
+        None
+      }
+    }
+  }
+
+  /// Locates the entry for `source` and `variant`, hashing the source. Returns
+  /// `None` if the source can't be read; the conversion will report why.
+  pub fn slot(&self, source: &Path, variant: &impl Serialize) -> Option<Slot> {
+    let source_hash = hash_file(source).ok()?;
+    let variant = serde_json::to_string(variant).ok()?;
+    let mut key = Sha256::new();
+    key.update(CACHE_VERSION.to_le_bytes());
+    key.update(source.to_string_lossy().as_bytes());
+    key.update([0]);
+    key.update(variant.as_bytes());
+    let name = format!("{}.json", hex(&key.finalize()[..16]));
+    Some(Slot {
+      path: self.dir.join(name),
+      source_hash,
+    })
+  }
+
+  /// Deletes every entry, returning the number of bytes freed.
+  pub fn clear(&self) -> io::Result<u64> {
+    let entries = match fs::read_dir(&self.dir) {
+      Ok(entries) => entries,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+      Err(err) => return Err(err),
+    };
+    let mut freed = 0;
+    for entry in entries {
+      let entry = entry?;
+      let metadata = entry.metadata()?;
+      if metadata.is_file() {
+        fs::remove_file(entry.path())?;
+        freed += metadata.len();
+      }
+    }
+    Ok(freed)
+  }
+}
+
+/// Returns the cached result of converting `source` with `variant` (the
+/// command's options), or runs `convert` and caches what it returns. Without
+/// a cache this just runs `convert`.
+pub fn cached<T, F>(
+  cache: Option<&Cache>,
+  source: &Path,
+  variant: &impl Serialize,
+  convert: F,
+) -> Result<T, ConversionError>
+where
+  T: Serialize + DeserializeOwned,
+  F: FnOnce() -> Result<T, ConversionError>,
+{
+  let Some(slot) = cache.and_then(|cache| cache.slot(source, variant)) else {
+    return convert();
+  };
+  if let Some(output) = slot.load() {
+    return Ok(output);
+  }
+  let output = convert()?;
+  slot.store(&output);
+  Ok(output)
+}
+
+/// The cache entry for one source file and set of options.
+pub struct Slot {
+  path: PathBuf,
+  source_hash: String,
+}
+
+impl Slot {
+  /// The cached output, if there is one for the current file content.
+  pub fn load<T: DeserializeOwned>(&self) -> Option<T> {
+    let text = fs::read_to_string(&self.path).ok()?;
+    match serde_json::from_str::<Entry<String, T>>(&text) {
+      Ok(entry) if entry.source_hash == self.source_hash => Some(entry.output),
+      _ => {
+        // Stale (the PDF changed) or unreadable; drop it.
+        let _ = fs::remove_file(&self.path);
+        None
+      }
+    }
+  }
+
+  /// Saves `output`. A cache that can't be written only costs time, so
+  /// failures are logged rather than returned.
+  pub fn store<T: Serialize>(&self, output: &T) {
+    let entry = Entry {
+      source_hash: &self.source_hash,
+      output,
+    };
+    let result = self
+      .path
+      .parent()
+      .map_or(Ok(()), fs::create_dir_all)
+      .and_then(|()| serde_json::to_vec(&entry).map_err(io::Error::other))
+      .and_then(|json| fs::write(&self.path, json));
+    if let Err(err) = result {
+      log::warn!("cannot write cache entry {}: {err}", self.path.display());
+    }
+  }
+}
+
+/// Empties the conversion cache and returns how many bytes were freed.
+#[tauri::command]
+pub fn clear_cache(app: AppHandle) -> Result<u64, ConversionError> {
+  let Some(cache) = Cache::open(&app) else {
+    return Ok(0);
+  };
+  let freed = cache
+    .clear()
+    .map_err(|e| ConversionError::io("cannot clear cache", e))?;
+  log::info!("cleared {freed} bytes of cached conversions");
+  Ok(freed)
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+  let mut hasher = Sha256::new();
+  io::copy(&mut File::open(path)?, &mut hasher)?;
+  Ok(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct TempDir(PathBuf);
+
+  impl TempDir {
+    fn new(name: &str) -> Self {
+      let dir = std::env::temp_dir().join(format!("pdf2markdown-{name}-{}", std::process::id()));
+      fs::create_dir_all(&dir).unwrap();
+      TempDir(dir)
+    }
+  }
+
+  impl Drop for TempDir {
+    fn drop(&mut self) {
+      let _ = fs::remove_dir_all(&self.0);
+    }
+  }
+
+  #[test]
+  fn reuses_results_until_the_source_changes() {
+    let temp = TempDir::new("cache-reuse");
+    let cache = Cache {
+      dir: temp.0.join("cache"),
+    };
+    let source = temp.0.join("a.pdf");
+    fs::write(&source, "one").unwrap();
+
+    let convert = |text: &'static str| move || Ok::<_, ConversionError>(text.to_string());
+    let first = cached(Some(&cache), &source, &"opts", convert("first")).unwrap();
+    let again = cached(Some(&cache), &source, &"opts", convert("second")).unwrap();
+    assert_eq!((first.as_str(), again.as_str()), ("first", "first"));
+
+    // Different options are a different entry.
+    let other = cached(Some(&cache), &source, &"other", convert("third")).unwrap();
+    assert_eq!(other, "third");
+
+    fs::write(&source, "two").unwrap();
+    let changed = cached(Some(&cache), &source, &"opts", convert("fourth")).unwrap();
+    assert_eq!(changed, "fourth");
+
+    assert!(cache.clear().unwrap() > 0);
+    assert_eq!(cache.clear().unwrap(), 0);
+  }
+}
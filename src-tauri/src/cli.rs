@@ -0,0 +1,295 @@
+//! Headless use from the command line.
+//!
+//! ```text
+//! pdf2markdown convert <input.pdf> [-o <output>] [--format <format>] [--password <password>]
+//! pdf2markdown --batch <dir> [--recursive] [--format <format>]
+//! ```
+//!
+//! Any other arguments (or none) start the app as usual, opening the PDFs
+//! among them.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::batch;
+use crate::convert;
+use crate::document::OutputFormat;
+use crate::jobs::CancelToken;
+use crate::options::ConvertOptions;
+use crate::output::WriteMode;
+
+const USAGE: &str = "\
+usage: pdf2markdown convert <input.pdf> [-o <output>] [--format <format>] [--password <password>]
+       pdf2markdown --batch <dir> [--recursive] [--format <format>]
+
+Without -o the converted document is written to stdout. <format> is one of
+markdown (the default), html or text; with -o it is inferred from the output
+file's extension when not given. --batch writes each PDF's output next to it.
+";
+
+/// Exit code for a failed conversion.
+const FAILURE: i32 = 1;
+
+/// Exit code for unusable arguments.
+const USAGE_ERROR: i32 = 2;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+  Convert {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    format: Option<OutputFormat>,
+    password: Option<String>,
+  },
+  Batch {
+    dir: PathBuf,
+    recursive: bool,
+    format: Option<OutputFormat>,
+  },
+  Help,
+  Version,
+}
+
+/// Runs the command given in `args` (without the program name) and returns
+/// the process exit code, or `None` if the arguments aren't a command and the
+/// app should start.
+pub fn run(args: impl IntoIterator<Item = OsString>) -> Option<i32> {
+  let args: Vec<String> = args
+    .into_iter()
+    .map(|arg| arg.to_string_lossy().into_owned())
+    .collect();
+  let command = match parse(&args) {
+    Ok(command) => command?,
+    Err(err) => {
+      eprintln!("pdf2markdown: {err}\n\n{USAGE}");
+      return Some(USAGE_ERROR);
+    }
+  };
+  Some(match command {
+    Command::Help => {
+      print!("{USAGE}");
+      0
+    }
+    Command::Version => {
+      println!("pdf2markdown {}", env!("CARGO_PKG_VERSION"));
+      0
+    }
+    Command::Convert {
+      input,
+      output,
+      format,
+      password,
+    } => convert(&input, output.as_deref(), format, password.as_deref()),
+    Command::Batch {
+      dir,
+      recursive,
+      format,
+    } => convert_batch(&dir, recursive, format.unwrap_or_default()),
+  })
+}
+
+fn parse(args: &[String]) -> Result<Option<Command>, String> {
+  let Some(first) = args.first() else {
+    return Ok(None);
+  };
+  let mut rest = args[1..].iter();
+  let mut value = |flag: &str| {
+    rest
+      .next()
+      .cloned()
+      .ok_or_else(|| format!("{flag} needs a value"))
+  };
+  let command = match first.as_str() {
+    "-h" | "--help" | "help" => Command::Help,
+    "-V" | "--version" => Command::Version,
+    "convert" => {
+      let (mut input, mut output, mut format, mut password) = (None, None, None, None);
+      while let Ok(arg) = value("") {
+        match arg.as_str() {
+          "-o" | "--output" => output = Some(PathBuf::from(value(&arg)?)),
+          "-f" | "--format" => format = Some(parse_format(&value(&arg)?)?),
+          "--password" => password = Some(value(&arg)?),
+          flag if flag.starts_with('-') => return Err(format!("unknown option {flag}")),
+          _ if input.is_some() => return Err(format!("unexpected argument {arg}")),
+          _ => input = Some(PathBuf::from(arg)),
+        }
+      }
+      Command::Convert {
+        input: input.ok_or("convert needs an input PDF")?,
+        output,
+        format,
+        password,
+      }
+    }
+    "--batch" => {
+      let dir = PathBuf::from(value("--batch")?);
+      let (mut recursive, mut format) = (false, None);
+      while let Ok(arg) = value("") {
+        match arg.as_str() {
+          "-r" | "--recursive" => recursive = true,
+          "-f" | "--format" => format = Some(parse_format(&value(&arg)?)?),
+          _ => return Err(format!("unexpected argument {arg}")),
+        }
+      }
+      Command::Batch {
+        dir,
+        recursive,
+        format,
+      }
+    }
+    _ => return Ok(None),
+  };
+  Ok(Some(command))
+}
+
+fn parse_format(name: &str) -> Result<OutputFormat, String> {
+  match name.to_ascii_lowercase().as_str() {
+    "md" | "markdown" => Ok(OutputFormat::Markdown),
+    "html" | "htm" => Ok(OutputFormat::Html),
+    "txt" | "text" | "plain" => Ok(OutputFormat::PlainText),
+    _ => Err(format!("unknown format {name}")),
+  }
+}
+
+fn convert(
+  input: &Path,
+  output: Option<&Path>,
+  format: Option<OutputFormat>,
+  password: Option<&str>,
+) -> i32 {
+  let format = format
+    .or_else(|| {
+      let extension = output?.extension()?.to_str()?;
+      parse_format(extension).ok()
+    })
+    .unwrap_or_default();
+  let options = ConvertOptions::default();
+  let cancel = CancelToken::default();
+  // Pages are written as they are converted, so long PDFs needn't fit in memory.
+  let result = match output {
+    None => convert::convert_to_writer(
+      input,
+      password,
+      &options,
+      format,
+      io::stdout().lock(),
+      &cancel,
+    )
+    .map(drop),
+    Some(output) => convert::convert_to_file(input, output, password, &options, format, &cancel),
+  };
+  match result {
+    Ok(()) => 0,
+    Err(err) => {
+      eprintln!("pdf2markdown: {}: {err}", input.display());
+      FAILURE
+    }
+  }
+}
+
+/// Converts every PDF in `dir`, reporting each file on stderr. Fails if any
+/// file did.
+fn convert_batch(dir: &Path, recursive: bool, format: OutputFormat) -> i32 {
+  let files = match batch::collect_pdfs(dir, recursive) {
+    Ok(files) => files,
+    Err(err) => {
+      eprintln!(
+        "pdf2markdown: cannot read directory {}: {err}",
+        dir.display()
+      );
+      return FAILURE;
+    }
+  };
+  let options = ConvertOptions::default();
+  let cancel = CancelToken::default();
+  let mut failed = 0;
+  for file in &files {
+    let result = match batch::convert_to(
+      file,
+      &file.with_extension(format.extension()),
+      &options,
+      format,
+      WriteMode::Overwrite,
+      None,
+      &cancel,
+    ) {
+      Ok(result) => result,
+      Err(err) => {
+        eprintln!("pdf2markdown: {err}");
+        return FAILURE;
+      }
+    };
+    match (result.output, result.error) {
+      (Some(output), _) => eprintln!("{} -> {output}", result.source),
+      (None, error) => {
+        failed += 1;
+        eprintln!("{}: {}", result.source, error.unwrap_or_default());
+      }
+    }
+  }
+  if failed > 0 {
+    eprintln!("pdf2markdown: {failed} of {} files failed", files.len());
+    return FAILURE;
+  }
+  0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(str::to_string).collect()
+  }
+
+  #[test]
+  fn parses_convert() {
+    assert_eq!(
+      parse(&args("convert in.pdf -o out.html --password pw")),
+      Ok(Some(Command::Convert {
+        input: "in.pdf".into(),
+        output: Some("out.html".into()),
+        format: None,
+        password: Some("pw".into()),
+      }))
+    );
+    assert_eq!(
+      parse(&args("convert --format text in.pdf")),
+      Ok(Some(Command::Convert {
+        input: "in.pdf".into(),
+        output: None,
+        format: Some(OutputFormat::PlainText),
+        password: None,
+      }))
+    );
+  }
+
+  #[test]
+  fn parses_batch() {
+    assert_eq!(
+      parse(&args("--batch scans -r")),
+      Ok(Some(Command::Batch {
+        dir: "scans".into(),
+        recursive: true,
+        format: None,
+      }))
+    );
+  }
+
+  #[test]
+  fn rejects_bad_arguments() {
+    assert!(parse(&args("convert")).is_err());
+    assert!(parse(&args("convert a.pdf b.pdf")).is_err());
+    assert!(parse(&args("convert a.pdf -o")).is_err());
+    assert!(parse(&args("convert a.pdf --format docx")).is_err());
+    assert!(parse(&args("--batch")).is_err());
+  }
+
+  #[test]
+  fn other_arguments_start_the_app() {
+    assert_eq!(parse(&[]), Ok(None));
+    assert_eq!(parse(&args("report.pdf")), Ok(None));
+    assert_eq!(parse(&args("-psn_0_12345")), Ok(None));
+  }
+}
@@ -0,0 +1,68 @@
+//! Copying conversions to the system clipboard, so a result can be pasted
+//! elsewhere without saving it first.
+
+use std::borrow::Cow;
+
+use tauri::{AppHandle, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::convert;
+use crate::error::ConversionError;
+use crate::jobs::ConversionRegistry;
+use crate::options::ConvertOptions;
+
+/// Puts `text` on the clipboard as plain text, all of it however long.
+#[tauri::command]
+pub fn copy_to_clipboard(app: AppHandle, text: String) -> Result<(), ConversionError> {
+  write(&app, &text)
+}
+
+/// Converts the PDF at `path` to Markdown, as `convert_pdf_to_markdown`
+/// does, and copies it. The Markdown is returned as well, so the UI can show
+/// what was copied.
+#[tauri::command]
+pub async fn copy_file_as_markdown(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  options: Option<ConvertOptions>,
+  password: Option<String>,
+  job_id: Option<String>,
+) -> Result<String, ConversionError> {
+  let markdown =
+    convert::convert_pdf_to_markdown(app.clone(), registry, path, options, None, password, job_id)
+      .await?
+      .output;
+  write(&app, &markdown)?;
+  Ok(markdown)
+}
+
+fn write(app: &AppHandle, text: &str) -> Result<(), ConversionError> {
+  app
+    .clipboard()
+    .write_text(clipboard_text(text))
+    .map_err(|err| ConversionError::Clipboard(err.to_string()))?;
+  log::info!("copied {} bytes to the clipboard", text.len());
+  Ok(())
+}
+
+/// `text` without NUL characters: some platforms' clipboards end the text
+/// at the first one, which would silently drop the rest.
+fn clipboard_text(text: &str) -> Cow<'_, str> {
+  if text.contains('\0') {
+    Cow::Owned(text.replace('\0', ""))
+  } else {
+    Cow::Borrowed(text)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn nul_characters_are_dropped_not_the_text_after_them() {
+    assert_eq!(clipboard_text("# Title\0\n\nBody"), "# Title\n\nBody");
+    assert!(matches!(clipboard_text("plain"), Cow::Borrowed("plain")));
+  }
+}
@@ -0,0 +1,240 @@
+//! Code set in monospace fonts.
+//!
+//! Detection goes by font family alone: indentation and short lines are just
+//! as common in prose, but code samples are almost always typeset in a
+//! monospace face. Consecutive lines in such a font become a code block,
+//! keeping their line breaks, blank lines and indentation; monospace words
+//! inside ordinary text become inline code. A gap between lines that isn't
+//! a whole number of lines ends one block and starts the next.
+
+use std::ops::Range;
+
+use crate::extract::{TextLine, TextSpan};
+
+/// The most, relative to the font size, that the line pitch of code is
+/// taken to be, for blocks too short to tell.
+const MAX_PITCH: f32 = 1.4;
+
+/// How far, in lines, a gap may be off a whole number of them to count as
+/// blank lines.
+const PITCH_TOLERANCE: f32 = 0.25;
+
+/// The most blank lines kept in a row; a wider gap separates two blocks.
+const MAX_BLANK_LINES: usize = 2;
+
+/// Whether `font_name` contains one of `patterns`, ignoring case.
+pub fn is_monospace(font_name: &str, patterns: &[String]) -> bool {
+  let name = font_name.to_ascii_lowercase();
+  patterns
+    .iter()
+    .any(|pattern| !pattern.is_empty() && name.contains(&pattern.to_ascii_lowercase()))
+}
+
+/// Ranges of consecutive lines set entirely in a monospace font, split
+/// where the space between two lines isn't blank lines of code.
+pub fn find_code_blocks(lines: &[TextLine], patterns: &[String]) -> Vec<Range<usize>> {
+  let is_code = |line: &TextLine| {
+    line
+      .spans
+      .iter()
+      .filter(|span| !span.text.trim().is_empty())
+      .all(|span| is_monospace(&span.font_name, patterns))
+  };
+  let mut blocks: Vec<Range<usize>> = Vec::new();
+  for (i, line) in lines.iter().enumerate() {
+    if !is_code(line) {
+      continue;
+    }
+    match blocks.last_mut() {
+      Some(block) if block.end == i => block.end = i + 1,
+      _ => blocks.push(i..i + 1),
+    }
+  }
+  blocks
+    .into_iter()
+    .flat_map(|block| {
+      let pitch = pitch(&lines[block.clone()]);
+      let mut split = Vec::new();
+      let mut start = block.start;
+      for i in block.start + 1..block.end {
+        if blank_lines(&lines[i - 1], &lines[i], pitch).is_none() {
+          split.push(start..i);
+          start = i;
+        }
+      }
+      split.push(start..block.end);
+      split
+    })
+    .collect()
+}
+
+/// The distance between the baselines of consecutive lines of code: the
+/// smallest gap between them, but no more than [`MAX_PITCH`] lines.
+fn pitch(lines: &[TextLine]) -> f32 {
+  let size = lines.first().map_or(10.0, TextLine::font_size);
+  lines
+    .windows(2)
+    .map(|pair| pair[0].y() - pair[1].y())
+    .filter(|gap| *gap > 0.5 * size)
+    .fold(MAX_PITCH * size, f32::min)
+}
+
+/// How many blank lines of code the gap between `above` and `below` holds,
+/// or `None` if it is no whole number of lines, or too many.
+fn blank_lines(above: &TextLine, below: &TextLine, pitch: f32) -> Option<usize> {
+  let lines = (above.y() - below.y()) / pitch;
+  let whole = lines.round();
+  let blank = (whole as usize).saturating_sub(1);
+  ((lines - whole).abs() <= PITCH_TOLERANCE && blank <= MAX_BLANK_LINES).then_some(blank)
+}
+
+/// The text of monospace lines as a code block. Spans are placed at the
+/// column their position corresponds to, which restores indentation and
+/// alignment; indentation shared by every line is removed, and gaps between
+/// lines become blank lines.
+pub fn render_code_block(lines: &[TextLine]) -> String {
+  let (width, chars) = lines
+    .iter()
+    .flat_map(|line| &line.spans)
+    .fold((0.0, 0), |(width, chars), span| {
+      (width + span.width, chars + span.text.chars().count())
+    });
+  let fallback = lines.first().map_or(6.0, |line| 0.6 * line.font_size());
+  let char_width = if chars > 0 && width > 0.0 {
+    width / chars as f32
+  } else {
+    fallback
+  };
+  let left = lines
+    .iter()
+    .filter_map(|line| line.spans.first())
+    .map(|span| span.x)
+    .fold(f32::MAX, f32::min);
+
+  // Each line as (column of its first character, text).
+  let placed: Vec<(usize, String)> = lines
+    .iter()
+    .map(|line| {
+      let mut text = String::new();
+      let mut start = None;
+      for span in &line.spans {
+        let content = span.text.trim_start();
+        if content.is_empty() {
+          continue;
+        }
+        let leading = span.text.chars().count() - content.chars().count();
+        let mut column = ((span.x - left) / char_width).round().max(0.0) as usize;
+        if text.is_empty() {
+          // Spaces drawn at the start of a line are its indentation. Later in
+          // the line they may be separators extraction added at a gap.
+          column += leading;
+        }
+        let first = *start.get_or_insert(column);
+        let current = first + text.chars().count();
+        if column > current {
+          text.push_str(&" ".repeat(column - current));
+        } else if leading > 0 && !text.is_empty() && !text.ends_with(' ') {
+          text.push(' ');
+        }
+        text.push_str(content.trim_end());
+      }
+      (start.unwrap_or(0), text)
+    })
+    .collect();
+  let indent = placed
+    .iter()
+    .filter(|(_, text)| !text.is_empty())
+    .map(|(column, _)| *column)
+    .min()
+    .unwrap_or(0);
+
+  let pitch = pitch(lines);
+  let mut body: Vec<String> = Vec::with_capacity(placed.len());
+  for (i, (column, text)) in placed.into_iter().enumerate() {
+    if i > 0 {
+      let blank = blank_lines(&lines[i - 1], &lines[i], pitch).unwrap_or(0);
+      body.extend(std::iter::repeat(String::new()).take(blank));
+    }
+    body.push(format!(
+      "{}{text}",
+      " ".repeat(column.saturating_sub(indent))
+    ));
+  }
+  body.join("\n")
+}
+
+/// Whether every visible character of `span` is set in a monospace font.
+pub fn is_code_span(span: &TextSpan, patterns: &[String]) -> bool {
+  !span.text.trim().is_empty() && is_monospace(&span.font_name, patterns)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn patterns() -> Vec<String> {
+    vec!["courier".into(), "mono".into()]
+  }
+
+  fn line(y: f32, spans: &[(f32, &str, &str)]) -> TextLine {
+    TextLine {
+      spans: spans
+        .iter()
+        .map(|&(x, text, font)| TextSpan {
+          text: text.to_string(),
+          x,
+          y,
+          width: 6.0 * text.chars().count() as f32,
+          font_size: 10.0,
+          font_name: font.to_string(),
+          link: None,
+          style: Default::default(),
+          footnote: None,
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn groups_consecutive_monospace_lines() {
+    let lines = [
+      line(100.0, &[(72.0, "Run the following:", "Helvetica")]),
+      line(88.0, &[(72.0, "fn main() {", "Courier")]),
+      line(76.0, &[(84.0, "println!(\"hi\");", "Courier")]),
+      line(64.0, &[(72.0, "}", "Courier")]),
+      line(52.0, &[(96.0, "An indented sentence.", "Helvetica")]),
+    ];
+    let blocks = find_code_blocks(&lines, &patterns());
+    assert_eq!(blocks, vec![1..4]);
+    assert_eq!(
+      render_code_block(&lines[blocks[0].clone()]),
+      "fn main() {\n  println!(\"hi\");\n}"
+    );
+  }
+
+  #[test]
+  fn gaps_make_blank_lines_or_separate_blocks() {
+    let lines = [
+      line(200.0, &[(72.0, "import os", "Courier")]),
+      line(176.0, &[(72.0, "def main():", "Courier")]),
+      line(164.0, &[(84.0, "pass", "Courier")]),
+      // Space for prose, but with none in between.
+      line(146.0, &[(72.0, "$ python main.py", "Courier")]),
+      line(134.0, &[(72.0, "ok", "Courier")]),
+    ];
+    let blocks = find_code_blocks(&lines, &patterns());
+    assert_eq!(blocks, vec![0..3, 3..5]);
+    assert_eq!(
+      render_code_block(&lines[0..3]),
+      "import os\n\ndef main():\n  pass"
+    );
+  }
+
+  #[test]
+  fn font_patterns_are_case_insensitive() {
+    assert!(is_monospace("DejaVuSansMono-Bold", &patterns()));
+    assert!(is_monospace("Consolas", &["CONSOLAS".into()]));
+    assert!(!is_monospace("Helvetica", &patterns()));
+    assert!(!is_monospace("Helvetica", &["".into()]));
+  }
+}
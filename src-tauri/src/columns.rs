@@ -0,0 +1,338 @@
+//! Multi-column layout.
+//!
+//! Lines are grouped by baseline across the whole page, so on a two-column
+//! page each line holds text from both columns. A column gutter shows up as a
+//! vertical strip that the text of most lines leaves empty, with prose on both
+//! sides. Lines that cross the gutter (titles, abstracts, wide captions),
+//! images that span it and wide gaps across all columns cut the page into
+//! bands; within each band the left column is read top to bottom before the
+//! one to its right.
+
+use crate::extract::{ImagePlacement, TextLine, TextSpan};
+
+/// Pages with fewer lines than this are read as they are.
+const MIN_LINES: usize = 8;
+
+/// Each side of a gutter needs at least this many lines of text.
+const MIN_COLUMN_LINES: usize = 4;
+
+/// Median characters per line a column needs; shorter is table cells or
+/// margin notes, not running text.
+const MIN_COLUMN_CHARS: usize = 12;
+
+/// Gutters are searched for between these fractions of the text's width.
+const GUTTER_REGION: (f32, f32) = (0.2, 0.8);
+
+/// A gutter must be at least this wide, relative to the median font size.
+const MIN_GUTTER: f32 = 1.0;
+
+/// Most lines must stay clear of a gutter; at most this share may cross it.
+const MAX_CROSSING_SHARE: f32 = 0.5;
+
+/// A gap between lines wider than this, relative to the font size, across
+/// all columns at once separates bands.
+const MAX_BAND_GAP: f32 = 2.5;
+
+/// At most this share of a column's lines may look like table rows.
+const MAX_TABULAR_SHARE: f32 = 0.2;
+
+/// A vertical strip of the page, in page space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Gutter {
+  left: f32,
+  right: f32,
+}
+
+impl Gutter {
+  fn crosses(&self, span: &TextSpan) -> bool {
+    span.x < self.right && span.right() > self.left
+  }
+}
+
+/// `lines` (ordered top to bottom) in reading order: unchanged on a
+/// single-column page, otherwise split at the column gutters and arranged
+/// column by column within each band between full-width lines and images.
+pub fn reading_order(lines: &[TextLine], images: &[ImagePlacement]) -> Vec<TextLine> {
+  let Some(gutter) = find_gutter(lines) else {
+    return lines.to_vec();
+  };
+  // Images spanning the gutter cut the page at their top and bottom edges.
+  let mut cuts: Vec<f32> = images
+    .iter()
+    .filter(|image| image.left < gutter.left && image.right > gutter.right)
+    .flat_map(|image| [image.y, image.bottom])
+    .collect();
+  cuts.sort_by(|a, b| b.total_cmp(a));
+  let mut cuts = cuts.into_iter().peekable();
+
+  let mut out = Vec::with_capacity(lines.len());
+  let mut band: Vec<&TextLine> = Vec::new();
+  for line in lines {
+    // Both columns leaving a wide gap at once, as under an author block, is
+    // also where one band ends and the next begins.
+    let mut cut = band.last().is_some_and(|last: &&TextLine| {
+      last.y() - line.y() > MAX_BAND_GAP * last.font_size().max(line.font_size())
+    });
+    while cuts.next_if(|&y| y > line.y()).is_some() {
+      cut = true;
+    }
+    let crossing = line.spans.iter().any(|span| gutter.crosses(span));
+    if cut || crossing {
+      read_band(&band, gutter, images, &mut out);
+      band.clear();
+    }
+    if crossing {
+      out.push(line.clone());
+    } else {
+      band.push(line);
+    }
+  }
+  read_band(&band, gutter, images, &mut out);
+  out
+}
+
+/// Appends the lines of a band left column first. Each column may itself be
+/// split further, for layouts with more than two.
+fn read_band(
+  band: &[&TextLine],
+  gutter: Gutter,
+  images: &[ImagePlacement],
+  out: &mut Vec<TextLine>,
+) {
+  let (mut left, mut right) = (Vec::new(), Vec::new());
+  for line in band {
+    let (l, r): (Vec<TextSpan>, Vec<TextSpan>) = line
+      .spans
+      .iter()
+      .cloned()
+      .partition(|span| span.right() <= gutter.left);
+    if !l.is_empty() {
+      left.push(TextLine { spans: l });
+    }
+    if !r.is_empty() {
+      right.push(TextLine { spans: r });
+    }
+  }
+  out.extend(reading_order(&left, images));
+  out.extend(reading_order(&right, images));
+}
+
+/// The widest strip in the middle of the text that the fewest lines cross,
+/// if it separates two columns of running text.
+fn find_gutter(lines: &[TextLine]) -> Option<Gutter> {
+  if lines.len() < MIN_LINES {
+    return None;
+  }
+  let spans = || lines.iter().flat_map(|line| &line.spans);
+  let start = spans().map(|span| span.x).fold(f32::MAX, f32::min);
+  let end = spans().map(TextSpan::right).fold(f32::MIN, f32::max);
+  let width = end - start;
+  if width <= 0.0 {
+    return None;
+  }
+
+  // How many lines cover each point-wide strip.
+  let bins = width.ceil() as usize;
+  let mut coverage = vec![0usize; bins];
+  let mut covered = vec![false; bins];
+  for line in lines {
+    covered.fill(false);
+    for span in &line.spans {
+      let from = ((span.x - start).floor().max(0.0) as usize).min(bins);
+      let to = ((span.right() - start).ceil() as usize).min(bins);
+      covered[from..to].fill(true);
+    }
+    for (count, &covered) in coverage.iter_mut().zip(&covered) {
+      *count += usize::from(covered);
+    }
+  }
+
+  let region = (width * GUTTER_REGION.0) as usize..(width * GUTTER_REGION.1) as usize;
+  let fewest = *coverage[region.clone()].iter().min()?;
+  // The widest run of bins at that minimum.
+  let mut best: Option<(usize, usize)> = None;
+  let mut run_start = None;
+  // A trailing `None` closes a run that reaches the end of the region.
+  let counts = coverage[region.clone()].iter().map(Some).chain([None]);
+  for (bin, count) in (region.start..).zip(counts) {
+    match (count == Some(&fewest), run_start) {
+      (true, None) => run_start = Some(bin),
+      (false, Some(from)) => {
+        if best.map_or(true, |(a, b)| bin - from > b - a) {
+          best = Some((from, bin));
+        }
+        run_start = None;
+      }
+      _ => {}
+    }
+  }
+  let (from, to) = best?;
+  let gutter = Gutter {
+    left: start + from as f32,
+    right: start + to as f32,
+  };
+
+  let mut sizes: Vec<f32> = spans().map(|span| span.font_size).collect();
+  sizes.sort_by(f32::total_cmp);
+  if gutter.right - gutter.left < MIN_GUTTER * sizes[sizes.len() / 2] {
+    return None;
+  }
+
+  let mut crossing = 0;
+  let (mut left, mut right) = (Vec::new(), Vec::new());
+  for line in lines {
+    if line.spans.iter().any(|span| gutter.crosses(span)) {
+      crossing += 1;
+      continue;
+    }
+    let (l, r): (Vec<&TextSpan>, Vec<&TextSpan>) = line
+      .spans
+      .iter()
+      .partition(|span| span.right() <= gutter.left);
+    for (side, spans) in [(&mut left, l), (&mut right, r)] {
+      if !spans.is_empty() {
+        side.push(Part::of(&spans));
+      }
+    }
+  }
+  let few_crossing = crossing as f32 <= MAX_CROSSING_SHARE * lines.len() as f32;
+  (few_crossing && is_prose(&mut left) && is_prose(&mut right)).then_some(gutter)
+}
+
+/// The text of one line on one side of a gutter.
+struct Part {
+  chars: usize,
+  /// Whether it has cell-sized gaps, like a table row.
+  tabular: bool,
+}
+
+impl Part {
+  fn of(spans: &[&TextSpan]) -> Part {
+    Part {
+      chars: spans
+        .iter()
+        .map(|span| span.text.trim().chars().count())
+        .sum(),
+      tabular: spans
+        .windows(2)
+        .any(|w| w[1].x - w[0].right() > MIN_GUTTER * w[1].font_size),
+    }
+  }
+}
+
+/// Whether the parts on one side of a gutter read like a column of text:
+/// enough lines, long enough, and not laid out in cells.
+fn is_prose(parts: &mut [Part]) -> bool {
+  if parts.len() < MIN_COLUMN_LINES {
+    return false;
+  }
+  parts.sort_unstable_by_key(|part| part.chars);
+  let tabular = parts.iter().filter(|part| part.tabular).count();
+  parts[parts.len() / 2].chars >= MIN_COLUMN_CHARS
+    && tabular as f32 <= MAX_TABULAR_SHARE * parts.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn span(x: f32, y: f32, text: &str) -> TextSpan {
+    TextSpan {
+      text: text.to_string(),
+      x,
+      y,
+      width: 5.0 * text.len() as f32,
+      font_size: 10.0,
+      font_name: "Times-Roman".into(),
+      link: None,
+      style: Default::default(),
+      footnote: None,
+    }
+  }
+
+  fn texts(lines: &[TextLine]) -> Vec<String> {
+    lines.iter().map(TextLine::text).collect()
+  }
+
+  /// `rows` lines of two columns, starting at `y`.
+  fn two_columns(y: f32, rows: usize, tag: &str) -> Vec<TextLine> {
+    (0..rows)
+      .map(|i| {
+        let y = y - 12.0 * i as f32;
+        TextLine {
+          spans: vec![
+            span(72.0, y, &format!("left {tag}{i} of the column")),
+            span(320.0, y, &format!("right {tag}{i} of the column")),
+          ],
+        }
+      })
+      .collect()
+  }
+
+  #[test]
+  fn reads_each_column_before_the_next() {
+    let mut lines = vec![TextLine {
+      spans: vec![span(
+        72.0,
+        720.0,
+        "A title that runs across both of the columns of the page, and then on some more",
+      )],
+    }];
+    lines.extend(two_columns(700.0, 8, ""));
+    assert_eq!(
+      texts(&reading_order(&lines, &[])),
+      [lines[0].text()]
+        .into_iter()
+        .chain((0..8).map(|i| format!("left {i} of the column")))
+        .chain((0..8).map(|i| format!("right {i} of the column")))
+        .collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn a_spanning_figure_separates_bands() {
+    let mut lines = two_columns(700.0, 4, "a");
+    lines.extend(two_columns(500.0, 4, "b"));
+    let figure = ImagePlacement {
+      id: (1, 0),
+      y: 640.0,
+      bottom: 520.0,
+      left: 72.0,
+      right: 540.0,
+    };
+    let order = texts(&reading_order(&lines, &[figure]));
+    let expected: Vec<String> = ["a", "b"]
+      .iter()
+      .flat_map(|tag| {
+        let side =
+          |side: &'static str| (0..4).map(move |i| format!("{side} {tag}{i} of the column"));
+        side("left").chain(side("right"))
+      })
+      .collect();
+    assert_eq!(order, expected);
+  }
+
+  #[test]
+  fn single_column_and_tables_are_left_alone() {
+    let prose: Vec<TextLine> = (0..10)
+      .map(|i| TextLine {
+        spans: vec![span(
+          72.0,
+          700.0 - 12.0 * i as f32,
+          "a line of ordinary running text across the page",
+        )],
+      })
+      .collect();
+    assert_eq!(texts(&reading_order(&prose, &[])), texts(&prose));
+
+    let table: Vec<TextLine> = (0..10)
+      .map(|i| {
+        let y = 700.0 - 12.0 * i as f32;
+        TextLine {
+          spans: vec![span(72.0, y, "Item"), span(320.0, y, "12.5")],
+        }
+      })
+      .collect();
+    assert_eq!(texts(&reading_order(&table, &[])), texts(&table));
+  }
+}
@@ -0,0 +1,915 @@
+//! PDF conversion commands.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use lopdf::encryption::DecryptionError;
+use lopdf::Document;
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::boilerplate::{self, Boilerplate};
+use crate::cache::{self, Cache};
+use crate::document::{self, Block, Inline, OutputFormat, PageWriter};
+use crate::error::ConversionError;
+use crate::extract::{self, PageText, TextLine, TextSpan};
+use crate::jobs::{CancelToken, ConversionRegistry};
+use crate::options::ConvertOptions;
+use crate::output::{self, OutputFile, WriteMode, WriteOutcome};
+use crate::words::Vocabulary;
+use crate::{
+  code, columns, footnotes, frontmatter, headings, inline, links, lists, math, outline, quotes,
+  ranges, settings, tables, toc,
+};
+
+/// A converted PDF and the anchors of its headings, for an outline that
+/// links into it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversion {
+  pub output: String,
+  pub headings: Vec<toc::Heading>,
+}
+
+impl Conversion {
+  fn new(document: &document::Document, format: OutputFormat) -> Self {
+    Conversion {
+      output: document.render(format),
+      headings: toc::document_headings(&document.pages),
+    }
+  }
+}
+
+/// Converts the PDF at `path` and returns it rendered as `format`, Markdown
+/// by default, with its headings and their anchors.
+///
+/// Passing a `job_id` lets the frontend stop the conversion with
+/// `cancel_conversion`; without one, the id is made up and sent in the first
+/// `conversion-progress` event. Events carrying the same id follow the
+/// conversion page by page. Results are cached until the file changes.
+///
+/// `password` opens encrypted PDFs. It is never logged, and conversions that
+/// need one are not cached, so their text isn't left on disk.
+#[tauri::command]
+pub async fn convert_pdf_to_markdown(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  password: Option<String>,
+  job_id: Option<String>,
+) -> Result<Conversion, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let job = registry.start(job_id).report_progress(&app);
+  let cache = Cache::open(&app).filter(|_| password.is_none());
+  tauri::async_runtime::spawn_blocking(move || {
+    let path = Path::new(&path);
+    cache::cached(cache.as_ref(), path, &("document", &options), || {
+      convert_file(path, password.as_deref(), &options, job.token())
+    })
+    .map(|output| Conversion::new(&output, format))
+  })
+  .await?
+}
+
+/// Converts only the pages selected by `ranges`, e.g. `"1-3,7,10-12"`.
+///
+/// Pages are numbered from 1; an empty spec converts every page. Returns
+/// what `convert_pdf_to_markdown` does.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn convert_pdf_pages(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  ranges: String,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  password: Option<String>,
+  job_id: Option<String>,
+) -> Result<Conversion, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let job = registry.start(job_id).report_progress(&app);
+  let cache = Cache::open(&app).filter(|_| password.is_none());
+  tauri::async_runtime::spawn_blocking(move || {
+    let parsed = ranges::parse_ranges(&ranges)?;
+    let path = Path::new(&path);
+    cache::cached(cache.as_ref(), path, &("pages", &ranges, &options), || {
+      convert_pages(path, &parsed, password.as_deref(), &options, job.token())
+    })
+    .map(|output| Conversion::new(&output, format))
+  })
+  .await?
+}
+
+/// The first pages of a PDF, rendered, and how many pages it has in all.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preview {
+  pub output: String,
+  pub pages_shown: usize,
+  pub total_pages: usize,
+}
+
+/// Converts just the first `pages` pages, for a quick look at a large PDF.
+/// The rest of the document is never extracted. Previews aren't cached.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn preview_pdf(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  pages: usize,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  password: Option<String>,
+  job_id: Option<String>,
+) -> Result<Preview, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let job = registry.start(job_id).report_progress(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let (output, total_pages) = preview(
+      Path::new(&path),
+      pages,
+      password.as_deref(),
+      &options,
+      job.token(),
+    )?;
+    Ok(Preview {
+      output: output.render(format),
+      pages_shown: output.pages.len(),
+      total_pages,
+    })
+  })
+  .await?
+}
+
+/// Converts the PDF at `path` straight into the file at `out_path`, writing
+/// each page as soon as it is rendered. Meant for PDFs too long to convert in
+/// memory: only a page of text is held at a time, though the parsed PDF itself
+/// stays loaded. Nothing is cached. Without `out_path`, the file goes next to
+/// the PDF or into the output directory of the settings.
+///
+/// The output appears at `out_path` only once the conversion has succeeded.
+/// If a file is there already, `write_mode` says whether to replace it, leave
+/// it and skip the conversion, or write next to it under a new name.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn convert_pdf_to_file(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  out_path: Option<String>,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  password: Option<String>,
+  job_id: Option<String>,
+  write_mode: Option<WriteMode>,
+) -> Result<OutputFile, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let out_path = out_path.map_or_else(
+    || {
+      let source = Path::new(&path);
+      let out_dir = settings::load_settings(app.clone()).output_dir;
+      output::converted_path(
+        source,
+        source.parent().unwrap_or(Path::new("")),
+        out_dir.as_deref().map(Path::new),
+        format.extension(),
+      )
+    },
+    PathBuf::from,
+  );
+  let job = registry.start(job_id).report_progress(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let out_path = out_path.as_path();
+    let Some((target, outcome)) = output::destination(out_path, write_mode.unwrap_or_default())
+    else {
+      return Ok(OutputFile::new(out_path, WriteOutcome::Skipped));
+    };
+    convert_to_file(
+      Path::new(&path),
+      &target,
+      password.as_deref(),
+      &options,
+      format,
+      job.token(),
+    )?;
+    Ok(OutputFile::new(&target, outcome))
+  })
+  .await?
+}
+
+/// Converts the PDF at `path` into one file per page in `out_dir`, named
+/// `<name>-page-<n>` with the page number padded so that the files sort in
+/// page order. Returns the files, each with whether it was written, skipped
+/// or renamed as `write_mode` says.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn convert_pdf_to_page_files(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  out_dir: String,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  password: Option<String>,
+  job_id: Option<String>,
+  write_mode: Option<WriteMode>,
+) -> Result<Vec<OutputFile>, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let job = registry.start(job_id).report_progress(&app);
+  let cache = Cache::open(&app).filter(|_| password.is_none());
+  tauri::async_runtime::spawn_blocking(move || {
+    let path = Path::new(&path);
+    let document = cache::cached(cache.as_ref(), path, &("document", &options), || {
+      convert_file(path, password.as_deref(), &options, job.token())
+    })?;
+    let written = write_page_files(
+      &document,
+      path,
+      Path::new(&out_dir),
+      format,
+      write_mode.unwrap_or_default(),
+    )?;
+    Ok(
+      written
+        .iter()
+        .map(|(file, outcome)| OutputFile::new(file, *outcome))
+        .collect(),
+    )
+  })
+  .await?
+}
+
+pub fn convert_file(
+  path: &Path,
+  password: Option<&str>,
+  options: &ConvertOptions,
+  cancel: &CancelToken,
+) -> Result<document::Document, ConversionError> {
+  convert_pages(path, &[], password, options, cancel)
+}
+
+pub fn convert_pages(
+  path: &Path,
+  ranges: &[RangeInclusive<usize>],
+  password: Option<&str>,
+  options: &ConvertOptions,
+  cancel: &CancelToken,
+) -> Result<document::Document, ConversionError> {
+  let doc = load_document(path, password)?;
+  let numbers = ranges::select_pages(ranges, doc.get_pages().len())?;
+  convert_document(&doc, numbers, options, cancel)
+}
+
+/// Converts the first `pages` pages (at least one) of the PDF at `path`.
+/// Also returns the document's total page count.
+pub fn preview(
+  path: &Path,
+  pages: usize,
+  password: Option<&str>,
+  options: &ConvertOptions,
+  cancel: &CancelToken,
+) -> Result<(document::Document, usize), ConversionError> {
+  let doc = load_document(path, password)?;
+  let total = doc.get_pages().len();
+  let numbers = (1..=pages.max(1).min(total)).collect();
+  Ok((convert_document(&doc, numbers, options, cancel)?, total))
+}
+
+/// Converts the PDF at `path` into `out` page by page, returning `out` once
+/// the document is complete.
+///
+/// Pages are extracted twice: once to find the body font size that headings
+/// are measured against, the running headers and footers and the words used,
+/// and again to render them. A table of contents, or links from one page to
+/// another, take a pass more, in between, to find the headings they link to.
+pub fn convert_to_writer<W: Write>(
+  path: &Path,
+  password: Option<&str>,
+  options: &ConvertOptions,
+  format: OutputFormat,
+  out: W,
+  cancel: &CancelToken,
+) -> Result<W, ConversionError> {
+  let doc = load_document(path, password)?;
+  let count = doc.get_pages().len() as u32;
+  let find_headings =
+    options.generate_toc || (options.preserve_links && links::has_page_links(&doc));
+  cancel.expect_pages(count as usize, if find_headings { 3 } else { 2 });
+  let mut sizes = headings::FontSizes::default();
+  let mut boilerplate = Boilerplate::default();
+  let mut words = Vocabulary::default();
+  for number in 1..=count {
+    for (_, page) in extract_pages(&doc, [number], cancel)? {
+      sizes.add(&page);
+      boilerplate.add(&page);
+      words.add(&page);
+    }
+  }
+  let body_size = sizes.body_size();
+  let render = |number| -> Result<Vec<document::Page>, ConversionError> {
+    let mut pages = Vec::new();
+    for (number, mut page) in extract_pages(&doc, [number], cancel)? {
+      if options.strip_boilerplate {
+        boilerplate.strip(&mut page, options.boilerplate_threshold);
+      }
+      pages.push(render_page(
+        number,
+        &page,
+        Vec::new(),
+        options,
+        body_size,
+        &words,
+      ));
+    }
+    Ok(pages)
+  };
+
+  let mut headings = Vec::new();
+  if find_headings {
+    let mut anchors = toc::Anchors::default();
+    for number in 1..=count {
+      for page in render(number)? {
+        headings.extend(toc::headings(&page, &mut anchors));
+      }
+    }
+  }
+  let mut toc = None;
+  if options.generate_toc {
+    let numbers: Vec<u32> = (1..=count).collect();
+    toc = toc::generate(outline::bookmarks(&doc), &headings, &numbers);
+  }
+
+  let mut writer = PageWriter::new(
+    out,
+    frontmatter::metadata(&doc, options),
+    options.page_separator.clone(),
+    options.tight_output,
+    format,
+  )?;
+  let mut numbering = footnotes::Numbering::default();
+  for number in 1..=count {
+    for mut page in render(number)? {
+      numbering.page(&mut page);
+      toc::link_headings(std::slice::from_mut(&mut page), &headings);
+      if let Some(toc) = toc.take() {
+        page.blocks.insert(0, toc);
+      }
+      writer.page(&page)?;
+    }
+  }
+  Ok(writer.finish()?)
+}
+
+/// Streams the conversion into a temporary file next to `out_path`, moving it
+/// into place when done and removing it if the conversion fails.
+pub fn convert_to_file(
+  path: &Path,
+  out_path: &Path,
+  password: Option<&str>,
+  options: &ConvertOptions,
+  format: OutputFormat,
+  cancel: &CancelToken,
+) -> Result<(), ConversionError> {
+  let mut partial = out_path.as_os_str().to_owned();
+  partial.push(".part");
+  let partial = Path::new(&partial);
+  let file = File::create(partial)
+    .map_err(|err| ConversionError::io(format!("cannot create {}", partial.display()), err))?;
+  let result = convert_to_writer(
+    path,
+    password,
+    options,
+    format,
+    BufWriter::new(file),
+    cancel,
+  )
+  .and_then(|_| {
+    fs::rename(partial, out_path)
+      .map_err(|err| ConversionError::io(format!("cannot write {}", out_path.display()), err))
+  });
+  if result.is_err() {
+    let _ = fs::remove_file(partial);
+  }
+  result
+}
+
+/// Writes each page of `document`, converted from `source`, to a file of its
+/// own in `out_dir`, creating the directory if need be.
+pub fn write_page_files(
+  document: &document::Document,
+  source: &Path,
+  out_dir: &Path,
+  format: OutputFormat,
+  write_mode: WriteMode,
+) -> Result<Vec<(PathBuf, WriteOutcome)>, ConversionError> {
+  fs::create_dir_all(out_dir)
+    .map_err(|e| ConversionError::io(format_args!("cannot create {}", out_dir.display()), e))?;
+  let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+  let last = document
+    .pages
+    .iter()
+    .map(|page| page.number)
+    .max()
+    .unwrap_or(0);
+  let width = last.to_string().len();
+  let mut written = Vec::with_capacity(document.pages.len());
+  for page in document.split_pages() {
+    let number = page.pages[0].number;
+    let file = out_dir.join(format!(
+      "{stem}-page-{number:0width$}.{}",
+      format.extension()
+    ));
+    let file = output::write(&file, page.render(format).as_bytes(), write_mode)
+      .map_err(|e| ConversionError::io(format_args!("cannot write {}", file.display()), e))?;
+    written.push(file);
+  }
+  Ok(written)
+}
+
+pub fn convert_document(
+  doc: &Document,
+  numbers: Vec<usize>,
+  options: &ConvertOptions,
+  cancel: &CancelToken,
+) -> Result<document::Document, ConversionError> {
+  cancel.expect_pages(numbers.len(), 1);
+  let mut pages = extract_pages(doc, numbers.into_iter().map(|n| n as u32), cancel)?;
+  let body_size = headings::body_font_size(pages.iter().map(|(_, page)| page));
+  let words = Vocabulary::of(pages.iter().map(|(_, page)| page));
+  if options.strip_boilerplate {
+    boilerplate::strip(&mut pages, options.boilerplate_threshold);
+  }
+  let mut rendered: Vec<document::Page> = pages
+    .iter()
+    .map(|(number, page)| render_page(*number, page, Vec::new(), options, body_size, &words))
+    .collect();
+  footnotes::number(&mut rendered);
+  let headings = toc::document_headings(&rendered);
+  toc::link_headings(&mut rendered, &headings);
+  if options.generate_toc {
+    toc::prepend(outline::bookmarks(doc), &mut rendered);
+  }
+  Ok(document::Document {
+    metadata: frontmatter::metadata(doc, options),
+    pages: rendered,
+    page_separator: options.page_separator.clone(),
+    tight_output: options.tight_output,
+  })
+}
+
+/// Extracts the given one-based pages, checking for cancellation before each
+/// and reporting it done after.
+pub fn extract_pages(
+  doc: &Document,
+  numbers: impl IntoIterator<Item = u32>,
+  cancel: &CancelToken,
+) -> Result<Vec<(u32, PageText)>, ConversionError> {
+  let page_ids = doc.get_pages();
+  numbers
+    .into_iter()
+    .map(|number| {
+      cancel.check()?;
+      let page_id = page_ids
+        .get(&number)
+        .ok_or_else(|| ConversionError::InvalidPageRange(format!("no page {number}")))?;
+      let page = extract::extract_page(doc, *page_id)
+        .map_err(|e| ConversionError::InvalidPdf(format!("page {number}: {e}")))?;
+      cancel.page_done(number);
+      Ok((number, page))
+    })
+    .collect()
+}
+
+/// Loads and, if it is encrypted, decrypts the PDF at `path`.
+pub fn load_document(path: &Path, password: Option<&str>) -> Result<Document, ConversionError> {
+  let mut doc = read_document(path)?;
+  // PDFs that open with an empty password are already decrypted by now.
+  if doc.is_encrypted() {
+    let password = password.ok_or(ConversionError::PasswordRequired)?;
+    doc.decrypt(password).map_err(|err| match err {
+      lopdf::Error::Decryption(
+        DecryptionError::IncorrectPassword | DecryptionError::StringPrep(_),
+      ) => ConversionError::WrongPassword,
+      err => ConversionError::InvalidPdf(format!("cannot decrypt: {err}")),
+    })?;
+  }
+  Ok(doc)
+}
+
+/// Loads the PDF at `path` without decrypting it, unless it opens with an
+/// empty password.
+pub fn read_document(path: &Path) -> Result<Document, ConversionError> {
+  if !path.is_file() {
+    return Err(ConversionError::FileNotFound(path.to_path_buf()));
+  }
+  let bytes = fs::read(path)?;
+  // The header may be preceded by junk, but readers only look at the first KiB.
+  let head = &bytes[..bytes.len().min(1024)];
+  if !head.windows(5).any(|w| w == b"%PDF-") {
+    return Err(ConversionError::InvalidPdf("missing %PDF header".into()));
+  }
+  Ok(Document::load_mem(&bytes)?)
+}
+
+/// A block anchored at a vertical position on the page, such as an image, to
+/// be woven in between the paragraphs.
+pub struct Figure {
+  /// Top edge, in page space.
+  pub y: f32,
+  pub block: Block,
+}
+
+/// Lays page `number` out as blocks (headings, paragraphs, tables and code),
+/// placing each figure before the first block that starts below it, with its
+/// footnotes set apart. `body_size` is the document's body font size, which
+/// headings are measured against, and `words` its vocabulary, for joining
+/// hyphenated words.
+pub fn render_page(
+  number: u32,
+  page: &PageText,
+  mut figures: Vec<Figure>,
+  options: &ConvertOptions,
+  body_size: f32,
+  words: &Vocabulary,
+) -> document::Page {
+  let mut lines = if options.detect_columns {
+    columns::reading_order(&page.lines, &page.images)
+  } else {
+    page.lines.clone()
+  };
+  let notes = if options.convert_footnotes {
+    footnotes::split(&mut lines, body_size)
+  } else {
+    Vec::new()
+  };
+  // Each block with the top edge of its first line.
+  let mut blocks: Vec<(f32, Block)> = Vec::new();
+  let mut next = 0;
+  for block in code::find_code_blocks(&lines, &options.monospace_fonts) {
+    render_text(
+      &lines[next..block.start],
+      options,
+      body_size,
+      words,
+      &mut blocks,
+    );
+    let code = &lines[block.clone()];
+    blocks.push((top(code), Block::Code(code::render_code_block(code))));
+    next = block.end;
+  }
+  render_text(&lines[next..], options, body_size, words, &mut blocks);
+
+  figures.sort_by(|a, b| b.y.total_cmp(&a.y));
+  let mut figures = figures.into_iter().peekable();
+  let mut out = Vec::new();
+  for (top, block) in blocks {
+    while let Some(figure) = figures.next_if(|f| f.y >= top) {
+      out.push(figure.block);
+    }
+    out.push(block);
+  }
+  out.extend(figures.map(|f| f.block));
+
+  // A reference can end up where inline content isn't kept, in a table
+  // cell say; its note is left in the text then, rather than lost.
+  let referenced = footnotes::referenced(&mut out);
+  let mut notes_kept = Vec::new();
+  for note in notes {
+    let content = inline::inlines(&note.lines, options, words);
+    if referenced.contains(&note.mark) {
+      notes_kept.push(document::Footnote {
+        label: note.mark,
+        content,
+      });
+    } else {
+      let mut paragraph = vec![Inline::Text(format!("{} ", note.mark))];
+      paragraph.extend(content);
+      out.push(Block::Paragraph(paragraph));
+    }
+  }
+  document::Page {
+    number,
+    blocks: out,
+    footnotes: notes_kept,
+  }
+}
+
+/// The top edge of the first line.
+fn top(lines: &[TextLine]) -> f32 {
+  lines[0].y() + lines[0].font_size()
+}
+
+/// Renders lines other than code as tables, lists and paragraphs, appending
+/// them to `blocks`.
+fn render_text(
+  lines: &[TextLine],
+  options: &ConvertOptions,
+  body_size: f32,
+  words: &Vocabulary,
+  blocks: &mut Vec<(f32, Block)>,
+) {
+  let tables = if options.detect_tables {
+    tables::find_tables(lines, options.table_tolerance)
+  } else {
+    Vec::new()
+  };
+  let mut next = 0;
+  for table in tables {
+    render_prose(
+      &lines[next..table.lines.start],
+      options,
+      body_size,
+      words,
+      blocks,
+    );
+    blocks.push((top(&lines[table.lines.start..]), table.block));
+    next = table.lines.end;
+  }
+  render_prose(&lines[next..], options, body_size, words, blocks);
+}
+
+/// Renders lines with no tables or code as equations, lists and paragraphs.
+fn render_prose(
+  lines: &[TextLine],
+  options: &ConvertOptions,
+  body_size: f32,
+  words: &Vocabulary,
+  blocks: &mut Vec<(f32, Block)>,
+) {
+  let equations = if options.detect_math {
+    math::find_display_math(lines)
+  } else {
+    Vec::new()
+  };
+  let mut next = 0;
+  for equation in equations {
+    render_lists(
+      &lines[next..equation.lines.start],
+      options,
+      body_size,
+      words,
+      blocks,
+    );
+    blocks.push((top(&lines[equation.lines.start..]), equation.block));
+    next = equation.lines.end;
+  }
+  render_lists(&lines[next..], options, body_size, words, blocks);
+}
+
+fn render_lists(
+  lines: &[TextLine],
+  options: &ConvertOptions,
+  body_size: f32,
+  words: &Vocabulary,
+  blocks: &mut Vec<(f32, Block)>,
+) {
+  let lists = if options.detect_lists {
+    lists::find_lists(lines, options, body_size, words)
+  } else {
+    Vec::new()
+  };
+  let mut next = 0;
+  let mut before = None;
+  for list in lists {
+    render_paragraphs(
+      &lines[next..list.lines.start],
+      before,
+      options,
+      body_size,
+      words,
+      blocks,
+    );
+    blocks.push((top(&lines[list.lines.start..]), list.block));
+    before = Some(&lines[list.lines.clone()]);
+    next = list.lines.end;
+  }
+  render_paragraphs(&lines[next..], before, options, body_size, words, blocks);
+}
+
+/// Renders lines of nothing but paragraphs, those that are quoted as
+/// blockquotes. `list` holds the lines of the list they follow, if any.
+fn render_paragraphs(
+  lines: &[TextLine],
+  list: Option<&[TextLine]>,
+  options: &ConvertOptions,
+  body_size: f32,
+  words: &Vocabulary,
+  blocks: &mut Vec<(f32, Block)>,
+) {
+  let paragraphs = paragraphs(lines);
+  let depths = if options.detect_blockquotes {
+    quotes::depths(&paragraphs, list, options, body_size)
+  } else {
+    vec![0; paragraphs.len()]
+  };
+  // The quoted paragraphs in a row, and the top of the first.
+  let mut quoted = Vec::new();
+  let mut quote_top = 0.0;
+  for (paragraph, depth) in paragraphs.into_iter().zip(depths) {
+    let block = render_paragraph(paragraph, options, body_size, words);
+    if depth == 0 {
+      if !quoted.is_empty() {
+        blocks.push((quote_top, quotes::quote(std::mem::take(&mut quoted))));
+      }
+      blocks.push((top(paragraph), block));
+    } else {
+      if quoted.is_empty() {
+        quote_top = top(paragraph);
+      }
+      quoted.push((depth, block));
+    }
+  }
+  if !quoted.is_empty() {
+    blocks.push((quote_top, quotes::quote(quoted)));
+  }
+}
+
+fn render_paragraph(
+  lines: &[TextLine],
+  options: &ConvertOptions,
+  body_size: f32,
+  words: &Vocabulary,
+) -> Block {
+  let content = inline::inlines(lines, options, words);
+  match headings::heading_level(lines, body_size, options.heading_sensitivity) {
+    Some(level) => Block::Heading {
+      level,
+      content: inline::heading(content),
+    },
+    None => Block::Paragraph(content),
+  }
+}
+
+/// Splits lines into paragraphs wherever the vertical gap is clearly larger
+/// than the usual line spacing (of the page, or of the font size for large
+/// text such as titles), or the font size or weight changes.
+fn paragraphs(lines: &[TextLine]) -> Vec<&[TextLine]> {
+  let spacing = line_spacing(lines);
+  let mut out = Vec::new();
+  let mut start = 0;
+  for i in 1..lines.len() {
+    let (prev, line) = (&lines[i - 1], &lines[i]);
+    let gap = prev.y() - line.y();
+    let size = prev.font_size().max(line.font_size());
+    let size_changed = (prev.font_size() - line.font_size()).abs() > 0.2 * size;
+    let limit = spacing.unwrap_or(0.0).max(size * 1.2) * 1.5;
+    // A bold line followed by regular text is usually a run-in heading.
+    let bold = |line: &TextLine| line.spans.iter().all(TextSpan::is_bold);
+    let weight_changed = bold(prev) != bold(line);
+    if gap > limit || gap < 0.0 || size_changed || weight_changed {
+      out.push(&lines[start..i]);
+      start = i;
+    }
+  }
+  if start < lines.len() {
+    out.push(&lines[start..]);
+  }
+  out
+}
+
+/// The median baseline distance between consecutive lines, if there are any.
+fn line_spacing(lines: &[TextLine]) -> Option<f32> {
+  let mut gaps: Vec<f32> = lines
+    .windows(2)
+    .map(|w| w[0].y() - w[1].y())
+    .filter(|gap| *gap > 0.0)
+    .collect();
+  if gaps.is_empty() {
+    return None;
+  }
+  gaps.sort_by(f32::total_cmp);
+  Some(gaps[gaps.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use lopdf::encryption::{EncryptionState, EncryptionVersion, Permissions};
+  use lopdf::{dictionary, Object};
+
+  fn write_pdf(name: &str, user_password: Option<&str>) -> PathBuf {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+      "Type" => "Page",
+      "Parent" => pages_id,
+      "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+    });
+    doc.objects.insert(
+      pages_id,
+      Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![page_id.into()],
+        "Count" => 1,
+      }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    if let Some(user_password) = user_password {
+      let id = Object::string_literal("0123456789abcdef");
+      doc.trailer.set("ID", vec![id.clone(), id]);
+      let version = EncryptionVersion::V2 {
+        document: &doc,
+        owner_password: "owner",
+        user_password,
+        key_length: 128,
+        permissions: Permissions::all(),
+      };
+      let state = EncryptionState::try_from(version).unwrap();
+      doc.encrypt(&state).unwrap();
+    }
+    let path = std::env::temp_dir().join(format!("pdf2markdown-{name}-{}.pdf", std::process::id()));
+    doc.save(&path).unwrap();
+    path
+  }
+
+  #[test]
+  fn encrypted_pdfs_need_the_right_password() {
+    let path = write_pdf("encrypted", Some("secret"));
+    let result = (
+      load_document(&path, None).map(|_| ()),
+      load_document(&path, Some("guess")).map(|_| ()),
+      load_document(&path, Some("secret")).map(|doc| doc.get_pages().len()),
+    );
+    let _ = fs::remove_file(&path);
+    assert!(matches!(result.0, Err(ConversionError::PasswordRequired)));
+    assert!(matches!(result.1, Err(ConversionError::WrongPassword)));
+    assert_eq!(result.2.unwrap(), 1);
+  }
+
+  #[test]
+  fn decrypted_pdfs_save_without_a_password() {
+    // As OCR does, to render the pages of a PDF that needs a password.
+    let path = write_pdf("decrypted", Some("secret"));
+    let copy = path.with_extension("plain.pdf");
+    let saved = load_document(&path, Some("secret")).map(|mut doc| doc.save(&copy));
+    let reopened = load_document(&copy, None).map(|doc| doc.get_pages().len());
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&copy);
+    assert!(saved.unwrap().is_ok());
+    assert_eq!(reopened.unwrap(), 1);
+  }
+
+  #[test]
+  fn previews_stop_at_the_last_page() {
+    let path = write_pdf("preview", None);
+    let options = ConvertOptions::default();
+    let cancel = CancelToken::default();
+    let result =
+      preview(&path, 5, None, &options, &cancel).map(|(output, total)| (output.pages.len(), total));
+    let _ = fs::remove_file(&path);
+    assert_eq!(result.unwrap(), (1, 1));
+  }
+
+  #[test]
+  fn page_files_are_numbered_to_sort() {
+    let dir = std::env::temp_dir().join(format!("pdf2markdown-pages-{}", std::process::id()));
+    let page = |number| document::Page {
+      number,
+      blocks: vec![Block::Paragraph(vec![document::Inline::Text(format!(
+        "Page {number}"
+      ))])],
+      footnotes: Vec::new(),
+    };
+    let document = document::Document {
+      pages: vec![page(9), page(10)],
+      ..Default::default()
+    };
+    let written = write_page_files(
+      &document,
+      Path::new("/in/report.pdf"),
+      &dir,
+      OutputFormat::Markdown,
+      WriteMode::Overwrite,
+    );
+    let names: Vec<String> = written
+      .unwrap()
+      .iter()
+      .map(|(file, _)| file.file_name().unwrap().to_string_lossy().into_owned())
+      .collect();
+    let first = fs::read_to_string(dir.join(&names[0]));
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(names, ["report-page-09.md", "report-page-10.md"]);
+    assert_eq!(first.unwrap(), "Page 9\n");
+  }
+
+  #[test]
+  fn passwords_are_ignored_for_unencrypted_pdfs() {
+    let path = write_pdf("plain", None);
+    let result = load_document(&path, Some("unused")).map(|doc| doc.get_pages().len());
+    let _ = fs::remove_file(&path);
+    assert_eq!(result.unwrap(), 1);
+  }
+}
@@ -0,0 +1,484 @@
+//! The document model conversions produce.
+//!
+//! Layout analysis builds a [`Document`] once per PDF; the `markdown`, `html`
+//! and `plain_text` renderers turn it into the format that was asked for, so
+//! none of the extraction logic depends on the output format.
+
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::toc::{self, Anchors};
+use crate::whitespace::Normalizer;
+use crate::{html, markdown, plain_text, whitespace};
+
+/// The format a conversion is rendered to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputFormat {
+  #[default]
+  Markdown,
+  Html,
+  PlainText,
+}
+
+impl OutputFormat {
+  /// File extension for output in this format.
+  pub fn extension(self) -> &'static str {
+    match self {
+      OutputFormat::Markdown => "md",
+      OutputFormat::Html => "html",
+      OutputFormat::PlainText => "txt",
+    }
+  }
+}
+
+/// A converted PDF.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+  /// Document metadata (`title`, `author`, ...) in output order; empty unless
+  /// front matter was asked for.
+  pub metadata: Vec<(String, String)>,
+  pub pages: Vec<Page>,
+  /// Put between pages instead of a blank line, with `{page}` replaced by
+  /// the number of the page that follows.
+  pub page_separator: Option<String>,
+  /// Whether Markdown output drops the blank lines between list items.
+  pub tight_output: bool,
+}
+
+/// One page of a converted PDF.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Page {
+  /// One-based, as PDF viewers count.
+  pub number: u32,
+  pub blocks: Vec<Block>,
+  /// The notes at the foot of the page, which go at the end of the
+  /// document.
+  pub footnotes: Vec<Footnote>,
+}
+
+/// A footnote, referred to from the text by its label.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Footnote {
+  pub label: String,
+  pub content: Vec<Inline>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Block {
+  Heading {
+    level: usize,
+    content: Vec<Inline>,
+  },
+  Paragraph(Vec<Inline>),
+  List(List),
+  /// Rows of cells; the first row is the header.
+  Table(Vec<Vec<String>>),
+  /// Preformatted text: code, or a table whose columns couldn't be trusted.
+  Code(String),
+  Image {
+    src: String,
+  },
+  /// A display equation, as LaTeX.
+  Math(String),
+  /// A quotation: paragraphs, and quotations nested within it.
+  Quote(Vec<Block>),
+}
+
+/// A bulleted or numbered list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct List {
+  /// The number of the first item of a numbered list; `None` for bullets.
+  pub start: Option<u32>,
+  pub items: Vec<ListItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListItem {
+  pub content: Vec<Inline>,
+  /// Lists nested under this item.
+  pub children: Vec<List>,
+}
+
+impl List {
+  /// The marker of the item at `index`: `-`, or its number followed by `.`.
+  pub fn marker(&self, index: usize) -> String {
+    match self.start {
+      Some(start) => format!("{}.", start as usize + index),
+      None => "-".to_string(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Inline {
+  Text(String),
+  Code(String),
+  Link {
+    content: Vec<Inline>,
+    url: String,
+  },
+  /// A URL written out as itself.
+  Url(String),
+  /// Inline math, as LaTeX.
+  Math(String),
+  /// Bold text.
+  Strong(Vec<Inline>),
+  /// Italic text.
+  Emphasis(Vec<Inline>),
+  /// A reference to the footnote with this label.
+  FootnoteRef(String),
+}
+
+impl Document {
+  pub fn render(&self, format: OutputFormat) -> String {
+    render_merged(std::slice::from_ref(self), "", format)
+  }
+
+  /// Each page as a document of its own, with this one's metadata.
+  pub fn split_pages(&self) -> impl Iterator<Item = Document> + '_ {
+    self.pages.iter().map(|page| Document {
+      metadata: self.metadata.clone(),
+      pages: vec![page.clone()],
+      page_separator: None,
+      tight_output: self.tight_output,
+    })
+  }
+}
+
+/// Renders a document to `out` a page at a time, for documents too long to
+/// hold whole. The output is the same as [`Document::render`]'s.
+pub struct PageWriter<W: Write> {
+  out: W,
+  format: OutputFormat,
+  metadata: Vec<(String, String)>,
+  page_separator: Option<String>,
+  /// Whether any page or text has been written yet.
+  pages_started: bool,
+  text_started: bool,
+  /// The footnotes of the pages so far, written at the end.
+  footnotes: Vec<Footnote>,
+  /// Tidies the whitespace of Markdown on its way out.
+  normalizer: Option<Normalizer>,
+  anchors: Anchors,
+}
+
+impl<W: Write> PageWriter<W> {
+  /// Starts the document, writing what comes before the first page.
+  pub fn new(
+    out: W,
+    metadata: Vec<(String, String)>,
+    page_separator: Option<String>,
+    tight_output: bool,
+    format: OutputFormat,
+  ) -> io::Result<PageWriter<W>> {
+    let head = match format {
+      OutputFormat::Markdown => markdown::head(&metadata),
+      OutputFormat::Html => html::head(&metadata),
+      OutputFormat::PlainText => plain_text::head(&metadata),
+    };
+    let mut writer = PageWriter {
+      out,
+      format,
+      metadata,
+      page_separator,
+      pages_started: false,
+      text_started: false,
+      footnotes: Vec::new(),
+      normalizer: (format == OutputFormat::Markdown).then(|| Normalizer::new(tight_output)),
+      anchors: Anchors::default(),
+    };
+    writer.write(&head)?;
+    Ok(writer)
+  }
+
+  pub fn page(&mut self, page: &Page) -> io::Result<()> {
+    let text = render_page(page, self.format, &mut self.anchors);
+    let first = !std::mem::replace(&mut self.pages_started, true);
+    let start = page_start(
+      self.page_separator.as_deref(),
+      page,
+      first,
+      &text,
+      self.text_started,
+    );
+    self.write(&start)?;
+    self.write(&text)?;
+    self.text_started |= !start.is_empty() || !text.is_empty();
+    self.footnotes.extend(page.footnotes.iter().cloned());
+    Ok(())
+  }
+
+  /// Ends the document and flushes it, returning the writer.
+  pub fn finish(mut self) -> io::Result<W> {
+    let notes = render_footnotes(&self.footnotes, self.format);
+    if !notes.is_empty() {
+      if self.text_started {
+        self.write("\n\n")?;
+      }
+      self.write(&notes)?;
+    }
+    let tail = match self.format {
+      OutputFormat::Markdown => markdown::tail(&self.metadata),
+      OutputFormat::Html => html::tail(&self.metadata),
+      OutputFormat::PlainText => plain_text::tail(&self.metadata),
+    };
+    self.write(&tail)?;
+    if let Some(normalizer) = self.normalizer.take() {
+      self.out.write_all(normalizer.finish().as_bytes())?;
+    }
+    self.out.flush()?;
+    Ok(self.out)
+  }
+
+  fn write(&mut self, text: &str) -> io::Result<()> {
+    match &mut self.normalizer {
+      Some(normalizer) => self.out.write_all(normalizer.push(text).as_bytes()),
+      None => self.out.write_all(text.as_bytes()),
+    }
+  }
+}
+
+/// Renders `documents` one after the other with `separator` between them.
+/// Only the first document's metadata and `tight_output` are used.
+pub fn render_merged(documents: &[Document], separator: &str, format: OutputFormat) -> String {
+  // The documents become one, so their headings' anchors are numbered
+  // together.
+  let mut anchors = Anchors::default();
+  let bodies: Vec<String> = documents
+    .iter()
+    .map(|document| render_pages(document, format, &mut anchors))
+    .filter(|body| !body.is_empty())
+    .collect();
+  let body = bodies.join(separator);
+  let metadata = documents.first().map_or(&[][..], |d| &d.metadata);
+  let tight = documents.first().is_some_and(|d| d.tight_output);
+  match format {
+    OutputFormat::Markdown => whitespace::normalize(&markdown::document(metadata, &body), tight),
+    OutputFormat::Html => html::document(metadata, &body),
+    OutputFormat::PlainText => plain_text::document(metadata, &body),
+  }
+}
+
+/// Renders every page of `document`, one after the other.
+fn render_pages(document: &Document, format: OutputFormat, anchors: &mut Anchors) -> String {
+  let mut out = String::new();
+  for (index, page) in document.pages.iter().enumerate() {
+    let text = render_page(page, format, anchors);
+    let separator = document.page_separator.as_deref();
+    out.push_str(&page_start(
+      separator,
+      page,
+      index == 0,
+      &text,
+      !out.is_empty(),
+    ));
+    out.push_str(&text);
+  }
+  let footnotes: Vec<Footnote> = document
+    .pages
+    .iter()
+    .flat_map(|page| page.footnotes.iter().cloned())
+    .collect();
+  let notes = render_footnotes(&footnotes, format);
+  if !notes.is_empty() {
+    if !out.is_empty() {
+      out.push_str("\n\n");
+    }
+    out.push_str(&notes);
+  }
+  out
+}
+
+/// The footnotes, for the end of the document.
+fn render_footnotes(footnotes: &[Footnote], format: OutputFormat) -> String {
+  if footnotes.is_empty() {
+    return String::new();
+  }
+  match format {
+    OutputFormat::Markdown => markdown::footnotes(footnotes),
+    OutputFormat::Html => html::footnotes(footnotes),
+    OutputFormat::PlainText => plain_text::footnotes(footnotes),
+  }
+}
+
+/// The page's blocks, separated by blank lines, its headings given the
+/// next of `anchors`.
+fn render_page(page: &Page, format: OutputFormat, anchors: &mut Anchors) -> String {
+  page
+    .blocks
+    .iter()
+    .map(|block| render_block(block, format, anchors))
+    .filter(|text| !text.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+/// What goes before a page rendered as `text`: the separator, on every page
+/// but the first, even empty ones so that each page is accounted for.
+/// Without a separator, a blank line once there is text on both sides.
+fn page_start(
+  separator: Option<&str>,
+  page: &Page,
+  first: bool,
+  text: &str,
+  after_text: bool,
+) -> String {
+  match separator {
+    Some(separator) if !first => separator.replace("{page}", &page.number.to_string()),
+    None if after_text && !text.is_empty() => "\n\n".to_string(),
+    _ => String::new(),
+  }
+}
+
+fn render_block(block: &Block, format: OutputFormat, anchors: &mut Anchors) -> String {
+  match (format, block) {
+    // GitHub gives Markdown headings the same anchors itself, all but those
+    // it gives none, which are written out.
+    (OutputFormat::Markdown, Block::Heading { level, content }) => {
+      let text = plain(content);
+      let anchor = anchors.next(text.trim());
+      let id = toc::needs_id(text.trim()).then_some(anchor.as_str());
+      markdown::heading(*level, content, id)
+    }
+    (OutputFormat::Markdown, _) => markdown::block(block),
+    (OutputFormat::Html, _) => html::block(block, anchors),
+    (OutputFormat::PlainText, _) => plain_text::block(block),
+  }
+}
+
+/// The text of `inlines` without any markup.
+pub fn plain(inlines: &[Inline]) -> String {
+  inlines
+    .iter()
+    .map(|inline| match inline {
+      Inline::Text(text) | Inline::Code(text) | Inline::Url(text) | Inline::Math(text) => {
+        text.clone()
+      }
+      Inline::Link { content, .. } | Inline::Strong(content) | Inline::Emphasis(content) => {
+        plain(content)
+      }
+      Inline::FootnoteRef(_) => String::new(),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sample() -> Document {
+    let page = |number, blocks| Page {
+      number,
+      blocks,
+      footnotes: Vec::new(),
+    };
+    Document {
+      metadata: Vec::new(),
+      pages: vec![
+        page(
+          1,
+          vec![
+            Block::Heading {
+              level: 1,
+              content: vec![Inline::Text("Results".into())],
+            },
+            Block::Paragraph(vec![
+              Inline::Text("See ".into()),
+              Inline::Link {
+                content: vec![Inline::Text("the <data>".into())],
+                url: "https://example.com/data".into(),
+              },
+              Inline::Text(" and run ".into()),
+              Inline::Code("make".into()),
+              Inline::Text(".".into()),
+            ]),
+          ],
+        ),
+        page(2, Vec::new()),
+        page(
+          3,
+          vec![Block::Table(vec![
+            vec!["Name".into(), "Qty".into()],
+            vec!["Apples".into(), "3".into()],
+          ])],
+        ),
+      ],
+      page_separator: None,
+      tight_output: false,
+    }
+  }
+
+  #[test]
+  fn renders_markdown() {
+    assert_eq!(
+      sample().render(OutputFormat::Markdown),
+      "# Results\n\nSee [the <data>](https://example.com/data) and run `make`.\n\n\
+       | Name | Qty |\n|---|---|\n| Apples | 3 |\n"
+    );
+  }
+
+  #[test]
+  fn renders_html() {
+    assert_eq!(
+      sample().render(OutputFormat::Html),
+      "<h1 id=\"results\">Results</h1>\n\n\
+       <p>See <a href=\"https://example.com/data\">the &lt;data&gt;</a> and run <code>make</code>.</p>\n\n\
+       <table>\n<thead>\n<tr><th>Name</th><th>Qty</th></tr>\n</thead>\n\
+       <tbody>\n<tr><td>Apples</td><td>3</td></tr>\n</tbody>\n</table>\n"
+    );
+  }
+
+  #[test]
+  fn writing_page_by_page_matches_rendering() {
+    let mut document = sample();
+    document.metadata = vec![("title".into(), "Report".into())];
+    for (format, separator) in [
+      (OutputFormat::Markdown, None),
+      (OutputFormat::Html, None),
+      (OutputFormat::PlainText, None),
+      (
+        OutputFormat::Markdown,
+        Some("\n\n<!-- page {page} -->\n\n".to_string()),
+      ),
+    ] {
+      document.page_separator = separator;
+      let mut writer = PageWriter::new(
+        Vec::new(),
+        document.metadata.clone(),
+        document.page_separator.clone(),
+        document.tight_output,
+        format,
+      )
+      .unwrap();
+      for page in &document.pages {
+        writer.page(page).unwrap();
+      }
+      let written = String::from_utf8(writer.finish().unwrap()).unwrap();
+      assert_eq!(written, document.render(format));
+    }
+  }
+
+  #[test]
+  fn marks_every_page_break() {
+    let document = Document {
+      page_separator: Some("\n\n<!-- page {page} -->\n\n".into()),
+      ..sample()
+    };
+    assert_eq!(
+      document.render(OutputFormat::Markdown),
+      "# Results\n\nSee [the <data>](https://example.com/data) and run `make`.\
+       \n\n<!-- page 2 -->\n\n<!-- page 3 -->\n\n\
+       | Name | Qty |\n|---|---|\n| Apples | 3 |\n"
+    );
+  }
+
+  #[test]
+  fn renders_plain_text() {
+    assert_eq!(
+      sample().render(OutputFormat::PlainText),
+      "Results\n\nSee the <data> and run make.\n\nName\tQty\nApples\t3\n"
+    );
+  }
+}
@@ -0,0 +1,174 @@
+//! Image encoders: PNG through the `png` crate, baseline JPEG and lossless
+//! WebP through `image`. They are used for images the PDF stores as raw or
+//! Flate samples, which tend to be diagrams and screenshots, not photos.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+
+/// Decoded 8-bit samples, gray (1 channel) or RGB (3 channels).
+#[derive(Debug, Clone)]
+pub struct Pixels {
+  pub samples: Vec<u8>,
+  pub width: u32,
+  pub height: u32,
+  pub channels: u8,
+}
+
+impl Pixels {
+  /// Scales the image down to `width` × `height` by averaging each block of
+  /// source pixels.
+  pub fn downsample(&self, width: u32, height: u32) -> Pixels {
+    let channels = self.channels as usize;
+    let (src_w, src_h) = (self.width as usize, self.height as usize);
+    let (dst_w, dst_h) = (width as usize, height as usize);
+    let mut samples = Vec::with_capacity(dst_w * dst_h * channels);
+    for y in 0..dst_h {
+      let rows = y * src_h / dst_h..((y + 1) * src_h / dst_h).max(y * src_h / dst_h + 1);
+      for x in 0..dst_w {
+        let cols = x * src_w / dst_w..((x + 1) * src_w / dst_w).max(x * src_w / dst_w + 1);
+        let count = (rows.len() * cols.len()) as u32;
+        for c in 0..channels {
+          let mut sum = 0u32;
+          for row in rows.clone() {
+            for col in cols.clone() {
+              sum += self.samples[(row * src_w + col) * channels + c] as u32;
+            }
+          }
+          samples.push(((sum + count / 2) / count) as u8);
+        }
+      }
+    }
+    Pixels {
+      samples,
+      width,
+      height,
+      channels: self.channels,
+    }
+  }
+
+  fn color_type(&self) -> ExtendedColorType {
+    match self.channels {
+      1 => ExtendedColorType::L8,
+      _ => ExtendedColorType::Rgb8,
+    }
+  }
+}
+
+pub fn png(pixels: &Pixels) -> Option<Vec<u8>> {
+  let mut data = Vec::new();
+  let mut encoder = png::Encoder::new(&mut data, pixels.width, pixels.height);
+  encoder.set_color(match pixels.channels {
+    1 => png::ColorType::Grayscale,
+    _ => png::ColorType::Rgb,
+  });
+  encoder.set_depth(png::BitDepth::Eight);
+  let mut writer = encoder.write_header().ok()?;
+  writer.write_image_data(&pixels.samples).ok()?;
+  writer.finish().ok()?;
+  Some(data)
+}
+
+/// A baseline JPEG of `quality` from 1 to 100.
+pub fn jpeg(pixels: &Pixels, quality: u8) -> Option<Vec<u8>> {
+  let mut data = Vec::new();
+  JpegEncoder::new_with_quality(&mut data, quality)
+    .write_image(
+      &pixels.samples,
+      pixels.width,
+      pixels.height,
+      pixels.color_type(),
+    )
+    .ok()?;
+  Some(data)
+}
+
+/// A lossless WebP.
+pub fn webp(pixels: &Pixels) -> Option<Vec<u8>> {
+  let mut data = Vec::new();
+  WebPEncoder::new_lossless(&mut data)
+    .write_image(
+      &pixels.samples,
+      pixels.width,
+      pixels.height,
+      pixels.color_type(),
+    )
+    .ok()?;
+  Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn gradient(channels: u8) -> Pixels {
+    let (width, height) = (20, 12);
+    let samples = (0..width * height)
+      .flat_map(|i| (0..channels).map(move |c| (i * 7 + c as u32 * 40) as u8))
+      .collect();
+    Pixels {
+      samples,
+      width,
+      height,
+      channels,
+    }
+  }
+
+  #[test]
+  fn encoders_write_their_headers() {
+    for channels in [1, 3] {
+      let pixels = gradient(channels);
+      let jpeg = jpeg(&pixels, 90).unwrap();
+      assert_eq!(jpeg[..4], [0xFF, 0xD8, 0xFF, 0xE0]);
+      assert_eq!(jpeg[jpeg.len() - 2..], [0xFF, 0xD9]);
+
+      let webp = webp(&pixels).unwrap();
+      assert_eq!(&webp[..4], b"RIFF");
+      assert_eq!(&webp[8..16], b"WEBPVP8L");
+      let size = u32::from_le_bytes(webp[4..8].try_into().unwrap());
+      assert_eq!(size as usize, webp.len() - 8);
+    }
+    let small = gradient(3).downsample(5, 3);
+    assert_eq!(small.samples.len(), 5 * 3 * 3);
+  }
+
+  #[test]
+  fn encoders_round_trip_through_a_decoder() {
+    // Sizes that leave partial blocks at the right and bottom edges.
+    let (width, height) = (37, 23);
+    for channels in [1u8, 3] {
+      let samples = (0..width * height)
+        .flat_map(|i| {
+          let (x, y) = (i % width, i / width);
+          (0..channels).map(move |c| (x * 5 + y * 3 + c as u32 * 60) as u8)
+        })
+        .collect();
+      let pixels = Pixels {
+        samples,
+        width,
+        height,
+        channels,
+      };
+      let decoded = |data: &[u8], format| {
+        let image = image::load_from_memory_with_format(data, format).unwrap();
+        assert_eq!((image.width(), image.height()), (width, height));
+        match channels {
+          1 => image.to_luma8().into_raw(),
+          _ => image.to_rgb8().into_raw(),
+        }
+      };
+
+      let webp = decoded(&webp(&pixels).unwrap(), image::ImageFormat::WebP);
+      assert_eq!(webp, pixels.samples, "lossless WebP, {channels} channel(s)");
+
+      let jpeg = decoded(&jpeg(&pixels, 90).unwrap(), image::ImageFormat::Jpeg);
+      let error: u32 = jpeg
+        .iter()
+        .zip(&pixels.samples)
+        .map(|(&a, &b)| a.abs_diff(b) as u32)
+        .sum();
+      let mean = error as f64 / pixels.samples.len() as f64;
+      assert!(mean < 4.0, "JPEG, {channels} channel(s), off by {mean:.2}");
+    }
+  }
+}
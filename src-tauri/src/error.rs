@@ -0,0 +1,204 @@
+//! The error type commands fail with.
+//!
+//! Errors reach the frontend as `{ kind, message }` (plus `path` for errors
+//! about one file of several), so the UI can branch on `kind` and show
+//! `message` as is.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// Why a command failed.
+#[derive(Debug)]
+pub enum ConversionError {
+  FileNotFound(PathBuf),
+  InvalidPdf(String),
+  InvalidPageRange(String),
+  Io(io::Error),
+  Ocr(String),
+  Remote(String),
+  Cancelled,
+  /// The PDF is encrypted and no password was given.
+  PasswordRequired,
+  /// The PDF is encrypted and the given password doesn't open it.
+  WrongPassword,
+  /// A folder couldn't be watched.
+  Watch(String),
+  InvalidSettings(String),
+  /// The conversion history couldn't be read or written.
+  Database(String),
+  /// An update couldn't be downloaded or installed.
+  Update(String),
+  /// Text couldn't be put on the clipboard.
+  Clipboard(String),
+  /// The PDF has no bookmarks to split it by.
+  NoOutline,
+  /// An error converting one of several files.
+  InFile {
+    path: PathBuf,
+    error: Box<ConversionError>,
+  },
+  /// A failure in the app itself rather than in the input.
+  Internal(String),
+}
+
+impl ConversionError {
+  /// The machine-readable name of the error, as sent to the frontend.
+  pub fn kind(&self) -> &'static str {
+    match self {
+      ConversionError::FileNotFound(_) => "fileNotFound",
+      ConversionError::InvalidPdf(_) => "invalidPdf",
+      ConversionError::InvalidPageRange(_) => "invalidPageRange",
+      ConversionError::Io(_) => "io",
+      ConversionError::Ocr(_) => "ocr",
+      ConversionError::Remote(_) => "remote",
+      ConversionError::Cancelled => "cancelled",
+      ConversionError::PasswordRequired => "passwordRequired",
+      ConversionError::WrongPassword => "wrongPassword",
+      ConversionError::Watch(_) => "watch",
+      ConversionError::InvalidSettings(_) => "invalidSettings",
+      ConversionError::Database(_) => "database",
+      ConversionError::Update(_) => "update",
+      ConversionError::Clipboard(_) => "clipboard",
+      ConversionError::NoOutline => "noOutline",
+      ConversionError::InFile { error, .. } => error.kind(),
+      ConversionError::Internal(_) => "internal",
+    }
+  }
+
+  /// Ties the error to `path`. Cancellation is left alone: it's about the
+  /// whole job, not the file.
+  pub fn in_file(self, path: impl Into<PathBuf>) -> ConversionError {
+    match self {
+      ConversionError::Cancelled => self,
+      error => ConversionError::InFile {
+        path: path.into(),
+        error: Box::new(error),
+      },
+    }
+  }
+
+  /// An I/O error, with what was being done when it happened.
+  pub fn io(context: impl fmt::Display, err: io::Error) -> ConversionError {
+    ConversionError::Io(io::Error::new(err.kind(), format!("{context}: {err}")))
+  }
+}
+
+impl fmt::Display for ConversionError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ConversionError::FileNotFound(path) => write!(f, "file not found: {}", path.display()),
+      ConversionError::InvalidPdf(reason) => write!(f, "invalid PDF: {reason}"),
+      ConversionError::InvalidPageRange(reason) => write!(f, "invalid page range: {reason}"),
+      ConversionError::Io(err) => write!(f, "I/O error: {err}"),
+      ConversionError::Ocr(reason) => write!(f, "OCR failed: {reason}"),
+      ConversionError::Remote(reason) => write!(f, "remote conversion failed: {reason}"),
+      ConversionError::Cancelled => write!(f, "conversion cancelled"),
+      ConversionError::PasswordRequired => write!(f, "the PDF is password-protected"),
+      ConversionError::WrongPassword => write!(f, "wrong password for the PDF"),
+      ConversionError::Watch(reason) => write!(f, "cannot watch folder: {reason}"),
+      ConversionError::InvalidSettings(reason) => write!(f, "invalid settings: {reason}"),
+      ConversionError::Database(reason) => write!(f, "history database error: {reason}"),
+      ConversionError::Update(reason) => write!(f, "update failed: {reason}"),
+      ConversionError::Clipboard(reason) => write!(f, "cannot copy to clipboard: {reason}"),
+      ConversionError::NoOutline => write!(
+        f,
+        "the PDF has no bookmarks to split it by; convert it as a whole instead"
+      ),
+      ConversionError::InFile { path, error } => write!(f, "{}: {error}", path.display()),
+      ConversionError::Internal(reason) => write!(f, "internal error: {reason}"),
+    }
+  }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl Serialize for ConversionError {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let path = match self {
+      ConversionError::InFile { path, .. } | ConversionError::FileNotFound(path) => Some(path),
+      _ => None,
+    };
+    let mut state = serializer.serialize_struct("ConversionError", 3)?;
+    state.serialize_field("kind", self.kind())?;
+    state.serialize_field("message", &self.to_string())?;
+    match path {
+      Some(path) => state.serialize_field("path", path)?,
+      None => state.skip_field("path")?,
+    }
+    state.end()
+  }
+}
+
+impl From<io::Error> for ConversionError {
+  fn from(err: io::Error) -> Self {
+    ConversionError::Io(err)
+  }
+}
+
+impl From<lopdf::Error> for ConversionError {
+  fn from(err: lopdf::Error) -> Self {
+    ConversionError::InvalidPdf(err.to_string())
+  }
+}
+
+impl From<tauri::Error> for ConversionError {
+  fn from(err: tauri::Error) -> Self {
+    ConversionError::Internal(err.to_string())
+  }
+}
+
+impl From<rusqlite::Error> for ConversionError {
+  fn from(err: rusqlite::Error) -> Self {
+    ConversionError::Database(err.to_string())
+  }
+}
+
+impl From<notify::Error> for ConversionError {
+  fn from(err: notify::Error) -> Self {
+    ConversionError::Watch(err.to_string())
+  }
+}
+
+impl From<tauri_plugin_updater::Error> for ConversionError {
+  fn from(err: tauri_plugin_updater::Error) -> Self {
+    ConversionError::Update(err.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn serializes_kind_and_message() {
+    let json = serde_json::to_value(ConversionError::PasswordRequired).unwrap();
+    assert_eq!(
+      json,
+      serde_json::json!({
+        "kind": "passwordRequired",
+        "message": "the PDF is password-protected",
+      })
+    );
+  }
+
+  #[test]
+  fn errors_in_one_file_keep_their_kind() {
+    let error = ConversionError::InvalidPdf("missing %PDF header".into()).in_file("/a/b.pdf");
+    assert_eq!(
+      serde_json::to_value(&error).unwrap(),
+      serde_json::json!({
+        "kind": "invalidPdf",
+        "message": "/a/b.pdf: invalid PDF: missing %PDF header",
+        "path": "/a/b.pdf",
+      })
+    );
+    assert!(matches!(
+      ConversionError::Cancelled.in_file("/a/b.pdf"),
+      ConversionError::Cancelled
+    ));
+  }
+}
@@ -0,0 +1,906 @@
+//! Positioned text extraction.
+//!
+//! `lopdf` only hands us raw content-stream operations, so this module runs a
+//! small interpreter over them that tracks the text and graphics state and
+//! records every shown string together with its page position and font. The
+//! spans are then grouped into lines, which is what the Markdown layer works
+//! with.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Encoding, Object, ObjectId};
+
+use crate::links::{self, Link};
+
+/// Form XObjects can reference each other; stop following them past this depth.
+const MAX_FORM_DEPTH: usize = 8;
+
+/// A run of text drawn with a single font at a single position.
+#[derive(Debug, Clone)]
+pub struct TextSpan {
+  pub text: String,
+  /// Left edge of the span, in page space (points, origin bottom-left).
+  pub x: f32,
+  /// Baseline of the span, in page space.
+  pub y: f32,
+  pub width: f32,
+  pub font_size: f32,
+  /// Base font name with any subset prefix (`ABCDEF+`) removed.
+  pub font_name: String,
+  /// URL of the link annotation the span lies on, if any.
+  pub link: Option<String>,
+  /// Weight and slant as the font's descriptor gives them.
+  pub style: FontStyle,
+  /// The label of the footnote the span is the reference mark of, once
+  /// [`footnotes::split`](crate::footnotes::split) has matched it to one.
+  pub footnote: Option<String>,
+}
+
+/// What a font's descriptor says about its face. Fonts often leave this out,
+/// so the name is checked as well; see [`TextSpan::is_bold`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FontStyle {
+  pub bold: bool,
+  pub italic: bool,
+}
+
+impl FontStyle {
+  /// Stems at least this thick (in glyph space) suggest a bold face; regular
+  /// text faces sit around 80-90.
+  const BOLD_STEM: f32 = 120.0;
+
+  /// The style `font` declares. `StemV` is a rough measure that producers
+  /// often get wrong, so it only counts for fonts named `F1` and the like,
+  /// whose name says nothing about their weight.
+  fn from_descriptor(doc: &Document, font: &Dictionary, name: &str) -> Self {
+    let Ok(descriptor) = font
+      .get_deref(b"FontDescriptor", doc)
+      .and_then(Object::as_dict)
+    else {
+      return FontStyle::default();
+    };
+    let value = |key: &[u8]| descriptor.get(key).map(number).ok();
+    let flags = descriptor
+      .get(b"Flags")
+      .and_then(Object::as_i64)
+      .unwrap_or(0);
+    let lower = name.to_ascii_lowercase();
+    let weight_named = [
+      "regular", "roman", "book", "light", "medium", "thin", "normal", "bold", "black", "heavy",
+    ]
+    .iter()
+    .any(|w| lower.contains(w));
+    let weight = value(b"FontWeight");
+    FontStyle {
+      bold: flags & FORCE_BOLD != 0
+        || weight.is_some_and(|weight| weight >= 700.0)
+        || (weight.is_none()
+          && !weight_named
+          && value(b"StemV").is_some_and(|stem| stem >= Self::BOLD_STEM)),
+      italic: flags & ITALIC != 0 || value(b"ItalicAngle").is_some_and(|angle| angle.abs() >= 1.0),
+    }
+  }
+}
+
+/// Font descriptor flags (PDF 32000-1, table 123).
+const ITALIC: i64 = 1 << 6;
+const FORCE_BOLD: i64 = 1 << 18;
+
+impl TextSpan {
+  pub fn right(&self) -> f32 {
+    self.x + self.width
+  }
+
+  /// Whether the font is a bold face, by its descriptor or its name
+  /// (`Arial-BoldMT`, `Helvetica,Bold`, `Inter-Black`, ...).
+  pub fn is_bold(&self) -> bool {
+    let name = self.font_name.to_ascii_lowercase();
+    self.style.bold || ["bold", "black", "heavy"].iter().any(|w| name.contains(w))
+  }
+
+  /// Whether the font is an italic or oblique face, by its descriptor or its
+  /// name.
+  pub fn is_italic(&self) -> bool {
+    let name = self.font_name.to_ascii_lowercase();
+    self.style.italic || ["italic", "oblique"].iter().any(|w| name.contains(w))
+  }
+}
+
+/// Spans sharing a baseline, ordered left to right.
+#[derive(Debug, Clone)]
+pub struct TextLine {
+  pub spans: Vec<TextSpan>,
+}
+
+impl TextLine {
+  pub fn y(&self) -> f32 {
+    self.spans.first().map_or(0.0, |s| s.y)
+  }
+
+  /// The largest font size on the line.
+  pub fn font_size(&self) -> f32 {
+    self.spans.iter().map(|s| s.font_size).fold(0.0, f32::max)
+  }
+
+  pub fn text(&self) -> String {
+    self.spans.iter().map(|s| s.text.as_str()).collect()
+  }
+}
+
+/// Where an image XObject is drawn on the page.
+#[derive(Debug, Clone)]
+pub struct ImagePlacement {
+  pub id: ObjectId,
+  /// Top edge of the image, in page space.
+  pub y: f32,
+  pub bottom: f32,
+  pub left: f32,
+  pub right: f32,
+}
+
+/// The content of one page, lines ordered top to bottom and images in
+/// drawing order.
+#[derive(Debug, Clone)]
+pub struct PageText {
+  pub lines: Vec<TextLine>,
+  pub images: Vec<ImagePlacement>,
+}
+
+/// Extracts the positioned text of a single page.
+pub fn extract_page(doc: &Document, page_id: ObjectId) -> lopdf::Result<PageText> {
+  let resources = Resources::for_page(doc, page_id)?;
+  let content = Content::decode(&doc.get_page_content(page_id)?)?;
+
+  let mut interpreter = Interpreter::new(doc, links::page_links(doc, page_id));
+  interpreter.run(&content, &resources, 0);
+
+  Ok(PageText {
+    lines: group_lines(interpreter.spans),
+    images: interpreter.images,
+  })
+}
+
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Computes `a × b`, i.e. the transform that applies `a` first and then `b`.
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+  [
+    a[0] * b[0] + a[1] * b[2],
+    a[0] * b[1] + a[1] * b[3],
+    a[2] * b[0] + a[3] * b[2],
+    a[2] * b[1] + a[3] * b[3],
+    a[4] * b[0] + a[5] * b[2] + b[4],
+    a[4] * b[1] + a[5] * b[3] + b[5],
+  ]
+}
+
+fn translate(tx: f32, ty: f32) -> Matrix {
+  [1.0, 0.0, 0.0, 1.0, tx, ty]
+}
+
+fn number(object: &Object) -> f32 {
+  object.as_float().unwrap_or(0.0)
+}
+
+fn numbers<const N: usize>(operands: &[Object]) -> Option<[f32; N]> {
+  if operands.len() < N {
+    return None;
+  }
+  let mut out = [0.0; N];
+  for (slot, operand) in out.iter_mut().zip(operands) {
+    *slot = operand.as_float().ok()?;
+  }
+  Some(out)
+}
+
+/// Strips the six-letter subset tag that embedded fonts carry (`ABCDEF+Name`).
+fn strip_subset_prefix(name: &str) -> &str {
+  match name.split_once('+') {
+    Some((tag, rest)) if tag.len() == 6 && tag.chars().all(|c| c.is_ascii_uppercase()) => rest,
+    _ => name,
+  }
+}
+
+struct Font<'a> {
+  name: String,
+  encoding: Option<Encoding<'a>>,
+  widths: BTreeMap<u32, f32>,
+  default_width: f32,
+  /// Bytes per character code: 2 for composite (Type0) fonts, 1 otherwise.
+  code_len: usize,
+  style: FontStyle,
+}
+
+impl<'a> Font<'a> {
+  fn load(doc: &'a Document, dict: &'a Dictionary) -> Self {
+    let name_of = |dict: &Dictionary| {
+      dict
+        .get(b"BaseFont")
+        .and_then(Object::as_name)
+        .map(|n| strip_subset_prefix(&String::from_utf8_lossy(n)).to_string())
+        .ok()
+    };
+    let encoding = dict.get_font_encoding(doc).ok();
+    let mut widths = BTreeMap::new();
+
+    if dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type0") {
+      let descendant = dict
+        .get_deref(b"DescendantFonts", doc)
+        .and_then(Object::as_array)
+        .ok()
+        .and_then(|fonts| fonts.first())
+        .and_then(|font| doc.dereference(font).ok())
+        .and_then(|(_, font)| font.as_dict().ok());
+      let mut default_width = 1000.0;
+      if let Some(descendant) = descendant {
+        if let Ok(dw) = descendant.get(b"DW") {
+          default_width = number(dw);
+        }
+        if let Ok(w) = descendant.get_deref(b"W", doc).and_then(Object::as_array) {
+          parse_cid_widths(doc, w, &mut widths);
+        }
+      }
+      let name = name_of(dict)
+        .or_else(|| descendant.and_then(name_of))
+        .unwrap_or_default();
+      let style = descendant.map_or_else(FontStyle::default, |descendant| {
+        FontStyle::from_descriptor(doc, descendant, &name)
+      });
+      return Font {
+        name,
+        encoding,
+        widths,
+        default_width,
+        code_len: 2,
+        style,
+      };
+    }
+
+    let first_char = dict.get(b"FirstChar").and_then(Object::as_i64).unwrap_or(0);
+    if let Ok(array) = dict.get_deref(b"Widths", doc).and_then(Object::as_array) {
+      for (offset, width) in array.iter().enumerate() {
+        let width = doc.dereference(width).map_or(0.0, |(_, w)| number(w));
+        widths.insert((first_char + offset as i64) as u32, width);
+      }
+    }
+    let default_width = dict
+      .get_deref(b"FontDescriptor", doc)
+      .and_then(Object::as_dict)
+      .and_then(|descriptor| descriptor.get(b"MissingWidth"))
+      .map(number)
+      .ok()
+      .filter(|w| *w > 0.0);
+    let name = name_of(dict).unwrap_or_default();
+    // The standard 14 fonts usually ship without widths, so fall back to a
+    // typical advance for the family.
+    let default_width = default_width.unwrap_or_else(|| {
+      let lower = name.to_ascii_lowercase();
+      if lower.contains("courier") || lower.contains("mono") {
+        600.0
+      } else if lower.contains("times") {
+        450.0
+      } else {
+        520.0
+      }
+    });
+
+    Font {
+      style: FontStyle::from_descriptor(doc, dict, &name),
+      name,
+      encoding,
+      widths,
+      default_width,
+      code_len: 1,
+    }
+  }
+
+  fn decode(&self, bytes: &[u8]) -> String {
+    match &self.encoding {
+      Some(encoding) => encoding.bytes_to_string(bytes).unwrap_or_default(),
+      None => bytes.iter().map(|&b| b as char).collect(),
+    }
+  }
+
+  fn codes<'b>(&self, bytes: &'b [u8]) -> impl Iterator<Item = u32> + 'b {
+    bytes
+      .chunks(self.code_len)
+      .map(|chunk| chunk.iter().fold(0u32, |code, &b| (code << 8) | b as u32))
+  }
+
+  fn width(&self, code: u32) -> f32 {
+    self
+      .widths
+      .get(&code)
+      .copied()
+      .unwrap_or(self.default_width)
+  }
+}
+
+/// Parses a CIDFont `W` array: `[c [w1 w2 ...]]` and `[c_first c_last w]` entries.
+fn parse_cid_widths(doc: &Document, array: &[Object], widths: &mut BTreeMap<u32, f32>) {
+  let mut i = 0;
+  while i < array.len() {
+    let Ok(first) = array[i].as_i64() else {
+      break;
+    };
+    match array.get(i + 1).map(|o| doc.dereference(o).map(|(_, o)| o)) {
+      Some(Ok(Object::Array(list))) => {
+        for (offset, width) in list.iter().enumerate() {
+          widths.insert(first as u32 + offset as u32, number(width));
+        }
+        i += 2;
+      }
+      Some(Ok(last)) => {
+        let (Ok(last), Some(width)) = (last.as_i64(), array.get(i + 2)) else {
+          break;
+        };
+        for code in first..=last.min(first + 0xFFFF) {
+          widths.insert(code as u32, number(width));
+        }
+        i += 3;
+      }
+      _ => break,
+    }
+  }
+}
+
+/// Fonts and XObjects available to a content stream.
+struct Resources<'a> {
+  fonts: BTreeMap<Vec<u8>, Rc<Font<'a>>>,
+  xobjects: Option<&'a Dictionary>,
+}
+
+impl<'a> Resources<'a> {
+  fn for_page(doc: &'a Document, page_id: ObjectId) -> lopdf::Result<Self> {
+    let fonts = doc
+      .get_page_fonts(page_id)?
+      .into_iter()
+      .map(|(name, dict)| (name, Rc::new(Font::load(doc, dict))))
+      .collect();
+    let (direct, inherited) = doc.get_page_resources(page_id)?;
+    let xobjects = direct
+      .into_iter()
+      .chain(
+        inherited
+          .into_iter()
+          .filter_map(|id| doc.get_dictionary(id).ok()),
+      )
+      .find_map(|resources| {
+        resources
+          .get_deref(b"XObject", doc)
+          .and_then(Object::as_dict)
+          .ok()
+      });
+    Ok(Resources { fonts, xobjects })
+  }
+
+  /// Resources of a form XObject, falling back to the parent's where the form has none.
+  fn for_form(doc: &'a Document, form: &'a Dictionary, parent: &Resources<'a>) -> Self {
+    let Ok(resources) = form.get_deref(b"Resources", doc).and_then(Object::as_dict) else {
+      return Resources {
+        fonts: parent.fonts.clone(),
+        xobjects: parent.xobjects,
+      };
+    };
+    let fonts = match resources.get_deref(b"Font", doc).and_then(Object::as_dict) {
+      Ok(fonts) => fonts
+        .iter()
+        .filter_map(|(name, font)| {
+          let (_, font) = doc.dereference(font).ok()?;
+          Some((name.clone(), Rc::new(Font::load(doc, font.as_dict().ok()?))))
+        })
+        .collect(),
+      Err(_) => parent.fonts.clone(),
+    };
+    let xobjects = resources
+      .get_deref(b"XObject", doc)
+      .and_then(Object::as_dict)
+      .ok();
+    Resources { fonts, xobjects }
+  }
+}
+
+#[derive(Clone)]
+struct GraphicsState<'a> {
+  ctm: Matrix,
+  font: Option<Rc<Font<'a>>>,
+  font_size: f32,
+  char_spacing: f32,
+  word_spacing: f32,
+  horizontal_scale: f32,
+  leading: f32,
+  rise: f32,
+}
+
+impl Default for GraphicsState<'_> {
+  fn default() -> Self {
+    GraphicsState {
+      ctm: IDENTITY,
+      font: None,
+      font_size: 0.0,
+      char_spacing: 0.0,
+      word_spacing: 0.0,
+      horizontal_scale: 1.0,
+      leading: 0.0,
+      rise: 0.0,
+    }
+  }
+}
+
+struct Interpreter<'a> {
+  doc: &'a Document,
+  state: GraphicsState<'a>,
+  stack: Vec<GraphicsState<'a>>,
+  text_matrix: Matrix,
+  line_matrix: Matrix,
+  spans: Vec<TextSpan>,
+  images: Vec<ImagePlacement>,
+  links: Vec<Link>,
+}
+
+impl<'a> Interpreter<'a> {
+  fn new(doc: &'a Document, links: Vec<Link>) -> Self {
+    Interpreter {
+      doc,
+      links,
+      state: GraphicsState::default(),
+      stack: Vec::new(),
+      text_matrix: IDENTITY,
+      line_matrix: IDENTITY,
+      spans: Vec::new(),
+      images: Vec::new(),
+    }
+  }
+
+  fn run(&mut self, content: &Content, resources: &Resources<'a>, depth: usize) {
+    for op in &content.operations {
+      let operands = op.operands.as_slice();
+      match op.operator.as_str() {
+        "q" => self.stack.push(self.state.clone()),
+        "Q" => {
+          if let Some(state) = self.stack.pop() {
+            self.state = state;
+          }
+        }
+        "cm" => {
+          if let Some(m) = numbers::<6>(operands) {
+            self.state.ctm = multiply(&m, &self.state.ctm);
+          }
+        }
+        "BT" => {
+          self.text_matrix = IDENTITY;
+          self.line_matrix = IDENTITY;
+        }
+        "Tf" => {
+          if let (Some(name), Some(size)) = (operands.first(), operands.get(1)) {
+            self.state.font = name
+              .as_name()
+              .ok()
+              .and_then(|name| resources.fonts.get(name))
+              .cloned();
+            self.state.font_size = number(size);
+          }
+        }
+        "Tc" => self.state.char_spacing = operands.first().map_or(0.0, number),
+        "Tw" => self.state.word_spacing = operands.first().map_or(0.0, number),
+        "Tz" => self.state.horizontal_scale = operands.first().map_or(100.0, number) / 100.0,
+        "TL" => self.state.leading = operands.first().map_or(0.0, number),
+        "Ts" => self.state.rise = operands.first().map_or(0.0, number),
+        "Td" => {
+          if let Some([tx, ty]) = numbers::<2>(operands) {
+            self.move_line(tx, ty);
+          }
+        }
+        "TD" => {
+          if let Some([tx, ty]) = numbers::<2>(operands) {
+            self.state.leading = -ty;
+            self.move_line(tx, ty);
+          }
+        }
+        "Tm" => {
+          if let Some(m) = numbers::<6>(operands) {
+            self.text_matrix = m;
+            self.line_matrix = m;
+          }
+        }
+        "T*" => self.next_line(),
+        "Tj" => {
+          if let Some(Object::String(bytes, _)) = operands.first() {
+            self.show(bytes);
+          }
+        }
+        "'" => {
+          self.next_line();
+          if let Some(Object::String(bytes, _)) = operands.first() {
+            self.show(bytes);
+          }
+        }
+        "\"" => {
+          if let [word_spacing, char_spacing, Object::String(bytes, _), ..] = operands {
+            self.state.word_spacing = number(word_spacing);
+            self.state.char_spacing = number(char_spacing);
+            self.next_line();
+            self.show(bytes);
+          }
+        }
+        "TJ" => {
+          if let Some(Object::Array(items)) = operands.first() {
+            for item in items {
+              match item {
+                Object::String(bytes, _) => self.show(bytes),
+                other => {
+                  let adjust =
+                    -number(other) / 1000.0 * self.state.font_size * self.state.horizontal_scale;
+                  self.text_matrix = multiply(&translate(adjust, 0.0), &self.text_matrix);
+                }
+              }
+            }
+          }
+        }
+        "Do" if depth < MAX_FORM_DEPTH => {
+          if let Some(name) = operands.first().and_then(|o| o.as_name().ok()) {
+            self.run_xobject(name, resources, depth);
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  fn run_xobject(&mut self, name: &[u8], resources: &Resources<'a>, depth: usize) {
+    let doc = self.doc;
+    let Some((id, object)) = resources
+      .xobjects
+      .and_then(|xobjects| xobjects.get(name).ok())
+      .and_then(|object| doc.dereference(object).ok())
+    else {
+      return;
+    };
+    let Ok(stream) = object.as_stream() else {
+      return;
+    };
+    match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+      Ok(b"Form") => {}
+      Ok(b"Image") => {
+        if let Some(id) = id {
+          self.place_image(id);
+        }
+        return;
+      }
+      _ => return,
+    }
+    let Ok(content) = stream
+      .get_plain_content()
+      .and_then(|data| Content::decode(&data))
+    else {
+      return;
+    };
+
+    let form_resources = Resources::for_form(doc, &stream.dict, resources);
+    let saved_text = (self.text_matrix, self.line_matrix);
+    self.stack.push(self.state.clone());
+    if let Some(m) = stream
+      .dict
+      .get(b"Matrix")
+      .and_then(Object::as_array)
+      .ok()
+      .and_then(|m| numbers::<6>(m))
+    {
+      self.state.ctm = multiply(&m, &self.state.ctm);
+    }
+    self.run(&content, &form_resources, depth + 1);
+    if let Some(state) = self.stack.pop() {
+      self.state = state;
+    }
+    (self.text_matrix, self.line_matrix) = saved_text;
+  }
+
+  /// Images are drawn into the unit square, so the CTM alone gives their bounds.
+  fn place_image(&mut self, id: ObjectId) {
+    let m = &self.state.ctm;
+    let xs = [m[4], m[4] + m[0], m[4] + m[2], m[4] + m[0] + m[2]];
+    let ys = [m[5], m[5] + m[1], m[5] + m[3], m[5] + m[1] + m[3]];
+    let max = |v: [f32; 4]| v.into_iter().fold(f32::MIN, f32::max);
+    let min = |v: [f32; 4]| v.into_iter().fold(f32::MAX, f32::min);
+    self.images.push(ImagePlacement {
+      id,
+      y: max(ys),
+      bottom: min(ys),
+      left: min(xs),
+      right: max(xs),
+    });
+  }
+
+  fn move_line(&mut self, tx: f32, ty: f32) {
+    self.line_matrix = multiply(&translate(tx, ty), &self.line_matrix);
+    self.text_matrix = self.line_matrix;
+  }
+
+  fn next_line(&mut self) {
+    self.move_line(0.0, -self.state.leading);
+  }
+
+  fn show(&mut self, bytes: &[u8]) {
+    let Some(font) = self.state.font.clone() else {
+      return;
+    };
+    let state = &self.state;
+    let render = multiply(
+      &multiply(&translate(0.0, state.rise), &self.text_matrix),
+      &state.ctm,
+    );
+    let x_scale = render[0].hypot(render[1]);
+    let y_scale = render[2].hypot(render[3]);
+    let font_size = state.font_size * y_scale;
+    // Page position of the point `advance` along the baseline.
+    let at = |advance: f32| {
+      (
+        render[4] + advance * render[0],
+        render[5] + advance * render[1],
+      )
+    };
+
+    // The string is cut into pieces wherever the link under the glyphs
+    // changes: (first byte, advance at its start, link index).
+    let mut pieces: Vec<(usize, f32, Option<usize>)> = Vec::new();
+    let mut advance = 0.0;
+    for (i, code) in font.codes(bytes).enumerate() {
+      let mut glyph = font.width(code) / 1000.0 * state.font_size + state.char_spacing;
+      if font.code_len == 1 && code == 32 {
+        glyph += state.word_spacing;
+      }
+      glyph *= state.horizontal_scale;
+      let (x, y) = at(advance + glyph / 2.0);
+      let link = self
+        .links
+        .iter()
+        .position(|link| link.covers(x, y, font_size));
+      if pieces.last().map(|piece| piece.2) != Some(link) {
+        pieces.push((i * font.code_len, advance, link));
+      }
+      advance += glyph;
+    }
+
+    for (n, &(start, offset, link)) in pieces.iter().enumerate() {
+      let (end, end_offset) = pieces
+        .get(n + 1)
+        .map_or((bytes.len(), advance), |next| (next.0, next.1));
+      let text = font.decode(&bytes[start..end]);
+      if text.is_empty() {
+        continue;
+      }
+      let (x, y) = at(offset);
+      self.spans.push(TextSpan {
+        text,
+        x,
+        y,
+        width: (end_offset - offset) * x_scale,
+        font_size,
+        font_name: font.name.clone(),
+        link: link.map(|i| self.links[i].uri.clone()),
+        style: font.style,
+        footnote: None,
+      });
+    }
+    self.text_matrix = multiply(&translate(advance, 0.0), &self.text_matrix);
+  }
+}
+
+/// Groups spans into lines by baseline and orders everything for reading.
+fn group_lines(spans: Vec<TextSpan>) -> Vec<TextLine> {
+  let same_baseline =
+    |a: &TextSpan, b: &TextSpan| (a.y - b.y).abs() < 0.5 * a.font_size.max(b.font_size);
+
+  // Consecutive spans in stream order that stay on one baseline and keep
+  // moving right form a run. Runs are never split up, so glyph widths we had
+  // to estimate can't interleave the characters of neighbouring runs.
+  let mut runs: Vec<Vec<TextSpan>> = Vec::new();
+  for span in spans {
+    if span.text.is_empty() || span.font_size <= 0.0 {
+      continue;
+    }
+    let extends_run = runs
+      .last()
+      .and_then(|run| run.last())
+      .is_some_and(|last| same_baseline(last, &span) && span.x >= last.x - 0.1 * span.font_size);
+    match runs.last_mut() {
+      Some(run) if extends_run => run.push(span),
+      _ => runs.push(vec![span]),
+    }
+  }
+  runs.sort_by(|a, b| b[0].y.total_cmp(&a[0].y).then(a[0].x.total_cmp(&b[0].x)));
+
+  let mut lines: Vec<Vec<Vec<TextSpan>>> = Vec::new();
+  for run in runs {
+    match lines.last_mut() {
+      Some(line) if same_baseline(&line[0][0], &run[0]) => line.push(run),
+      _ => lines.push(vec![run]),
+    }
+  }
+
+  lines
+    .into_iter()
+    .filter_map(|mut runs| {
+      runs.sort_by(|a, b| a[0].x.total_cmp(&b[0].x));
+      let spans = merge_spans(runs.into_iter().flatten().collect());
+      (!spans.is_empty()).then_some(TextLine { spans })
+    })
+    .collect()
+}
+
+/// Joins neighbouring spans of the same font and inserts the word gaps that
+/// PDFs usually encode as positioning rather than space characters.
+fn merge_spans(spans: Vec<TextSpan>) -> Vec<TextSpan> {
+  let mut merged: Vec<TextSpan> = Vec::with_capacity(spans.len());
+  for mut span in spans {
+    let blank = span.text.trim().is_empty();
+    let Some(prev) = merged.last_mut() else {
+      if !blank {
+        merged.push(span);
+      }
+      continue;
+    };
+    if blank {
+      // Explicit space glyphs only matter as separators.
+      if !prev.text.ends_with(char::is_whitespace) {
+        prev.text.push(' ');
+      }
+      prev.width = prev.width.max(span.right() - prev.x);
+      continue;
+    }
+    let gap = span.x - prev.right();
+    let needs_space = gap > 0.2 * span.font_size.min(prev.font_size)
+      && !prev.text.ends_with(char::is_whitespace)
+      && !span.text.starts_with(char::is_whitespace);
+    let same_font = prev.font_name == span.font_name
+      && (prev.font_size - span.font_size).abs() < 0.1
+      && (prev.y - span.y).abs() < 0.1
+      && prev.link == span.link;
+
+    // Wider gaps are kept apart; they are usually between table columns.
+    if same_font && gap < span.font_size {
+      if needs_space {
+        prev.text.push(' ');
+      }
+      prev.text.push_str(&span.text);
+      prev.width = span.right() - prev.x;
+    } else {
+      if needs_space {
+        span.text.insert(0, ' ');
+      }
+      merged.push(span);
+    }
+  }
+  merged
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lopdf::{dictionary, Stream};
+
+  /// The text of a page drawing `content` with Helvetica as `/F1`.
+  fn extract(content: &str) -> PageText {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+      "Type" => "Font",
+      "Subtype" => "Type1",
+      "BaseFont" => "Helvetica",
+    });
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.as_bytes().to_vec()));
+    let page_id = doc.add_object(dictionary! {
+      "Type" => "Page",
+      "Parent" => pages_id,
+      "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+      "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+      "Contents" => content_id,
+    });
+    doc.objects.insert(
+      pages_id,
+      Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![page_id.into()],
+        "Count" => 1,
+      }),
+    );
+    extract_page(&doc, page_id).unwrap()
+  }
+
+  /// Each line's text with the position and size of its first span.
+  fn placed(page: &PageText) -> Vec<(String, f32, f32, f32)> {
+    page
+      .lines
+      .iter()
+      .map(|line| (line.text(), line.spans[0].x, line.y(), line.font_size()))
+      .collect()
+  }
+
+  fn span(text: &str, x: f32, y: f32) -> TextSpan {
+    TextSpan {
+      text: text.to_string(),
+      x,
+      y,
+      width: 5.0 * text.len() as f32,
+      font_size: 10.0,
+      font_name: "Helvetica".into(),
+      link: None,
+      style: FontStyle::default(),
+      footnote: None,
+    }
+  }
+
+  #[test]
+  fn text_operators_move_the_text_position() {
+    let page = extract(
+      "BT /F1 10 Tf 72 700 Td (Hello) Tj 0 -14 Td (second) Tj 12 TL T* (third) Tj \
+       0 -14 TD (fourth) Tj T* (fifth) Tj 2 0 0 3 300 500 Tm (big) Tj ET",
+    );
+    assert_eq!(
+      placed(&page),
+      [
+        ("Hello".to_string(), 72.0, 700.0, 10.0),
+        ("second".to_string(), 72.0, 686.0, 10.0),
+        ("third".to_string(), 72.0, 674.0, 10.0),
+        ("fourth".to_string(), 72.0, 660.0, 10.0),
+        ("fifth".to_string(), 72.0, 646.0, 10.0),
+        ("big".to_string(), 300.0, 500.0, 30.0),
+      ]
+    );
+    // Three glyphs of Helvetica's estimated 520 units, stretched twice over.
+    assert!((page.lines[5].spans[0].width - 2.0 * 3.0 * 5.2).abs() < 1e-3);
+
+    // Turned a quarter to the left: text runs up the page from the origin.
+    let page = extract("q 0 1 -1 0 200 100 cm BT /F1 12 Tf 10 0 Td (up) Tj ET Q");
+    assert_eq!(placed(&page), [("up".to_string(), 200.0, 110.0, 12.0)]);
+  }
+
+  #[test]
+  fn tj_kerning_wide_enough_becomes_a_space() {
+    let page = extract("BT /F1 10 Tf 72 700 Td [(Hel) -50 (lo) -400 (world) -2000 (A1)] TJ ET");
+    let texts: Vec<&str> = page.lines[0]
+      .spans
+      .iter()
+      .map(|span| span.text.as_str())
+      .collect();
+    // A gap of a font size or more keeps the spans apart, as table cells.
+    assert_eq!(texts, ["Hello world", " A1"]);
+  }
+
+  #[test]
+  fn lines_group_spans_within_half_a_font_size_of_a_baseline() {
+    let lines = group_lines(vec![
+      span("below", 72.0, 694.0),
+      span("right", 120.0, 702.0),
+      span("left", 72.0, 700.0),
+    ]);
+    let texts: Vec<String> = lines.iter().map(TextLine::text).collect();
+    assert_eq!(texts, ["left right", "below"]);
+  }
+
+  #[test]
+  fn cid_widths_take_both_forms() {
+    let doc = Document::with_version("1.5");
+    let array = [
+      1.into(),
+      Object::Array(vec![500.into(), 600.into()]),
+      10.into(),
+      12.into(),
+      300.into(),
+    ];
+    let mut widths = BTreeMap::new();
+    parse_cid_widths(&doc, &array, &mut widths);
+    let expected = [
+      (1, 500.0),
+      (2, 600.0),
+      (10, 300.0),
+      (11, 300.0),
+      (12, 300.0),
+    ];
+    assert_eq!(widths, BTreeMap::from(expected));
+  }
+}
@@ -0,0 +1,65 @@
+//! Native drag-and-drop of files onto the main window.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::{DragDropEvent, Emitter, WebviewWindow, WindowEvent};
+
+use crate::batch::is_pdf;
+
+/// Payload of the `files-dropped` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilesDropped {
+  /// The dropped PDFs, in the order they were dropped.
+  pub paths: Vec<String>,
+  /// Everything else that was dropped, so the UI can say only PDFs are supported.
+  pub rejected: Vec<String>,
+}
+
+/// Emits `files-dropped` whenever files are dropped onto `window`.
+pub fn listen(window: &WebviewWindow) {
+  let handle = window.clone();
+  window.on_window_event(move |event| {
+    let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else {
+      return;
+    };
+    if paths.is_empty() {
+      return;
+    }
+    if let Err(err) = handle.emit("files-dropped", split_pdfs(paths)) {
+      log::warn!("failed to emit dropped files: {err}");
+    }
+  });
+}
+
+fn split_pdfs(paths: &[PathBuf]) -> FilesDropped {
+  let (pdfs, rejected): (Vec<&PathBuf>, Vec<&PathBuf>) = paths
+    .iter()
+    .partition(|path| is_pdf(path) && !path.is_dir());
+  let strings = |paths: Vec<&PathBuf>| paths.iter().map(|p| p.display().to_string()).collect();
+  FilesDropped {
+    paths: strings(pdfs),
+    rejected: strings(rejected),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keeps_every_pdf_and_reports_the_rest() {
+    let dropped: Vec<PathBuf> = ["a.pdf", "notes.txt", "B.PDF", "image.png"]
+      .into_iter()
+      .map(PathBuf::from)
+      .collect();
+    assert_eq!(
+      split_pdfs(&dropped),
+      FilesDropped {
+        paths: vec!["a.pdf".into(), "B.PDF".into()],
+        rejected: vec!["notes.txt".into(), "image.png".into()],
+      }
+    );
+  }
+}
@@ -0,0 +1,325 @@
+//! Footnotes: the marks raised in the text and the notes in small print at
+//! the foot of the page that they refer to.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::document::{Block, Inline, List, Page};
+use crate::extract::TextLine;
+use crate::math;
+
+/// Notes are set smaller than this, relative to the body text.
+const NOTE_SIZE: f32 = 0.9;
+
+/// Lines of one note are at most this far apart, relative to their size.
+const NOTE_SPACING: f32 = 2.0;
+
+/// Footnote marks are at most this many characters long.
+const MAX_MARK_LEN: usize = 3;
+
+/// A footnote as found on its page: its mark and its lines, the mark taken
+/// off.
+pub struct Note {
+  pub mark: String,
+  pub lines: Vec<TextLine>,
+}
+
+/// Takes the footnotes out of `lines`, a page's lines in reading order, and
+/// labels the marks that refer to them with [`TextSpan::footnote`]. Returns
+/// the notes in order.
+///
+/// A note is small print at the foot of a column, starting with its mark. It
+/// only counts as one if a superscript with the same mark refers to it from
+/// the text; otherwise its lines, like superscripts without a note, are left
+/// as they are.
+///
+/// [`TextSpan::footnote`]: crate::extract::TextSpan::footnote
+pub fn split(lines: &mut Vec<TextLine>, body_size: f32) -> Vec<Note> {
+  let runs = foot_runs(lines, body_size);
+  let in_run = |i: usize| runs.iter().any(|run| run.contains(&i));
+  // The marks superscripts in the text refer to.
+  let referenced: HashSet<String> = (0..lines.len())
+    .filter(|&i| !in_run(i))
+    .flat_map(|i| references(&lines[i]).map(|(_, mark)| mark))
+    .collect();
+
+  // Each note with the lines it spans, a run holding one or more of them.
+  // A mark can only refer to one note, so a line starting with one already
+  // taken carries on the note before.
+  let mut found: Vec<(String, std::ops::Range<usize>)> = Vec::new();
+  let mut taken = HashSet::new();
+  for run in &runs {
+    let mut starts: Vec<(usize, String)> = Vec::new();
+    for i in run.clone() {
+      match mark(&lines[i]) {
+        Some((mark, _)) if referenced.contains(&mark) && !taken.contains(&mark) => {
+          taken.insert(mark.clone());
+          starts.push((i, mark));
+        }
+        _ if starts.is_empty() => break,
+        _ => {}
+      }
+    }
+    if starts
+      .first()
+      .map_or(true, |&(start, _)| start != run.start)
+    {
+      continue;
+    }
+    for (n, (start, mark)) in starts.iter().enumerate() {
+      let end = starts.get(n + 1).map_or(run.end, |&(next, _)| next);
+      found.push((mark.clone(), *start..end));
+    }
+  }
+  if found.is_empty() {
+    return Vec::new();
+  }
+
+  let marks: HashSet<&str> = found.iter().map(|(mark, _)| mark.as_str()).collect();
+  for line in lines.iter_mut() {
+    let marked: Vec<(usize, String)> = references(line)
+      .filter(|(_, mark)| marks.contains(mark.as_str()))
+      .collect();
+    for (span, mark) in marked {
+      line.spans[span].footnote = Some(mark);
+    }
+  }
+  let notes: Vec<Note> = found
+    .iter()
+    .map(|(mark, range)| {
+      let mut note: Vec<TextLine> = lines[range.clone()].to_vec();
+      if let Some((_, rest)) = self::mark(&note[0]) {
+        note[0] = rest;
+      }
+      Note {
+        mark: mark.clone(),
+        lines: note,
+      }
+    })
+    .collect();
+  let mut index = 0;
+  lines.retain(|_| {
+    index += 1;
+    !found.iter().any(|(_, range)| range.contains(&(index - 1)))
+  });
+  notes
+}
+
+/// Runs of small print ending a column: consecutive lines set smaller than
+/// the body text, each a little below the one before, and not followed by
+/// more text of the column.
+fn foot_runs(lines: &[TextLine], body_size: f32) -> Vec<std::ops::Range<usize>> {
+  let small = |line: &TextLine| line.font_size() < NOTE_SIZE * body_size;
+  // Whether `next` carries on the column of `line` at note spacing.
+  let follows = |line: &TextLine, next: &TextLine| {
+    let gap = line.y() - next.y();
+    gap > 0.0 && gap <= NOTE_SPACING * line.font_size().max(next.font_size())
+  };
+  let mut runs = Vec::new();
+  let mut i = 0;
+  while i < lines.len() {
+    if !small(&lines[i]) {
+      i += 1;
+      continue;
+    }
+    let start = i;
+    while i + 1 < lines.len() && small(&lines[i + 1]) && follows(&lines[i], &lines[i + 1]) {
+      i += 1;
+    }
+    i += 1;
+    if lines
+      .get(i)
+      .map_or(true, |next| !follows(&lines[i - 1], next))
+    {
+      runs.push(start..i);
+    }
+  }
+  runs
+}
+
+/// The mark `line` starts with, and the line without it: a short span of
+/// digits or note symbols of its own, or such a word before a space.
+fn mark(line: &TextLine) -> Option<(String, TextLine)> {
+  let first = line.spans.first()?;
+  let text = first.text.trim_start();
+  if is_mark(text.trim_end()) && line.spans.len() > 1 {
+    let rest = TextLine {
+      spans: line.spans[1..].to_vec(),
+    };
+    return Some((text.trim_end().to_string(), rest));
+  }
+  let (word, after) = text.split_once(char::is_whitespace)?;
+  if !is_mark(word) || after.trim().is_empty() {
+    return None;
+  }
+  let mut rest = line.clone();
+  rest.spans[0].text = after.trim_start().to_string();
+  Some((word.to_string(), rest))
+}
+
+/// The superscripts of `line` that could be footnote marks, by index.
+fn references(line: &TextLine) -> impl Iterator<Item = (usize, String)> + '_ {
+  line.spans.iter().enumerate().filter_map(move |(i, span)| {
+    let text = span.text.trim();
+    (i > 0 && is_mark(text) && math::is_superscript(span, line)).then(|| (i, text.to_string()))
+  })
+}
+
+fn is_mark(text: &str) -> bool {
+  let count = text.chars().count();
+  (1..=MAX_MARK_LEN).contains(&count)
+    && (text.chars().all(|c| c.is_ascii_digit()) || text.chars().all(|c| "*†‡§¶".contains(c)))
+}
+
+/// Numbers the footnotes 1, 2, 3, ... through a document, page after page,
+/// so notes on different pages that used the same mark get labels of their
+/// own.
+#[derive(Debug, Default)]
+pub struct Numbering {
+  last: usize,
+}
+
+impl Numbering {
+  /// Relabels the notes of `page` and the references to them, carrying on
+  /// from the pages before.
+  pub fn page(&mut self, page: &mut Page) {
+    let mut labels = HashMap::new();
+    for note in &mut page.footnotes {
+      self.last += 1;
+      let label = self.last.to_string();
+      labels.insert(std::mem::replace(&mut note.label, label.clone()), label);
+    }
+    if !labels.is_empty() {
+      references_mut(&mut page.blocks, &mut |label| {
+        if let Some(new) = labels.get(label.as_str()) {
+          label.clone_from(new);
+        }
+      });
+    }
+  }
+}
+
+/// Numbers the footnotes of `pages` from 1.
+pub fn number(pages: &mut [Page]) {
+  let mut numbering = Numbering::default();
+  for page in pages {
+    numbering.page(page);
+  }
+}
+
+/// The labels `blocks` refer to.
+pub fn referenced(blocks: &mut [Block]) -> HashSet<String> {
+  let mut labels = HashSet::new();
+  references_mut(blocks, &mut |label| {
+    labels.insert(label.clone());
+  });
+  labels
+}
+
+/// Calls `f` with the label of every footnote reference in `blocks`.
+fn references_mut(blocks: &mut [Block], f: &mut impl FnMut(&mut String)) {
+  fn inlines(content: &mut [Inline], f: &mut impl FnMut(&mut String)) {
+    for inline in content {
+      match inline {
+        Inline::FootnoteRef(label) => f(label),
+        Inline::Link { content, .. } | Inline::Strong(content) | Inline::Emphasis(content) => {
+          inlines(content, f)
+        }
+        Inline::Text(_) | Inline::Code(_) | Inline::Url(_) | Inline::Math(_) => {}
+      }
+    }
+  }
+  fn list(items: &mut List, f: &mut impl FnMut(&mut String)) {
+    for item in &mut items.items {
+      inlines(&mut item.content, f);
+      for child in &mut item.children {
+        list(child, f);
+      }
+    }
+  }
+  for block in blocks {
+    match block {
+      Block::Heading { content, .. } | Block::Paragraph(content) => inlines(content, f),
+      Block::List(items) => list(items, f),
+      Block::Quote(blocks) => references_mut(blocks, f),
+      Block::Table(_) | Block::Code(_) | Block::Image { .. } | Block::Math(_) => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::extract::TextSpan;
+
+  fn span(text: &str, x: f32, y: f32, font_size: f32) -> TextSpan {
+    TextSpan {
+      text: text.to_string(),
+      x,
+      y,
+      width: text.len() as f32 * font_size * 0.5,
+      font_size,
+      font_name: "Times-Roman".into(),
+      link: None,
+      style: Default::default(),
+      footnote: None,
+    }
+  }
+
+  #[test]
+  fn notes_at_the_foot_are_matched_to_their_marks() {
+    let mut lines = vec![
+      TextLine {
+        spans: vec![
+          span("As reported", 72.0, 700.0, 10.0),
+          span("1", 130.0, 704.0, 6.0),
+        ],
+      },
+      TextLine {
+        spans: vec![
+          span("and squared", 72.0, 688.0, 10.0),
+          span("2", 130.0, 692.0, 6.0),
+        ],
+      },
+      TextLine {
+        spans: vec![
+          span("1", 72.0, 80.0, 5.0),
+          span("In the annual report.", 76.0, 78.0, 8.0),
+        ],
+      },
+      TextLine {
+        spans: vec![span("Restated.", 72.0, 70.0, 8.0)],
+      },
+    ];
+    let notes = split(&mut lines, 10.0);
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].mark, "1");
+    let text: Vec<String> = notes[0].lines.iter().map(TextLine::text).collect();
+    assert_eq!(text, ["In the annual report.", "Restated."]);
+    // The second superscript has no note, so it stays as it was.
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].spans[1].footnote.as_deref(), Some("1"));
+    assert_eq!(lines[1].spans[1].footnote, None);
+  }
+
+  #[test]
+  fn numbering_carries_on_across_pages() {
+    let page = |number| Page {
+      number,
+      blocks: vec![Block::Paragraph(vec![
+        Inline::Text("Text".into()),
+        Inline::FootnoteRef("1".into()),
+      ])],
+      footnotes: vec![crate::document::Footnote {
+        label: "1".into(),
+        content: vec![Inline::Text("Note".into())],
+      }],
+    };
+    let mut pages = [page(1), page(2)];
+    number(&mut pages);
+    assert_eq!(pages[1].footnotes[0].label, "2");
+    assert_eq!(
+      referenced(&mut pages[1].blocks),
+      HashSet::from(["2".to_string()])
+    );
+  }
+}
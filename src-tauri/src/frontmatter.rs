@@ -0,0 +1,207 @@
+//! Document metadata from the PDF's information dictionary, and the YAML
+//! front matter Markdown output carries it in.
+
+use lopdf::{Document, Object};
+
+use crate::options::ConvertOptions;
+
+/// Info dictionary entries that make it into the metadata, with the key
+/// they're written under.
+const FIELDS: [(&[u8], &str); 4] = [
+  (b"Title", "title"),
+  (b"Author", "author"),
+  (b"Subject", "subject"),
+  (b"CreationDate", "date"),
+];
+
+/// The metadata of `doc` as `(key, value)` pairs, or nothing if `options`
+/// don't ask for front matter. Missing and empty fields are left out, and the
+/// creation date is converted to ISO 8601.
+pub fn metadata(doc: &Document, options: &ConvertOptions) -> Vec<(String, String)> {
+  if !options.emit_frontmatter {
+    return Vec::new();
+  }
+  let mut fields = Vec::new();
+  for (name, key) in FIELDS {
+    let Some(value) = info_field(doc, name) else {
+      continue;
+    };
+    if key == "date" {
+      match iso_date(&value) {
+        Some(date) => fields.push((key.to_string(), date)),
+        None => log::debug!("ignoring unparseable creation date {value:?}"),
+      }
+    } else {
+      fields.push((key.to_string(), value));
+    }
+  }
+  fields
+}
+
+/// The entry `name` of the PDF's information dictionary, as text, if it is
+/// there and not blank.
+pub fn info_field(doc: &Document, name: &[u8]) -> Option<String> {
+  let info = doc
+    .trailer
+    .get_deref(b"Info", doc)
+    .and_then(Object::as_dict)
+    .ok()?;
+  let value = info
+    .get_deref(name, doc)
+    .ok()
+    .and_then(|value| lopdf::decode_text_string(value).ok())?;
+  let value = value.replace('\0', "");
+  let value = value.trim();
+  (!value.is_empty()).then(|| value.to_string())
+}
+
+/// The `---`-delimited front matter block for `metadata`, followed by a blank
+/// line, or an empty string if there is no metadata.
+pub fn yaml(metadata: &[(String, String)]) -> String {
+  if metadata.is_empty() {
+    return String::new();
+  }
+  let lines: Vec<String> = metadata
+    .iter()
+    .map(|(key, value)| match key.as_str() {
+      // Dates are left for YAML to read as timestamps.
+      "date" => format!("{key}: {value}"),
+      _ => format!("{key}: {}", yaml_string(value)),
+    })
+    .collect();
+  format!("---\n{}\n---\n\n", lines.join("\n"))
+}
+
+/// Converts a PDF date (`D:YYYYMMDDHHmmSSOHH'mm'`, everything after the year
+/// optional) to ISO 8601: a plain date if the time is missing, otherwise a
+/// date and time with the offset if one is given.
+fn iso_date(pdf: &str) -> Option<String> {
+  let s = pdf.strip_prefix("D:").unwrap_or(pdf);
+  let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+  if digits < 4 || digits % 2 != 0 || digits > 14 {
+    return None;
+  }
+  let field = |start: usize, default: u32| {
+    s.get(start..start + 2)
+      .filter(|_| start + 2 <= digits)
+      .map_or(Some(default), |f| f.parse().ok())
+  };
+  let year = &s[..4];
+  let (month, day) = (field(4, 1)?, field(6, 1)?);
+  let (hour, minute, second) = (field(8, 0)?, field(10, 0)?, field(12, 0)?);
+  if !(1..=12).contains(&month)
+    || !(1..=31).contains(&day)
+    || hour > 23
+    || minute > 59
+    || second > 59
+  {
+    return None;
+  }
+  let date = format!("{year}-{month:02}-{day:02}");
+  if digits <= 8 {
+    return Some(date);
+  }
+
+  let offset = match s[digits..].chars().next() {
+    None => String::new(),
+    Some('Z') => "Z".to_string(),
+    Some(sign @ ('+' | '-')) => {
+      // `HH'mm'`, with the apostrophes and minutes often left out.
+      let rest: String = s[digits + 1..]
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+      let hours: u32 = rest.get(..2)?.parse().ok()?;
+      let minutes: u32 = rest.get(2..4).map_or(Some(0), |m| m.parse().ok())?;
+      if hours > 23 || minutes > 59 {
+        return None;
+      }
+      format!("{sign}{hours:02}:{minutes:02}")
+    }
+    Some(_) => return None,
+  };
+  Some(format!("{date}T{hour:02}:{minute:02}:{second:02}{offset}"))
+}
+
+/// `value` as a YAML scalar: as is when that's unambiguous, double-quoted
+/// otherwise.
+fn yaml_string(value: &str) -> String {
+  let reserved = ["true", "false", "yes", "no", "on", "off", "null", "~"];
+  let needs_quotes = value.starts_with(|c: char| "-?:,[]{}#&*!|>%@`".contains(c))
+    || value.contains([':', '#', '"', '\'', '\\'])
+    || value.chars().any(char::is_control)
+    || reserved.contains(&value.to_ascii_lowercase().as_str())
+    || value.parse::<f64>().is_ok();
+  if !needs_quotes {
+    return value.to_string();
+  }
+  let mut out = String::with_capacity(value.len() + 2);
+  out.push('"');
+  for c in value.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\t' => out.push_str("\\t"),
+      c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lopdf::dictionary;
+
+  #[test]
+  fn converts_pdf_dates_to_iso_8601() {
+    assert_eq!(iso_date("D:20210315").as_deref(), Some("2021-03-15"));
+    assert_eq!(iso_date("D:2021").as_deref(), Some("2021-01-01"));
+    assert_eq!(
+      iso_date("D:20210315102030+01'00'").as_deref(),
+      Some("2021-03-15T10:20:30+01:00")
+    );
+    assert_eq!(
+      iso_date("20210315102030Z").as_deref(),
+      Some("2021-03-15T10:20:30Z")
+    );
+    assert_eq!(iso_date("D:20211345"), None);
+    assert_eq!(iso_date("yesterday"), None);
+  }
+
+  #[test]
+  fn quotes_titles_that_would_confuse_yaml() {
+    assert_eq!(yaml_string("Annual Report"), "Annual Report");
+    assert_eq!(yaml_string("IFRS: A Guide"), "\"IFRS: A Guide\"");
+    assert_eq!(
+      yaml_string("The \"Best\" Year"),
+      "\"The \\\"Best\\\" Year\""
+    );
+    assert_eq!(yaml_string("2021"), "\"2021\"");
+    assert_eq!(yaml_string("- draft"), "\"- draft\"");
+  }
+
+  #[test]
+  fn writes_only_the_fields_present() {
+    let mut doc = Document::with_version("1.5");
+    let info = doc.add_object(dictionary! {
+      "Title" => Object::string_literal("Report: 2021"),
+      "Author" => Object::string_literal(""),
+      "CreationDate" => Object::string_literal("D:20210315"),
+    });
+    doc.trailer.set("Info", info);
+
+    let mut options = ConvertOptions::default();
+    assert!(metadata(&doc, &options).is_empty());
+    options.emit_frontmatter = true;
+    let metadata = metadata(&doc, &options);
+    assert_eq!(
+      yaml(&metadata),
+      "---\ntitle: \"Report: 2021\"\ndate: 2021-03-15\n---\n\n"
+    );
+    assert_eq!(yaml(&[]), "");
+  }
+}
@@ -0,0 +1,192 @@
+//! Heading detection from font sizes and styles.
+//!
+//! The body text size is the most common font size in the document. A
+//! paragraph set noticeably larger than that becomes a heading, the level
+//! depending on how much larger it is; being short and bold, or short and in
+//! capitals, counts in its favour.
+
+use crate::extract::{PageText, TextLine};
+
+/// Paragraphs with more lines than this are running text, whatever their size.
+const MAX_HEADING_LINES: usize = 3;
+
+/// Headings longer than this are unusual; text this long only counts if it
+/// is also much larger than the body.
+const SHORT_HEADING_CHARS: usize = 80;
+
+/// Nothing longer than this is a heading (long titles run to about 120).
+const MAX_HEADING_CHARS: usize = 120;
+
+/// Score needed for `#`, `##` and `###`. The score is how much larger than
+/// the body the text is (0.2 = 20% larger) plus the style bonuses, times the
+/// sensitivity.
+const LEVEL_THRESHOLDS: [f32; 3] = [0.6, 0.3, 0.12];
+
+const BOLD_BONUS: f32 = 0.15;
+const CAPS_BONUS: f32 = 0.15;
+
+/// The most common font size across `pages`, weighted by character count.
+pub fn body_font_size<'a>(pages: impl IntoIterator<Item = &'a PageText>) -> f32 {
+  let mut sizes = FontSizes::default();
+  for page in pages {
+    sizes.add(page);
+  }
+  sizes.body_size()
+}
+
+/// Character counts per font size, gathered a page at a time so the pages
+/// don't all have to be kept around.
+#[derive(Debug, Default)]
+pub struct FontSizes {
+  /// Sizes are bucketed to half points; sizes computed from text matrices are
+  /// rarely exact.
+  counts: Vec<(i32, usize)>,
+}
+
+impl FontSizes {
+  pub fn add(&mut self, page: &PageText) {
+    for span in page.lines.iter().flat_map(|line| &line.spans) {
+      let bucket = (span.font_size * 2.0).round() as i32;
+      let chars = span.text.trim().chars().count();
+      match self.counts.iter_mut().find(|(b, _)| *b == bucket) {
+        Some((_, count)) => *count += chars,
+        None => self.counts.push((bucket, chars)),
+      }
+    }
+  }
+
+  /// The most common size so far, weighted by character count.
+  pub fn body_size(&self) -> f32 {
+    self
+      .counts
+      .iter()
+      .max_by_key(|&&(bucket, count)| (count, -bucket))
+      .map_or(0.0, |&(bucket, _)| bucket as f32 / 2.0)
+  }
+}
+
+/// The heading level (1–3) of a paragraph, or `None` for body text.
+///
+/// `sensitivity` scales how readily text is promoted: 0 disables headings,
+/// values above 1 promote more.
+pub fn heading_level(paragraph: &[TextLine], body_size: f32, sensitivity: f32) -> Option<usize> {
+  if paragraph.is_empty() || paragraph.len() > MAX_HEADING_LINES || body_size <= 0.0 {
+    return None;
+  }
+  let text: String = paragraph
+    .iter()
+    .map(|line| line.text())
+    .collect::<Vec<_>>()
+    .join(" ");
+  let text = text.trim();
+  // Page numbers and the like.
+  if !text.chars().any(char::is_alphabetic) {
+    return None;
+  }
+
+  let size = paragraph
+    .iter()
+    .map(TextLine::font_size)
+    .fold(0.0, f32::max);
+  let growth = size / body_size - 1.0;
+  if growth < -0.05 {
+    return None;
+  }
+
+  let chars = text.chars().count();
+  if chars > MAX_HEADING_CHARS {
+    return None;
+  }
+  let short = chars <= SHORT_HEADING_CHARS && paragraph.len() <= 2 && !text.ends_with(['.', ',']);
+  let bold = paragraph
+    .iter()
+    .flat_map(|line| &line.spans)
+    .all(|span| span.is_bold());
+  let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+  let caps = letters.len() >= 3 && letters.iter().all(|c| c.is_uppercase());
+
+  let mut score = growth.max(0.0);
+  if !short && score < LEVEL_THRESHOLDS[0] {
+    return None;
+  }
+  if short && bold {
+    score += BOLD_BONUS;
+  }
+  if short && caps {
+    score += CAPS_BONUS;
+  }
+  score *= sensitivity;
+  LEVEL_THRESHOLDS
+    .iter()
+    .position(|&threshold| score >= threshold)
+    .map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::extract::TextSpan;
+
+  fn line(text: &str, size: f32, font: &str) -> TextLine {
+    TextLine {
+      spans: vec![TextSpan {
+        text: text.to_string(),
+        x: 0.0,
+        y: 0.0,
+        width: 0.5 * size * text.len() as f32,
+        font_size: size,
+        font_name: font.to_string(),
+        link: None,
+        style: Default::default(),
+        footnote: None,
+      }],
+    }
+  }
+
+  #[test]
+  fn body_size_is_the_most_common_size() {
+    let page = PageText {
+      lines: vec![
+        line("Title", 24.0, "Helvetica"),
+        line("A long line of ordinary body text.", 10.0, "Helvetica"),
+        line("Another line of body text here.", 10.0, "Helvetica"),
+      ],
+      images: Vec::new(),
+    };
+    assert_eq!(body_font_size([&page]), 10.0);
+  }
+
+  #[test]
+  fn larger_text_gets_higher_levels() {
+    let level = |size| heading_level(&[line("Introduction", size, "Helvetica")], 10.0, 1.0);
+    assert_eq!(level(20.0), Some(1));
+    assert_eq!(level(14.0), Some(2));
+    assert_eq!(level(12.0), Some(3));
+    assert_eq!(level(10.0), None);
+  }
+
+  #[test]
+  fn short_bold_or_capitalized_lines_are_promoted() {
+    let bold = [line("Results", 10.0, "Helvetica-Bold")];
+    let caps = [line("METHODS", 10.0, "Helvetica")];
+    assert_eq!(heading_level(&bold, 10.0, 1.0), Some(3));
+    assert_eq!(heading_level(&caps, 10.0, 1.0), Some(3));
+    // Both together are a stronger signal.
+    let both = [line("METHODS", 10.0, "Helvetica-Bold")];
+    assert_eq!(heading_level(&both, 10.0, 1.0), Some(2));
+  }
+
+  #[test]
+  fn sentences_and_numbers_stay_body_text() {
+    let sentence = [line("This ends like a sentence.", 12.0, "Helvetica-Bold")];
+    let number = [line("12", 14.0, "Helvetica")];
+    assert_eq!(heading_level(&sentence, 10.0, 1.0), None);
+    assert_eq!(heading_level(&number, 10.0, 1.0), None);
+  }
+
+  #[test]
+  fn zero_sensitivity_disables_headings() {
+    let title = [line("Introduction", 20.0, "Helvetica-Bold")];
+    assert_eq!(heading_level(&title, 10.0, 0.0), None);
+  }
+}
@@ -0,0 +1,157 @@
+//! The list of recent conversions, kept in a SQLite database in the app's
+//! data directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::ConversionError;
+
+const HISTORY_FILE: &str = "history.sqlite";
+
+/// Entries returned by `list_history` when no limit is given.
+const DEFAULT_LIMIT: u32 = 50;
+
+const SCHEMA: &str = "
+  CREATE TABLE IF NOT EXISTS conversions (
+    id INTEGER PRIMARY KEY,
+    source TEXT NOT NULL,
+    output TEXT,
+    converted_at INTEGER NOT NULL,
+    page_count INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL
+  );
+  CREATE INDEX IF NOT EXISTS conversions_by_time ON conversions (converted_at);
+";
+
+/// One finished conversion.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+  pub source: String,
+  /// Where the result was written, if it was saved to a file.
+  pub output: Option<String>,
+  /// Milliseconds since the Unix epoch.
+  pub converted_at: i64,
+  pub page_count: u32,
+  pub duration_ms: u64,
+}
+
+/// Adds a conversion to the history.
+#[tauri::command]
+pub fn record_conversion(app: AppHandle, entry: HistoryEntry) -> Result<(), ConversionError> {
+  open(&history_path(&app)?)?.record(&entry)
+}
+
+/// Returns the `limit` (by default 50) most recent conversions, newest first.
+#[tauri::command]
+pub fn list_history(
+  app: AppHandle,
+  limit: Option<u32>,
+) -> Result<Vec<HistoryEntry>, ConversionError> {
+  open(&history_path(&app)?)?.list(limit.unwrap_or(DEFAULT_LIMIT))
+}
+
+/// Forgets every conversion, returning how many there were.
+#[tauri::command]
+pub fn clear_history(app: AppHandle) -> Result<usize, ConversionError> {
+  open(&history_path(&app)?)?.clear()
+}
+
+fn history_path(app: &AppHandle) -> tauri::Result<PathBuf> {
+  Ok(app.path().app_data_dir()?.join(HISTORY_FILE))
+}
+
+/// An open history database.
+struct History(Connection);
+
+/// Opens the database at `path`, creating it and its tables on first use.
+fn open(path: &Path) -> Result<History, ConversionError> {
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)
+      .map_err(|e| ConversionError::io(format_args!("cannot create {}", dir.display()), e))?;
+  }
+  let connection = Connection::open(path)?;
+  connection.execute_batch(SCHEMA)?;
+  Ok(History(connection))
+}
+
+impl History {
+  fn record(&self, entry: &HistoryEntry) -> Result<(), ConversionError> {
+    self.0.execute(
+      "INSERT INTO conversions (source, output, converted_at, page_count, duration_ms)
+       VALUES (?1, ?2, ?3, ?4, ?5)",
+      params![
+        entry.source,
+        entry.output,
+        entry.converted_at,
+        entry.page_count,
+        entry.duration_ms
+      ],
+    )?;
+    Ok(())
+  }
+
+  fn list(&self, limit: u32) -> Result<Vec<HistoryEntry>, ConversionError> {
+    let mut statement = self.0.prepare(
+      "SELECT source, output, converted_at, page_count, duration_ms FROM conversions
+       ORDER BY converted_at DESC, id DESC LIMIT ?1",
+    )?;
+    let entries = statement
+      .query_map([limit], |row| {
+        Ok(HistoryEntry {
+          source: row.get(0)?,
+          output: row.get(1)?,
+          converted_at: row.get(2)?,
+          page_count: row.get(3)?,
+          duration_ms: row.get(4)?,
+        })
+      })?
+      .collect::<Result<_, _>>()?;
+    Ok(entries)
+  }
+
+  fn clear(&self) -> Result<usize, ConversionError> {
+    Ok(self.0.execute("DELETE FROM conversions", [])?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(source: &str, converted_at: i64) -> HistoryEntry {
+    HistoryEntry {
+      source: source.into(),
+      output: Some(format!("{source}.md")),
+      converted_at,
+      page_count: 3,
+      duration_ms: 120,
+    }
+  }
+
+  #[test]
+  fn lists_newest_first_up_to_the_limit() {
+    let dir = std::env::temp_dir().join(format!("pdf2markdown-history-{}", std::process::id()));
+    let path = dir.join(HISTORY_FILE);
+    let history = open(&path).unwrap();
+    for (source, at) in [("a.pdf", 1_000), ("c.pdf", 3_000), ("b.pdf", 2_000)] {
+      history.record(&entry(source, at)).unwrap();
+    }
+    drop(history);
+
+    // Reopening finds the existing tables and rows.
+    let history = open(&path).unwrap();
+    assert_eq!(
+      history.list(2).unwrap(),
+      vec![entry("c.pdf", 3_000), entry("b.pdf", 2_000)]
+    );
+    assert_eq!(history.clear().unwrap(), 3);
+    assert!(history.list(10).unwrap().is_empty());
+    drop(history);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,201 @@
+//! HTML rendering of the document model.
+
+use crate::document::{self, Block, Footnote, Inline, List};
+use crate::toc::Anchors;
+
+/// With metadata, a complete HTML document carrying it in the `<head>`;
+/// without, just the body's elements, ready to embed.
+pub fn document(metadata: &[(String, String)], body: &str) -> String {
+  format!("{}{body}{}", head(metadata), tail(metadata))
+}
+
+/// What comes before the body: up to `<body>` with metadata, else nothing.
+pub fn head(metadata: &[(String, String)]) -> String {
+  if metadata.is_empty() {
+    return String::new();
+  }
+  let mut head = vec!["<meta charset=\"utf-8\">".to_string()];
+  for (key, value) in metadata {
+    if key == "title" {
+      head.push(format!("<title>{}</title>", escape(value)));
+    } else {
+      head.push(format!(
+        "<meta name=\"{}\" content=\"{}\">",
+        escape(key),
+        escape(value)
+      ));
+    }
+  }
+  format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n{}\n</head>\n<body>\n",
+    head.join("\n")
+  )
+}
+
+pub fn tail(metadata: &[(String, String)]) -> String {
+  if metadata.is_empty() {
+    "\n".to_string()
+  } else {
+    "\n</body>\n</html>\n".to_string()
+  }
+}
+
+/// `block` as HTML, a heading with the next of `anchors` as its id.
+pub fn block(block: &Block, anchors: &mut Anchors) -> String {
+  match block {
+    Block::Heading { level, content } => {
+      let level = (*level).clamp(1, 6);
+      // The id a table of contents links to.
+      let id = anchors.next(document::plain(content).trim());
+      format!(
+        "<h{level} id=\"{}\">{}</h{level}>",
+        escape(&id),
+        inlines(content)
+      )
+    }
+    Block::Paragraph(content) => format!("<p>{}</p>", inlines(content)),
+    Block::List(items) => list(items),
+    Block::Table(rows) => table(rows),
+    Block::Code(text) => format!("<pre><code>{}</code></pre>", escape(text)),
+    Block::Image { src } => format!("<img src=\"{}\" alt=\"\">", escape(src)),
+    Block::Math(tex) => format!("<div class=\"math display\">\\[{}\\]</div>", escape(tex)),
+    Block::Quote(blocks) => {
+      let inner: Vec<String> = blocks.iter().map(|b| self::block(b, anchors)).collect();
+      format!("<blockquote>\n{}\n</blockquote>", inner.join("\n"))
+    }
+  }
+}
+
+fn inlines(content: &[Inline]) -> String {
+  content
+    .iter()
+    .map(|inline| match inline {
+      Inline::Text(text) => escape(text),
+      Inline::Code(code) => format!("<code>{}</code>", escape(code)),
+      Inline::Link { content, url } => {
+        format!("<a href=\"{}\">{}</a>", escape(url), inlines(content))
+      }
+      Inline::Url(url) => format!("<a href=\"{0}\">{0}</a>", escape(url)),
+      Inline::Math(tex) => format!("<span class=\"math inline\">\\({}\\)</span>", escape(tex)),
+      Inline::Strong(content) => format!("<strong>{}</strong>", inlines(content)),
+      Inline::Emphasis(content) => format!("<em>{}</em>", inlines(content)),
+      Inline::FootnoteRef(label) => {
+        format!("<sup><a href=\"#fn-{0}\">{0}</a></sup>", escape(label))
+      }
+    })
+    .collect()
+}
+
+/// The footnotes as a section of paragraphs, each with the id its
+/// references link to.
+pub fn footnotes(footnotes: &[Footnote]) -> String {
+  let notes: Vec<String> = footnotes
+    .iter()
+    .map(|note| {
+      format!(
+        "<p id=\"fn-{0}\"><sup>{0}</sup> {1}</p>",
+        escape(&note.label),
+        inlines(&note.content)
+      )
+    })
+    .collect();
+  format!(
+    "<section class=\"footnotes\">\n{}\n</section>",
+    notes.join("\n")
+  )
+}
+
+fn list(list: &List) -> String {
+  let tag = match list.start {
+    Some(1) => "<ol>".to_string(),
+    Some(start) => format!("<ol start=\"{start}\">"),
+    None => "<ul>".to_string(),
+  };
+  let mut out = vec![tag];
+  for item in &list.items {
+    let mut li = format!("<li>{}", inlines(&item.content));
+    for child in &item.children {
+      li.push('\n');
+      li.push_str(&self::list(child));
+      li.push('\n');
+    }
+    li.push_str("</li>");
+    out.push(li);
+  }
+  out.push(
+    if list.start.is_some() {
+      "</ol>"
+    } else {
+      "</ul>"
+    }
+    .to_string(),
+  );
+  out.join("\n")
+}
+
+fn table(rows: &[Vec<String>]) -> String {
+  let Some(header) = rows.first() else {
+    return String::new();
+  };
+  let row = |cells: &[String], tag: &str| {
+    let cells: String = cells
+      .iter()
+      .map(|cell| format!("<{tag}>{}</{tag}>", escape(cell)))
+      .collect();
+    format!("<tr>{cells}</tr>")
+  };
+  let mut out = vec!["<table>".to_string(), "<thead>".to_string()];
+  out.push(row(header, "th"));
+  out.push("</thead>".to_string());
+  out.push("<tbody>".to_string());
+  out.extend(rows[1..].iter().map(|cells| row(cells, "td")));
+  out.push("</tbody>".to_string());
+  out.push("</table>".to_string());
+  out.join("\n")
+}
+
+fn escape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => out.push_str("&amp;"),
+      '<' => out.push_str("&lt;"),
+      '>' => out.push_str("&gt;"),
+      '"' => out.push_str("&quot;"),
+      '\'' => out.push_str("&#39;"),
+      c => out.push(c),
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn metadata_makes_a_complete_document() {
+    let metadata = vec![
+      ("title".to_string(), "Q&A".to_string()),
+      ("author".to_string(), "Jo \"JJ\" Smith".to_string()),
+    ];
+    assert_eq!(
+      document(&metadata, "<p>Hi</p>"),
+      "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Q&amp;A</title>\n\
+       <meta name=\"author\" content=\"Jo &quot;JJ&quot; Smith\">\n</head>\n\
+       <body>\n<p>Hi</p>\n</body>\n</html>\n"
+    );
+    assert_eq!(document(&[], "<p>Hi</p>"), "<p>Hi</p>\n");
+  }
+
+  #[test]
+  fn code_is_escaped() {
+    assert_eq!(
+      block(
+        &Block::Code("if a < b && c {}".into()),
+        &mut Anchors::default()
+      ),
+      "<pre><code>if a &lt; b &amp;&amp; c {}</code></pre>"
+    );
+  }
+}
@@ -0,0 +1,817 @@
+//! Extraction of embedded raster images.
+//!
+//! Images are written next to the converted document and referenced with
+//! relative links. Files are named after a hash of the image data, so an
+//! image that repeats on every page (a logo, say) is written only once.
+//! Alternatively they are embedded in the document itself as `data:` URIs.
+//!
+//! JPEG data is written as stored in the PDF, and Flate-compressed samples
+//! go into a PNG as they are when PNG is asked for. Everything else is
+//! decoded and encoded in the requested format, scaled down first if it is
+//! drawn at more than the highest resolution asked for. Nothing is
+//! rasterized: figures drawn as vector graphics don't come out as images.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use lopdf::{Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, State};
+
+use crate::cache::Cache;
+use crate::convert::{self, Figure};
+use crate::document::{self, Block, OutputFormat};
+use crate::encode::{self, Pixels};
+use crate::error::ConversionError;
+use crate::extract::ImagePlacement;
+use crate::jobs::{CancelToken, ConversionRegistry};
+use crate::options::ConvertOptions;
+use crate::words::Vocabulary;
+use crate::{boilerplate, footnotes, frontmatter, headings, outline, settings, toc};
+
+/// Images smaller than this (in either pixel dimension) are usually spacers
+/// or rules rather than content.
+const MIN_IMAGE_SIZE: i64 = 8;
+
+/// Resolution limits for [`ImageOptions::max_dpi`]. A full-page scan at the
+/// maximum is about 5000 × 6600 pixels.
+const MIN_DPI: u32 = 36;
+const MAX_DPI: u32 = 600;
+
+/// File format of the images written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageFormat {
+  #[default]
+  Png,
+  Jpeg,
+  /// Lossless WebP.
+  Webp,
+}
+
+/// Where images go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageMode {
+  /// Files in the image directory, linked from the document.
+  #[default]
+  Sidecar,
+  /// Base64 `data:` URIs in the document, for a single file that works
+  /// wherever it is pasted. Each image takes about a third more space than
+  /// its file would.
+  Embedded,
+}
+
+/// How images are written.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ImageOptions {
+  /// Format for images that have to be encoded. JPEG data in the PDF is
+  /// always written as is: decoding and re-encoding it would only lose
+  /// quality.
+  pub format: ImageFormat,
+  /// The highest resolution images are written at, in pixels per inch of
+  /// the page: images drawn finer, such as scanned pages, are scaled down to
+  /// it, and coarser ones are left as they are. Values outside 36–600 are
+  /// clamped. `None` for the `imageDpi` of the settings.
+  pub max_dpi: Option<u32>,
+  /// JPEG quality from 1 to 100.
+  pub jpeg_quality: u8,
+  pub mode: ImageMode,
+  /// With embedded images, output larger than this many bytes comes with a
+  /// warning: many editors struggle with files that size.
+  pub embedded_size_warning: usize,
+}
+
+impl Default for ImageOptions {
+  fn default() -> Self {
+    ImageOptions {
+      format: ImageFormat::default(),
+      max_dpi: None,
+      jpeg_quality: 85,
+      mode: ImageMode::default(),
+      embedded_size_warning: 5_000_000,
+    }
+  }
+}
+
+impl ImageOptions {
+  /// Checks the JPEG quality and brings `max_dpi`, `default_dpi` if there
+  /// is none, within limits.
+  fn validated(mut self, default_dpi: u32) -> Result<Self, ConversionError> {
+    if !(1..=100).contains(&self.jpeg_quality) {
+      return Err(ConversionError::InvalidSettings(format!(
+        "JPEG quality must be between 1 and 100, not {}",
+        self.jpeg_quality
+      )));
+    }
+    self.max_dpi = Some(self.max_dpi.unwrap_or(default_dpi).clamp(MIN_DPI, MAX_DPI));
+    Ok(self)
+  }
+}
+
+/// The converted document plus the image files written for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageConversion {
+  /// The document rendered in the requested format.
+  pub output: String,
+  /// Absolute paths of every image written, so the caller can clean up.
+  /// Empty when images are embedded.
+  pub images: Vec<String>,
+  /// Why the output may be a problem to open, if it might: embedded images
+  /// made it larger than [`ImageOptions::embedded_size_warning`].
+  pub warning: Option<String>,
+}
+
+/// Converts a PDF to `format` and writes its images to `image_dir`, which
+/// defaults to `<pdfname>_assets/` next to the PDF, or embeds them, as
+/// `image_options` says.
+///
+/// A cached result is only reused while all of its image files still exist.
+/// As with `convert_pdf_to_markdown`, conversions that need a `password`
+/// are not cached.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn convert_with_images(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  image_dir: Option<String>,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  password: Option<String>,
+  job_id: Option<String>,
+  image_options: Option<ImageOptions>,
+) -> Result<ImageConversion, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let image_options = image_options
+    .unwrap_or_default()
+    .validated(settings::load_settings(app.clone()).image_dpi)?;
+  let job = registry.start(job_id).report_progress(&app);
+  let cache = Cache::open(&app).filter(|_| password.is_none());
+  tauri::async_runtime::spawn_blocking(move || -> Result<_, ConversionError> {
+    let path = Path::new(&path);
+    let image_dir = image_dir.map_or_else(|| default_image_dir(path), PathBuf::from);
+    let slot = cache
+      .as_ref()
+      .and_then(|cache| cache.slot(path, &("images", &image_dir, &options, &image_options)));
+    let cached = slot
+      .as_ref()
+      .and_then(|slot| slot.load::<(document::Document, Vec<String>)>())
+      .filter(|(_, images)| images.iter().all(|image| Path::new(image).is_file()));
+    let (output, images) = match cached {
+      Some(cached) => cached,
+      None => {
+        let converted = convert_file_with_images(
+          path,
+          Some(&image_dir),
+          password.as_deref(),
+          &options,
+          &image_options,
+          job.token(),
+        )?;
+        if let Some(slot) = slot {
+          slot.store(&converted);
+        }
+        converted
+      }
+    };
+    let output = output.render(format);
+    let warning = size_warning(&output, &image_options);
+    if let Some(warning) = &warning {
+      log::warn!("{}: {warning}", path.display());
+    }
+    Ok(ImageConversion {
+      output,
+      images,
+      warning,
+    })
+  })
+  .await?
+}
+
+pub fn convert_file_with_images(
+  path: &Path,
+  image_dir: Option<&Path>,
+  password: Option<&str>,
+  options: &ConvertOptions,
+  image_options: &ImageOptions,
+  cancel: &CancelToken,
+) -> Result<(document::Document, Vec<String>), ConversionError> {
+  let doc = convert::load_document(path, password)?;
+  let dir = image_dir.map_or_else(|| default_image_dir(path), Path::to_path_buf);
+  // Links are relative to the output, which is written beside the PDF.
+  let link_base = path.parent().unwrap_or(Path::new("")).to_path_buf();
+  let mut writer = ImageWriter::new(dir, link_base, image_options.clone());
+
+  let numbers = doc.get_pages().into_keys().collect::<Vec<_>>();
+  // Once to extract each page, once more to write its images.
+  cancel.expect_pages(numbers.len(), 2);
+  let mut pages = convert::extract_pages(&doc, numbers, cancel)?;
+  let body_size = headings::body_font_size(pages.iter().map(|(_, page)| page));
+  let words = Vocabulary::of(pages.iter().map(|(_, page)| page));
+  if options.strip_boilerplate {
+    boilerplate::strip(&mut pages, options.boilerplate_threshold);
+  }
+  let mut rendered = Vec::new();
+  for (number, page) in &pages {
+    let mut figures = Vec::new();
+    for placement in &page.images {
+      if let Some(link) = writer.write(&doc, placement)? {
+        figures.push(Figure {
+          y: placement.y,
+          block: Block::Image { src: link },
+        });
+      }
+    }
+    rendered.push(convert::render_page(
+      *number, page, figures, options, body_size, &words,
+    ));
+    cancel.page_done(*number);
+  }
+
+  footnotes::number(&mut rendered);
+  let headings = toc::document_headings(&rendered);
+  toc::link_headings(&mut rendered, &headings);
+  if options.generate_toc {
+    toc::prepend(outline::bookmarks(&doc), &mut rendered);
+  }
+  let output = document::Document {
+    metadata: frontmatter::metadata(&doc, options),
+    pages: rendered,
+    page_separator: options.page_separator.clone(),
+    tight_output: options.tight_output,
+  };
+  Ok((output, writer.written))
+}
+
+/// The warning for `output` if it is too large with its images embedded.
+fn size_warning(output: &str, options: &ImageOptions) -> Option<String> {
+  (options.mode == ImageMode::Embedded && output.len() > options.embedded_size_warning).then(|| {
+    format!(
+      "the output is {:.1} MB with its images embedded; some editors may be slow to open it",
+      output.len() as f64 / 1_000_000.0
+    )
+  })
+}
+
+fn default_image_dir(pdf: &Path) -> PathBuf {
+  let stem = pdf.file_stem().unwrap_or_default().to_string_lossy();
+  pdf.with_file_name(format!("{stem}_assets"))
+}
+
+struct ImageWriter {
+  dir: PathBuf,
+  link_base: PathBuf,
+  options: ImageOptions,
+  /// Link for each image already written, by content hash.
+  links: HashMap<String, String>,
+  /// Object ids we could not decode, so we don't retry them on every page.
+  skipped: Vec<ObjectId>,
+  written: Vec<String>,
+}
+
+impl ImageWriter {
+  fn new(dir: PathBuf, link_base: PathBuf, options: ImageOptions) -> Self {
+    ImageWriter {
+      dir,
+      link_base,
+      options,
+      links: HashMap::new(),
+      skipped: Vec::new(),
+      written: Vec::new(),
+    }
+  }
+
+  /// Writes the image (unless an identical one was written before) and
+  /// returns the link to use for it, or `None` if it can't be decoded. An
+  /// embedded image's link is its data.
+  fn write(
+    &mut self,
+    doc: &Document,
+    placement: &ImagePlacement,
+  ) -> Result<Option<String>, ConversionError> {
+    if self.skipped.contains(&placement.id) {
+      return Ok(None);
+    }
+    let Some(stream) = doc
+      .get_object(placement.id)
+      .and_then(Object::as_stream)
+      .ok()
+    else {
+      return Ok(None);
+    };
+    let Some(image) = decode(doc, stream, placement, &self.options) else {
+      log::info!("skipping undecodable image {:?}", placement.id);
+      self.skipped.push(placement.id);
+      return Ok(None);
+    };
+
+    let hash = hex(&Sha256::digest(&image.data)[..8]);
+    if let Some(link) = self.links.get(&hash) {
+      return Ok(Some(link.clone()));
+    }
+
+    if self.options.mode == ImageMode::Embedded {
+      let link = data_uri(&image);
+      self.links.insert(hash, link.clone());
+      return Ok(Some(link));
+    }
+
+    let file = self.dir.join(format!("image-{hash}.{}", image.extension));
+    fs::create_dir_all(&self.dir)
+      .map_err(|e| ConversionError::io(format_args!("cannot create {}", self.dir.display()), e))?;
+    fs::write(&file, &image.data)
+      .map_err(|e| ConversionError::io(format_args!("cannot write {}", file.display()), e))?;
+
+    let link = relative_link(&file, &self.link_base);
+    self.links.insert(hash, link.clone());
+    self.written.push(file.display().to_string());
+    Ok(Some(link))
+  }
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// A link target for `file`: relative to `base` when it lives below
+/// it, with forward slashes and spaces escaped.
+fn relative_link(file: &Path, base: &Path) -> String {
+  let path = file.strip_prefix(base).unwrap_or(file);
+  path
+    .to_string_lossy()
+    .replace(std::path::MAIN_SEPARATOR, "/")
+    .replace(' ', "%20")
+}
+
+fn data_uri(image: &EncodedImage) -> String {
+  let mime = match image.extension {
+    "jpg" => "image/jpeg",
+    "webp" => "image/webp",
+    _ => "image/png",
+  };
+  format!(
+    "data:{mime};base64,{}",
+    base64::engine::general_purpose::STANDARD.encode(&image.data)
+  )
+}
+
+/// An image encoded in a format browsers can display.
+struct EncodedImage {
+  data: Vec<u8>,
+  extension: &'static str,
+}
+
+fn decode(
+  doc: &Document,
+  stream: &Stream,
+  placement: &ImagePlacement,
+  options: &ImageOptions,
+) -> Option<EncodedImage> {
+  let dict = &stream.dict;
+  let width = dict.get(b"Width").and_then(Object::as_i64).ok()?;
+  let height = dict.get(b"Height").and_then(Object::as_i64).ok()?;
+  if width < MIN_IMAGE_SIZE || height < MIN_IMAGE_SIZE {
+    return None;
+  }
+
+  let filters = stream.filters().unwrap_or_default();
+  // JPEG data can be written out untouched.
+  if filters == [b"DCTDecode"] {
+    return Some(EncodedImage {
+      data: stream.content.clone(),
+      extension: "jpg",
+    });
+  }
+
+  let max_dpi = options.max_dpi.unwrap_or(settings::DEFAULT_IMAGE_DPI);
+  let scaled = scaled_size(width, height, placement, max_dpi);
+  if scaled.is_none() && options.format == ImageFormat::Png {
+    if let Some(data) = flate_png(doc, stream, width, height) {
+      return Some(EncodedImage {
+        data,
+        extension: "png",
+      });
+    }
+  }
+
+  let data = stream.get_plain_content().ok()?;
+  let bits = dict
+    .get(b"BitsPerComponent")
+    .and_then(Object::as_i64)
+    .unwrap_or(8);
+  let is_mask = dict
+    .get(b"ImageMask")
+    .and_then(Object::as_bool)
+    .unwrap_or(false);
+  let (samples, channels) = if is_mask {
+    // Stencil masks paint where the bit is 0, so show those as black.
+    let samples = unpack(&data, width, height, 1, 1)?
+      .into_iter()
+      .map(|v| if v == 0 { 0 } else { 255 })
+      .collect();
+    (samples, 1)
+  } else {
+    let color_space = dict.get(b"ColorSpace").ok()?;
+    to_rgb_or_gray(doc, color_space, &data, width, height, bits)?
+  };
+
+  let mut pixels = Pixels {
+    samples,
+    width: width as u32,
+    height: height as u32,
+    channels,
+  };
+  if let Some((width, height)) = scaled {
+    pixels = pixels.downsample(width, height);
+  }
+  match options.format {
+    ImageFormat::Png => encode::png(&pixels).map(|data| EncodedImage {
+      data,
+      extension: "png",
+    }),
+    ImageFormat::Jpeg => encode::jpeg(&pixels, options.jpeg_quality).map(|data| EncodedImage {
+      data,
+      extension: "jpg",
+    }),
+    ImageFormat::Webp => encode::webp(&pixels).map(|data| EncodedImage {
+      data,
+      extension: "webp",
+    }),
+  }
+}
+
+/// The size to scale an image to so it is no more than `dpi` where it is
+/// drawn, or `None` if it already is. A stretched image keeps `dpi` along
+/// the axis it is stretched most.
+fn scaled_size(
+  width: i64,
+  height: i64,
+  placement: &ImagePlacement,
+  dpi: u32,
+) -> Option<(u32, u32)> {
+  let inches = |points: f32| points / 72.0;
+  let drawn_width = inches(placement.right - placement.left);
+  let drawn_height = inches(placement.y - placement.bottom);
+  if drawn_width <= 0.0 || drawn_height <= 0.0 {
+    return None;
+  }
+  let scale =
+    (dpi as f32 * drawn_width / width as f32).max(dpi as f32 * drawn_height / height as f32);
+  if scale >= 1.0 {
+    return None;
+  }
+  let scaled = |pixels: i64| ((pixels as f32 * scale).round() as u32).max(1);
+  Some((scaled(width), scaled(height)))
+}
+
+/// The image as a PNG around its own Flate data, for the samples a PNG can
+/// hold as they are: PNG-predicted gray or RGB rows, which is how most PDF
+/// writers store them.
+fn flate_png(doc: &Document, stream: &Stream, width: i64, height: i64) -> Option<Vec<u8>> {
+  let dict = &stream.dict;
+  let filters = stream.filters().ok()?;
+  if filters != [b"FlateDecode"] || dict.has(b"Decode") || dict.has(b"ImageMask") {
+    return None;
+  }
+  let params = match dict.get(b"DecodeParms").ok()? {
+    Object::Dictionary(params) => params,
+    Object::Array(array) => array.first()?.as_dict().ok()?,
+    _ => return None,
+  };
+  let param =
+    |key: &[u8], default: i64| params.get(key).and_then(Object::as_i64).unwrap_or(default);
+  let channels = components(doc, dict.get(b"ColorSpace").ok()?)?;
+  let bits = dict
+    .get(b"BitsPerComponent")
+    .and_then(Object::as_i64)
+    .ok()?;
+  let depth = match (channels, bits) {
+    (1, 1) => png::BitDepth::One,
+    (1, 2) => png::BitDepth::Two,
+    (1, 4) => png::BitDepth::Four,
+    (_, 8) => png::BitDepth::Eight,
+    _ => return None,
+  };
+  if param(b"Predictor", 1) < 10
+    || param(b"Colors", 1) != channels as i64
+    || param(b"BitsPerComponent", 8) != bits
+    || param(b"Columns", 1) != width
+  {
+    return None;
+  }
+
+  let mut data = Vec::new();
+  let mut encoder = png::Encoder::new(&mut data, width as u32, height as u32);
+  encoder.set_color(match channels {
+    1 => png::ColorType::Grayscale,
+    _ => png::ColorType::Rgb,
+  });
+  encoder.set_depth(depth);
+  let mut writer = encoder.write_header().ok()?;
+  writer.write_chunk(png::chunk::IDAT, &stream.content).ok()?;
+  writer.finish().ok()?;
+  Some(data)
+}
+
+/// Splits packed samples of 1, 2, 4 or 8 bits into one value per byte; rows
+/// are padded to a whole byte.
+fn unpack(data: &[u8], width: i64, height: i64, components: usize, bits: i64) -> Option<Vec<u8>> {
+  let (width, height) = (usize::try_from(width).ok()?, usize::try_from(height).ok()?);
+  let row_samples = width * components;
+  if bits == 8 {
+    return data.get(..row_samples * height).map(<[u8]>::to_vec);
+  }
+  if !matches!(bits, 1 | 2 | 4) {
+    return None;
+  }
+  let bits = bits as usize;
+  let row_bytes = (row_samples * bits).div_ceil(8);
+  if data.len() < row_bytes * height {
+    return None;
+  }
+  let mask = (1u8 << bits) - 1;
+  let mut out = Vec::with_capacity(row_samples * height);
+  for row in data.chunks(row_bytes).take(height) {
+    for i in 0..row_samples {
+      let bit = i * bits;
+      out.push((row[bit / 8] >> (8 - bits - bit % 8)) & mask);
+    }
+  }
+  Some(out)
+}
+
+/// Like [`unpack`], but scales the values to the full 0–255 range.
+fn unpack_scaled(
+  data: &[u8],
+  width: i64,
+  height: i64,
+  components: usize,
+  bits: i64,
+) -> Option<Vec<u8>> {
+  let samples = unpack(data, width, height, components, bits)?;
+  if bits == 8 {
+    return Some(samples);
+  }
+  let max = (1u16 << bits) - 1;
+  Some(
+    samples
+      .into_iter()
+      .map(|v| (v as u16 * 255 / max) as u8)
+      .collect(),
+  )
+}
+
+/// Number of components of a gray or RGB colour space, `None` for others.
+fn components(doc: &Document, color_space: &Object) -> Option<usize> {
+  let (_, color_space) = doc.dereference(color_space).ok()?;
+  let (name, params) = match color_space {
+    Object::Name(name) => (name.as_slice(), &[][..]),
+    Object::Array(array) => (array.first()?.as_name().ok()?, &array[1..]),
+    _ => return None,
+  };
+  match name {
+    b"DeviceGray" | b"CalGray" | b"G" => Some(1),
+    b"DeviceRGB" | b"CalRGB" | b"RGB" => Some(3),
+    b"ICCBased" => {
+      let profile = doc.dereference(params.first()?).ok()?.1.as_stream().ok()?;
+      match profile.dict.get(b"N").and_then(Object::as_i64).ok()? {
+        1 => Some(1),
+        3 => Some(3),
+        _ => None,
+      }
+    }
+    _ => None,
+  }
+}
+
+/// Converts decoded samples to 8-bit gray (1 channel) or RGB (3 channels).
+fn to_rgb_or_gray(
+  doc: &Document,
+  color_space: &Object,
+  data: &[u8],
+  width: i64,
+  height: i64,
+  bits: i64,
+) -> Option<(Vec<u8>, u8)> {
+  if let Some(n) = components(doc, color_space) {
+    return Some((unpack_scaled(data, width, height, n, bits)?, n as u8));
+  }
+
+  let (_, color_space) = doc.dereference(color_space).ok()?;
+  let (name, params) = match color_space {
+    Object::Name(name) => (name.as_slice(), &[][..]),
+    Object::Array(array) => (array.first()?.as_name().ok()?, &array[1..]),
+    _ => return None,
+  };
+  match name {
+    b"DeviceCMYK" | b"CMYK" => {
+      let rgb = unpack_scaled(data, width, height, 4, bits)?
+        .chunks(4)
+        .flat_map(|p| {
+          let k = 255 - p[3] as u16;
+          [p[0], p[1], p[2]].map(|c| ((255 - c as u16) * k / 255) as u8)
+        })
+        .collect();
+      Some((rgb, 3))
+    }
+    b"Indexed" | b"I" => {
+      let channels = components(doc, params.first()?)?;
+      let palette = match doc.dereference(params.get(2)?).ok()?.1 {
+        Object::String(bytes, _) => bytes.clone(),
+        Object::Stream(stream) => stream.get_plain_content().ok()?,
+        _ => return None,
+      };
+      let indices = unpack(data, width, height, 1, bits)?;
+      let mut out = Vec::with_capacity(indices.len() * channels);
+      for index in indices {
+        let start = index as usize * channels;
+        out.extend_from_slice(palette.get(start..start + channels)?);
+      }
+      Some((out, channels as u8))
+    }
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use lopdf::dictionary;
+
+  use super::*;
+
+  fn placement(width: f32, height: f32) -> ImagePlacement {
+    ImagePlacement {
+      id: (1, 0),
+      y: 700.0,
+      bottom: 700.0 - height,
+      left: 72.0,
+      right: 72.0 + width,
+    }
+  }
+
+  #[test]
+  fn options_are_checked_and_clamped() {
+    for jpeg_quality in [0, 101] {
+      let options = ImageOptions {
+        jpeg_quality,
+        ..ImageOptions::default()
+      };
+      assert!(matches!(
+        options.validated(150),
+        Err(ConversionError::InvalidSettings(_))
+      ));
+    }
+    let dpi = |max_dpi| {
+      let options = ImageOptions {
+        max_dpi,
+        ..ImageOptions::default()
+      };
+      options.validated(200).unwrap().max_dpi.unwrap()
+    };
+    assert_eq!(
+      (dpi(Some(10)), dpi(Some(300)), dpi(Some(1200))),
+      (MIN_DPI, 300, MAX_DPI)
+    );
+    // Without one of its own, the resolution is that of the settings.
+    assert_eq!(dpi(None), 200);
+  }
+
+  #[test]
+  fn images_are_scaled_down_to_the_resolution_they_are_drawn_at() {
+    // 1200 pixels across two inches is 600 dpi.
+    assert_eq!(
+      scaled_size(1200, 600, &placement(144.0, 72.0), 150),
+      Some((300, 150))
+    );
+    // Drawn larger than its pixels at that resolution, or not drawn at all.
+    assert_eq!(scaled_size(1200, 600, &placement(576.0, 288.0), 150), None);
+    assert_eq!(scaled_size(1200, 600, &placement(0.0, 72.0), 150), None);
+    // Stretched to twice the height: the height gets the full resolution.
+    assert_eq!(
+      scaled_size(1000, 1000, &placement(144.0, 288.0), 100),
+      Some((400, 400))
+    );
+  }
+
+  #[test]
+  fn packed_samples_are_split_and_scaled() {
+    // Three 2-bit samples to a row, padded to a byte.
+    let data = [0b1101_0000, 0b0010_0000];
+    assert_eq!(unpack(&data, 3, 2, 1, 2), Some(vec![3, 1, 0, 0, 2, 0]));
+    assert_eq!(
+      unpack_scaled(&data, 3, 2, 1, 2),
+      Some(vec![255, 85, 0, 0, 170, 0])
+    );
+    assert_eq!(unpack(&[0xf1, 0x20], 3, 1, 1, 4), Some(vec![15, 1, 2]));
+    assert_eq!(unpack(&[0b1010_0000], 1, 1, 3, 1), Some(vec![1, 0, 1]));
+    assert_eq!(unpack_scaled(&[7, 8, 9], 1, 1, 3, 8), Some(vec![7, 8, 9]));
+    // Too little data, or a depth PDF doesn't have.
+    assert_eq!(unpack(&data, 3, 3, 1, 2), None);
+    assert_eq!(unpack(&[0; 8], 2, 2, 1, 3), None);
+  }
+
+  #[test]
+  fn links_are_relative_and_escaped() {
+    let base = std::env::temp_dir().join("docs");
+    let file = base.join("My Report_assets").join("image-1.png");
+    assert_eq!(
+      relative_link(&file, &base),
+      "My%20Report_assets/image-1.png"
+    );
+    // A file elsewhere keeps its whole path.
+    let elsewhere = Path::new("images").join("a.png");
+    assert_eq!(relative_link(&elsewhere, &base), "images/a.png");
+  }
+
+  #[test]
+  fn embedded_images_become_data_uris() {
+    let mut doc = Document::with_version("1.5");
+    let jpeg = Stream::new(
+      dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => 16,
+        "Height" => 16,
+        "Filter" => "DCTDecode",
+      },
+      b"\xff\xd8not really a jpeg".to_vec(),
+    );
+    let gray = Stream::new(
+      dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => 16,
+        "Height" => 16,
+        "ColorSpace" => "DeviceGray",
+        "BitsPerComponent" => 8,
+      },
+      (0..=255).collect(),
+    );
+    let jpeg_id = doc.add_object(jpeg);
+    let gray_id = doc.add_object(gray);
+    let dir = std::env::temp_dir().join(format!("pdf2markdown-embedded-{}", std::process::id()));
+    let options = ImageOptions {
+      mode: ImageMode::Embedded,
+      ..ImageOptions::default()
+    };
+    let mut writer = ImageWriter::new(dir.clone(), dir.clone(), options.clone());
+    let link = |writer: &mut ImageWriter, id| {
+      let placement = ImagePlacement {
+        id,
+        ..placement(144.0, 144.0)
+      };
+      writer.write(&doc, &placement).unwrap().unwrap()
+    };
+
+    let jpeg = link(&mut writer, jpeg_id);
+    // JPEG data goes in as it is.
+    assert_eq!(jpeg, "data:image/jpeg;base64,/9hub3QgcmVhbGx5IGEganBlZw==");
+    let png = link(&mut writer, gray_id);
+    assert!(
+      png.starts_with("data:image/png;base64,iVBORw0KGgo"),
+      "{png}"
+    );
+    // Nothing is written to disk.
+    assert!(writer.written.is_empty());
+    assert!(!dir.exists());
+
+    let output = format!("![]({png})");
+    let small_enough = ImageOptions {
+      embedded_size_warning: output.len(),
+      ..options.clone()
+    };
+    assert_eq!(size_warning(&output, &small_enough), None);
+    let too_large = ImageOptions {
+      embedded_size_warning: output.len() - 1,
+      ..options.clone()
+    };
+    let warning = size_warning(&output, &too_large).unwrap();
+    assert!(warning.starts_with("the output is 0.0 MB"), "{warning}");
+    // Files in a sidecar directory don't make the output any larger.
+    let sidecar = ImageOptions {
+      mode: ImageMode::Sidecar,
+      ..too_large
+    };
+    assert_eq!(size_warning(&output, &sidecar), None);
+  }
+
+  #[test]
+  fn data_uris_name_the_format() {
+    let uri = |extension| {
+      data_uri(&EncodedImage {
+        data: b"abc".to_vec(),
+        extension,
+      })
+    };
+    assert_eq!(uri("jpg"), "data:image/jpeg;base64,YWJj");
+    assert_eq!(uri("webp"), "data:image/webp;base64,YWJj");
+    assert_eq!(uri("png"), "data:image/png;base64,YWJj");
+  }
+}
@@ -0,0 +1,443 @@
+//! Inline content: the text of a paragraph with its links, code spans and
+//! math.
+
+use crate::document::Inline;
+use crate::extract::TextLine;
+use crate::options::ConvertOptions;
+use crate::words::Vocabulary;
+use crate::{bidi, code, links, math};
+
+/// What sets a run of text apart from the plain text around it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Style<'a> {
+  /// The label of the footnote the text is the mark of.
+  footnote: Option<&'a str>,
+  link: Option<&'a str>,
+  code: bool,
+  math: bool,
+  bold: bool,
+  italic: bool,
+}
+
+/// Joins a paragraph's lines into one line of inline content. Linked text and
+/// bare URLs become links unless `preserve_links` is off, text in a
+/// monospace font becomes inline code, math becomes LaTeX unless
+/// `detect_math` is off, and text in bold and italic faces is emphasized
+/// unless `detect_emphasis` is, and footnote marks become references.
+/// Right-to-left text is put in logical order, and words hyphenated at the
+/// end of a line are joined unless `dehyphenate` is off, asking `words`
+/// which are compounds.
+pub fn inlines(lines: &[TextLine], options: &ConvertOptions, words: &Vocabulary) -> Vec<Inline> {
+  let lines: Vec<_> = lines
+    .iter()
+    .map(|line| bidi::logical_order(line, options.text_direction))
+    .collect();
+  // Runs of text in one style, across line breaks.
+  let mut runs: Vec<(String, Style)> = Vec::new();
+  for (index, line) in lines.iter().enumerate() {
+    let mut line_break = index > 0;
+    let last = line.spans.len().saturating_sub(1);
+    let math = if options.detect_math {
+      math::math_spans(line)
+    } else {
+      vec![false; line.spans.len()]
+    };
+    for (i, span) in line.spans.iter().enumerate() {
+      let footnote = span.footnote.as_deref();
+      let link = span
+        .link
+        .as_deref()
+        .filter(|_| options.preserve_links && footnote.is_none());
+      let is_math = math[i] && link.is_none() && footnote.is_none();
+      let latex;
+      let mut text = if is_math {
+        latex = math::span_latex(span, line);
+        latex.as_str()
+      } else {
+        span.text.as_str()
+      };
+      if i == 0 {
+        text = text.trim_start();
+      }
+      if i == last {
+        text = text.trim_end();
+      }
+      if text.is_empty() {
+        continue;
+      }
+      let code =
+        !is_math && footnote.is_none() && code::is_code_span(span, &options.monospace_fonts);
+      let emphasis = options.detect_emphasis && !is_math && !code && footnote.is_none();
+      let style = Style {
+        footnote,
+        link,
+        code,
+        math: is_math,
+        bold: emphasis && span.is_bold(),
+        italic: emphasis && span.is_italic(),
+      };
+      match runs.last_mut() {
+        Some((run, run_style)) if *run_style == style => {
+          if line_break {
+            let words = (options.dehyphenate && !style.code && !style.math).then_some(words);
+            break_line(run, text, style.link.is_some(), words);
+          }
+          run.push_str(text);
+        }
+        _ => {
+          if line_break {
+            match runs.last_mut() {
+              Some((run, run_style)) if *run_style == Style::default() => run.push(' '),
+              Some(_) => runs.push((" ".to_string(), Style::default())),
+              None => {}
+            }
+          }
+          match runs.last_mut() {
+            Some((run, run_style)) if *run_style == style => run.push_str(text),
+            _ => runs.push((text.to_string(), style)),
+          }
+        }
+      }
+      line_break = false;
+    }
+  }
+
+  // The content of each run, with whether it is bold and italic.
+  let mut pieces = Vec::new();
+  for (text, style) in runs {
+    let body = text.trim();
+    let content = match style {
+      Style {
+        footnote: Some(label),
+        ..
+      } => vec![Inline::FootnoteRef(label.to_string())],
+      Style {
+        link: Some(uri), ..
+      } if links::looks_like_url(body) => vec![Inline::Url(uri.to_string())],
+      Style {
+        link: Some(uri),
+        code,
+        ..
+      } => vec![Inline::Link {
+        content: vec![if code {
+          Inline::Code(body.to_string())
+        } else {
+          Inline::Text(body.to_string())
+        }],
+        url: uri.to_string(),
+      }],
+      Style { code: true, .. } => vec![Inline::Code(body.to_string())],
+      // Spaces don't matter to LaTeX, only that commands are kept apart.
+      Style { math: true, .. } => vec![Inline::Math(
+        body.split_whitespace().collect::<Vec<_>>().join(" "),
+      )],
+      _ if options.preserve_links => links::bare_urls(body),
+      _ => vec![Inline::Text(body.to_string())],
+    };
+    // Keep surrounding spaces outside the markup.
+    let lead = &text[..text.len() - text.trim_start().len()];
+    let trail = &text[text.trim_end().len().max(lead.len())..];
+    pieces.push((Inline::Text(lead.to_string()), false, false));
+    pieces.extend(
+      content
+        .into_iter()
+        .map(|inline| (inline, style.bold, style.italic)),
+    );
+    pieces.push((Inline::Text(trail.to_string()), false, false));
+  }
+  pieces.retain(|(inline, ..)| *inline != Inline::Text(String::new()));
+
+  // Spaces, punctuation and footnote references between two bold words are
+  // bold too, so the words are emphasized together rather than one at a
+  // time.
+  let neutral = |inline: &Inline| match inline {
+    Inline::Text(text) => !text.chars().any(char::is_alphanumeric),
+    Inline::FootnoteRef(_) => true,
+    _ => false,
+  };
+  let faces: Vec<(bool, bool)> = pieces
+    .iter()
+    .map(|&(_, bold, italic)| (bold, italic))
+    .collect();
+  for i in 0..pieces.len() {
+    if !neutral(&pieces[i].0) {
+      continue;
+    }
+    let before = (0..i).rev().find(|&j| !neutral(&pieces[j].0));
+    let after = (i + 1..pieces.len()).find(|&j| !neutral(&pieces[j].0));
+    let (bold, italic) = match (before, after) {
+      (Some(before), Some(after)) => (
+        faces[before].0 && faces[after].0,
+        faces[before].1 && faces[after].1,
+      ),
+      _ => (false, false),
+    };
+    pieces[i].1 = bold;
+    pieces[i].2 = italic;
+  }
+  merge_text(emphasize(pieces))
+}
+
+/// Wraps consecutive bold pieces in one `Strong` and italic ones in one
+/// `Emphasis`, bold outside italic, so neighbouring runs in the same face
+/// are never emphasized one by one (`**a****b**`).
+fn emphasize(pieces: Vec<(Inline, bool, bool)>) -> Vec<Inline> {
+  let mut out = Vec::new();
+  let mut pieces = pieces.into_iter().peekable();
+  while let Some((inline, bold, italic)) = pieces.next() {
+    if !bold && !italic {
+      out.push(inline);
+      continue;
+    }
+    // Within bold text, only whether it is italic is left to mark.
+    let mut inner = vec![(inline, false, bold && italic)];
+    while let Some((inline, _, next_italic)) = pieces.next_if(|&(_, next_bold, next_italic)| {
+      if bold {
+        next_bold
+      } else {
+        !next_bold && next_italic
+      }
+    }) {
+      inner.push((inline, false, bold && next_italic));
+    }
+    let content = merge_text(emphasize(inner));
+    out.push(if bold {
+      Inline::Strong(content)
+    } else {
+      Inline::Emphasis(content)
+    });
+  }
+  out
+}
+
+/// The content of a heading, without the bold that made it one: a heading
+/// is already set apart, so `## **Results**` says nothing `## Results`
+/// doesn't.
+pub fn heading(content: Vec<Inline>) -> Vec<Inline> {
+  match <[Inline; 1]>::try_from(content) {
+    Ok([Inline::Strong(content)]) => content,
+    Ok([inline]) => vec![inline],
+    Err(content) => content,
+  }
+}
+
+/// Joins adjacent runs of plain text.
+fn merge_text(inlines: Vec<Inline>) -> Vec<Inline> {
+  let mut out: Vec<Inline> = Vec::with_capacity(inlines.len());
+  for inline in inlines {
+    match (out.last_mut(), inline) {
+      (Some(Inline::Text(last)), Inline::Text(text)) => last.push_str(&text),
+      (_, inline) => out.push(inline),
+    }
+  }
+  out
+}
+
+/// Joins a run continued on the next line. A word hyphenated across the
+/// break is put back together, in linked text always and elsewhere if there
+/// are `words` to tell it from a compound, which is closed up around its
+/// hyphen instead, as one whose second part is capitalized (`Franco-` /
+/// `German`) always is. URLs are never split by a space.
+fn break_line(anchor: &mut String, next: &str, linked: bool, words: Option<&Vocabulary>) {
+  let hyphen = anchor
+    .strip_suffix('-')
+    .and_then(|rest| rest.chars().last())
+    .is_some_and(char::is_alphabetic);
+  let hyphenated = hyphen && next.starts_with(char::is_lowercase);
+  if hyphen && words.is_some() && next.starts_with(char::is_uppercase) {
+    // A compound: the hyphen stays, and no space goes in.
+  } else if hyphenated && linked {
+    anchor.pop();
+  } else if let (true, Some(words)) = (hyphenated, words) {
+    let first = anchor[..anchor.len() - 1]
+      .rsplit(char::is_whitespace)
+      .next()
+      .unwrap_or_default()
+      .trim_start_matches(|c: char| !c.is_alphanumeric());
+    let second = next
+      .split_whitespace()
+      .next()
+      .unwrap_or_default()
+      .trim_matches(|c: char| !c.is_alphanumeric());
+    if words.joins(first, second) {
+      anchor.pop();
+    }
+  } else if !(linked && links::looks_like_url(anchor)) {
+    anchor.push(' ');
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::extract::{PageText, TextSpan};
+  use crate::markdown;
+
+  fn render(lines: &[TextLine], options: &ConvertOptions) -> String {
+    markdown::inlines(&inlines(lines, options, &Vocabulary::default()))
+  }
+
+  fn line(spans: &[(&str, Option<&str>)]) -> TextLine {
+    TextLine {
+      spans: spans
+        .iter()
+        .map(|&(text, link)| TextSpan {
+          text: text.to_string(),
+          x: 0.0,
+          y: 0.0,
+          width: 0.0,
+          font_size: 10.0,
+          font_name: "Helvetica".into(),
+          link: link.map(str::to_string),
+          style: Default::default(),
+          footnote: None,
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn linked_text_becomes_a_markdown_link() {
+    let lines = [line(&[
+      ("See the ", None),
+      ("annual [2021] report", Some("https://example.com/a b")),
+      (" for details.", None),
+    ])];
+    let mut options = ConvertOptions::default();
+    assert_eq!(
+      render(&lines, &options),
+      "See the [annual \\[2021\\] report](https://example.com/a%20b) for details."
+    );
+    options.preserve_links = false;
+    assert_eq!(
+      render(&lines, &options),
+      "See the annual \\[2021] report for details."
+    );
+  }
+
+  #[test]
+  fn links_across_line_breaks_are_joined() {
+    let options = ConvertOptions::default();
+    let uri = Some("https://example.com/guide");
+    let lines = [
+      line(&[("Read the ", None), ("imple-", uri)]),
+      line(&[("mentation guide", uri), (" today.", None)]),
+    ];
+    assert_eq!(
+      render(&lines, &options),
+      "Read the [implementation guide](https://example.com/guide) today."
+    );
+
+    let lines = [
+      line(&[("Visit ", None), ("https://example.com/", uri)]),
+      line(&[("guide", uri)]),
+    ];
+    assert_eq!(
+      render(&lines, &options),
+      "Visit <https://example.com/guide>"
+    );
+  }
+
+  #[test]
+  fn monospace_words_become_inline_code() {
+    let mut lines = [line(&[
+      ("Call ", None),
+      ("parse()", None),
+      (" first.", None),
+    ])];
+    lines[0].spans[1].font_name = "Courier".into();
+    assert_eq!(
+      render(&lines, &ConvertOptions::default()),
+      "Call `parse()` first."
+    );
+  }
+
+  #[test]
+  fn math_becomes_inline_latex() {
+    let mut lines = [line(&[
+      ("At $5 each, ", None),
+      ("n ≤ 2", None),
+      (" copies.", None),
+    ])];
+    lines[0].spans[1].font_name = "CMMI10".into();
+    let mut options = ConvertOptions::default();
+    assert_eq!(
+      render(&lines, &options),
+      "At \\$5 each, $n \\leq 2$ copies."
+    );
+    options.detect_math = false;
+    assert_eq!(render(&lines, &options), "At $5 each, n ≤ 2 copies.");
+  }
+
+  #[test]
+  fn bold_and_italic_faces_are_emphasized_together() {
+    let mut lines = [
+      line(&[
+        ("A ", None),
+        ("bold", None),
+        (" ", None),
+        ("claim", None),
+        (", an ", None),
+        ("aside", None),
+        (" and ", None),
+        ("both", None),
+      ]),
+      line(&[("of them", None), (".", None)]),
+    ];
+    for (i, font) in [
+      (1, "Arial-BoldMT"),
+      (2, "Arial-BoldMT"),
+      (3, "Arial-Bold"),
+      (5, "Arial-ItalicMT"),
+      (7, "Arial-BoldItalicMT"),
+    ] {
+      lines[0].spans[i].font_name = font.into();
+    }
+    lines[1].spans[0].style.bold = true;
+    lines[1].spans[0].style.italic = true;
+    let mut options = ConvertOptions::default();
+    assert_eq!(
+      render(&lines, &options),
+      "A **bold claim**, an *aside* and ***both of them***."
+    );
+    // A heading in bold is a heading, not a bold one.
+    let mut title = [line(&[("Results", None)])];
+    title[0].spans[0].font_name = "Arial-BoldMT".into();
+    assert_eq!(
+      markdown::inlines(&heading(inlines(&title, &options, &Vocabulary::default()))),
+      "Results"
+    );
+
+    options.detect_emphasis = false;
+    assert_eq!(
+      render(&lines, &options),
+      "A bold claim, an aside and both of them."
+    );
+  }
+
+  #[test]
+  fn words_hyphenated_at_line_ends_are_joined() {
+    let lines = [
+      line(&[("An inter-", None)]),
+      line(&[("national, well-", None)]),
+      line(&[("known and state-of-the-", None)]),
+      line(&[("art review of Franco-", None)]),
+      line(&[("German ties.", None)]),
+    ];
+    let mut options = ConvertOptions::default();
+    let words = Vocabulary::of([&PageText {
+      lines: vec![line(&[("a well-known fact", None)])],
+      images: Vec::new(),
+    }]);
+    let render = |options: &ConvertOptions| markdown::inlines(&inlines(&lines, options, &words));
+    assert_eq!(
+      render(&options),
+      "An international, well-known and state-of-the-art review of Franco-German ties."
+    );
+    options.dehyphenate = false;
+    assert_eq!(
+      render(&options),
+      "An inter- national, well- known and state-of-the- art review of Franco- German ties."
+    );
+  }
+}
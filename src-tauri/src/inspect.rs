@@ -0,0 +1,169 @@
+//! A quick look at a PDF before converting it: how long it is, whether it
+//! opens without a password and whether it has text at all or is a scan.
+
+use std::fs;
+use std::path::Path;
+
+use lopdf::{Document, Object};
+use serde::Serialize;
+
+use crate::convert;
+use crate::error::ConversionError;
+use crate::{extract, frontmatter};
+
+/// Pages looked at for text. Spread over the document, they tell a scan
+/// from a text PDF without extracting every page of a long one.
+const SAMPLE_PAGES: usize = 5;
+
+/// What `inspect_pdf` finds out about a PDF.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfInfo {
+  pub page_count: usize,
+  /// Whether the PDF is encrypted, including PDFs that open without a
+  /// password but restrict what may be done with them.
+  pub encrypted: bool,
+  /// Whether any of the sampled pages has text. `false` suggests a scan
+  /// that needs OCR. `None` when a password is needed to read the pages.
+  pub has_text: Option<bool>,
+  pub has_images: bool,
+  /// `None` when missing or when a password is needed to read it.
+  pub title: Option<String>,
+  pub author: Option<String>,
+  /// Size of the file in bytes.
+  pub file_size: u64,
+}
+
+/// Reports on the PDF at `path` without converting it. Encrypted PDFs are
+/// inspected as far as they can be without their password.
+#[tauri::command]
+pub async fn inspect_pdf(path: String) -> Result<PdfInfo, ConversionError> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let path = Path::new(&path);
+    let doc = convert::read_document(path)?;
+    let file_size = fs::metadata(path)
+      .map_err(|e| ConversionError::io(format_args!("cannot read {}", path.display()), e))?
+      .len();
+    Ok(inspect(&doc, file_size))
+  })
+  .await?
+}
+
+fn inspect(doc: &Document, file_size: u64) -> PdfInfo {
+  // Opening with an empty password removes the encryption dictionary but
+  // leaves its state behind.
+  let encrypted = doc.is_encrypted() || doc.encryption_state.is_some();
+  let locked = doc.is_encrypted();
+  let pages = doc.get_pages();
+  let has_text = (!locked).then(|| {
+    sample(pages.len(), SAMPLE_PAGES).into_iter().any(|number| {
+      pages
+        .get(&(number as u32))
+        .and_then(|&id| extract::extract_page(doc, id).ok())
+        .is_some_and(|page| {
+          page
+            .lines
+            .iter()
+            .flat_map(|line| &line.spans)
+            .any(|span| !span.text.trim().is_empty())
+        })
+    })
+  });
+  // Stream dictionaries aren't encrypted, so this works on any PDF.
+  let has_images = doc.objects.values().any(|object| {
+    object.as_stream().is_ok_and(|stream| {
+      stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Image")
+    })
+  });
+  let field = |name: &[u8]| {
+    (!locked)
+      .then(|| frontmatter::info_field(doc, name))
+      .flatten()
+  };
+  PdfInfo {
+    page_count: pages.len(),
+    encrypted,
+    has_text,
+    has_images,
+    title: field(b"Title"),
+    author: field(b"Author"),
+    file_size,
+  }
+}
+
+/// Up to `n` one-based page numbers spread evenly over `count` pages, the
+/// first and last included.
+fn sample(count: usize, n: usize) -> Vec<usize> {
+  match count.min(n) {
+    0 => Vec::new(),
+    1 => vec![1],
+    n => (0..n).map(|i| 1 + i * (count - 1) / (n - 1)).collect(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lopdf::content::{Content, Operation};
+  use lopdf::{dictionary, Stream};
+
+  #[test]
+  fn samples_spread_over_the_document() {
+    assert_eq!(sample(0, 5), Vec::<usize>::new());
+    assert_eq!(sample(3, 5), [1, 2, 3]);
+    assert_eq!(sample(101, 5), [1, 26, 51, 76, 101]);
+  }
+
+  #[test]
+  fn reports_text_and_metadata() {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+      "Type" => "Font",
+      "Subtype" => "Type1",
+      "BaseFont" => "Helvetica",
+    });
+    let content = Content {
+      operations: vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F1".into(), 12.into()]),
+        Operation::new("Td", vec![72.into(), 700.into()]),
+        Operation::new("Tj", vec![Object::string_literal("Hello")]),
+        Operation::new("ET", vec![]),
+      ],
+    };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+    let page_id = doc.add_object(dictionary! {
+      "Type" => "Page",
+      "Parent" => pages_id,
+      "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+      "Contents" => content_id,
+      "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+    });
+    doc.objects.insert(
+      pages_id,
+      Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![page_id.into()],
+        "Count" => 1,
+      }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    let info_id = doc.add_object(dictionary! { "Title" => Object::string_literal("Report") });
+    doc.trailer.set("Root", catalog_id);
+    doc.trailer.set("Info", info_id);
+
+    assert_eq!(
+      inspect(&doc, 1234),
+      PdfInfo {
+        page_count: 1,
+        encrypted: false,
+        has_text: Some(true),
+        has_images: false,
+        title: Some("Report".into()),
+        author: None,
+        file_size: 1234,
+      }
+    );
+  }
+}
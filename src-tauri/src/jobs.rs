@@ -0,0 +1,188 @@
+//! Cancellation of running conversions.
+//!
+//! Each long-running command registers a job in the [`ConversionRegistry`]
+//! (kept in Tauri's managed state) and checks its [`CancelToken`] between
+//! pages. The frontend names the job when it starts the command, so it can
+//! cancel it while the command is still running, and single-file conversions
+//! report the pages they finish through the same token. A job the frontend
+//! doesn't name gets an id of its own, announced in a first
+//! `conversion-progress` event.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tauri::{AppHandle, State};
+
+use crate::error::ConversionError;
+use crate::progress::Progress;
+
+/// The conversions currently running, by job id.
+#[derive(Default)]
+pub struct ConversionRegistry {
+  jobs: Arc<Mutex<HashMap<String, CancelToken>>>,
+  next_id: AtomicU64,
+}
+
+impl ConversionRegistry {
+  /// Registers a conversion under `job_id`, or under a fresh id if none is
+  /// given. The job is unregistered when the returned [`Job`] is dropped.
+  pub fn start(&self, job_id: Option<String>) -> Job {
+    let id =
+      job_id.unwrap_or_else(|| format!("job-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1));
+    let token = CancelToken::default();
+    let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+    if jobs.insert(id.clone(), token.clone()).is_some() {
+      log::warn!("job id {id} was reused; the earlier job can no longer be cancelled");
+    }
+    Job {
+      id,
+      token,
+      jobs: Arc::clone(&self.jobs),
+    }
+  }
+
+  /// Flags a job for cancellation. Returns whether it was still running;
+  /// cancelling a finished or unknown job does nothing.
+  pub fn cancel(&self, job_id: &str) -> bool {
+    let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+    match jobs.get(job_id) {
+      Some(token) => {
+        token.cancel();
+        true
+      }
+      None => false,
+    }
+  }
+}
+
+/// A registered conversion.
+pub struct Job {
+  id: String,
+  token: CancelToken,
+  jobs: Arc<Mutex<HashMap<String, CancelToken>>>,
+}
+
+impl Job {
+  pub fn token(&self) -> &CancelToken {
+    &self.token
+  }
+
+  /// Sends a `conversion-progress` event at 0% with the job's id, for jobs
+  /// that report nothing else through it.
+  pub fn announce(self, app: &AppHandle) -> Self {
+    Progress::events(app, &self.id).announce();
+    self
+  }
+
+  /// Announces the job, then sends `conversion-progress` events for the
+  /// pages it converts.
+  pub fn report_progress(mut self, app: &AppHandle) -> Self {
+    let progress = Progress::events(app, &self.id);
+    progress.announce();
+    self.token.progress = Some(Arc::new(progress));
+    self
+  }
+}
+
+impl Drop for Job {
+  fn drop(&mut self) {
+    let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+    // Only remove our own entry, not a newer job that reused the id.
+    if jobs
+      .get(&self.id)
+      .is_some_and(|token| Arc::ptr_eq(&token.cancelled, &self.token.cancelled))
+    {
+      jobs.remove(&self.id);
+    }
+  }
+}
+
+/// Shared flag a conversion polls to find out it should stop, and through
+/// which it reports its progress if the job asked for that.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+  cancelled: Arc<AtomicBool>,
+  progress: Option<Arc<Progress>>,
+}
+
+impl CancelToken {
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+
+  /// Fails with [`ConversionError::Cancelled`] once the job was cancelled.
+  pub fn check(&self) -> Result<(), ConversionError> {
+    if self.cancelled.load(Ordering::Relaxed) {
+      Err(ConversionError::Cancelled)
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Announces `pages` pages to convert, each extracted `passes` times. Until
+  /// then, finished pages aren't reported.
+  pub fn expect_pages(&self, pages: usize, passes: usize) {
+    if let Some(progress) = &self.progress {
+      progress.start(pages, passes);
+    }
+  }
+
+  /// Reports `page` as done, if progress is being reported.
+  pub fn page_done(&self, page: u32) {
+    if let Some(progress) = &self.progress {
+      progress.page_done(page);
+    }
+  }
+
+  /// Reports `pages` pages as done at once, up to `page`, without timing
+  /// them.
+  pub fn pages_skipped(&self, pages: usize, page: u32) {
+    if let Some(progress) = &self.progress {
+      progress.skip(pages, page);
+    }
+  }
+}
+
+/// Asks the conversion running as `job_id` to stop after its current page.
+///
+/// Jobs that already finished are ignored, so the frontend doesn't need to
+/// track whether a result has arrived first.
+#[tauri::command]
+pub fn cancel_conversion(registry: State<'_, ConversionRegistry>, job_id: String) {
+  if registry.cancel(&job_id) {
+    log::info!("cancelling conversion {job_id}");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cancel_flags_a_running_job() {
+    let registry = ConversionRegistry::default();
+    let job = registry.start(Some("a".into()));
+    assert!(job.token().check().is_ok());
+    assert!(registry.cancel("a"));
+    assert!(matches!(
+      job.token().check(),
+      Err(ConversionError::Cancelled)
+    ));
+  }
+
+  #[test]
+  fn cancelling_a_finished_job_is_a_no_op() {
+    let registry = ConversionRegistry::default();
+    drop(registry.start(Some("a".into())));
+    assert!(!registry.cancel("a"));
+    assert!(!registry.cancel("never-started"));
+  }
+
+  #[test]
+  fn generated_ids_are_unique() {
+    let registry = ConversionRegistry::default();
+    let (a, b) = (registry.start(None), registry.start(None));
+    assert_ne!(a.id, b.id);
+  }
+}
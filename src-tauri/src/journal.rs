@@ -0,0 +1,163 @@
+//! The journal of a directory batch: the files converted so far, kept in
+//! the directory so a batch cut short by a crash, a force-quit or sleep can
+//! pick up where it stopped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::batch::ConversionResult;
+
+const JOURNAL_FILE: &str = ".pdf2markdown-batch.json";
+
+#[derive(Serialize, Deserialize)]
+struct Contents {
+  /// What the files were converted with; a journal written with other
+  /// settings doesn't count.
+  settings: String,
+  done: Vec<ConversionResult>,
+}
+
+/// The files a batch has converted, written out after each one.
+pub struct Journal {
+  path: PathBuf,
+  settings: String,
+  done: Mutex<Vec<ConversionResult>>,
+}
+
+impl Journal {
+  /// The journal of a batch over `dir` converting with `settings`. If
+  /// `resume`, it carries on from the one a batch with the same settings
+  /// left there; one that can't be read, or is for other settings, is
+  /// started over.
+  pub fn open(dir: &Path, settings: &impl Serialize, resume: bool) -> Journal {
+    let path = dir.join(JOURNAL_FILE);
+    let settings = serde_json::to_string(settings).unwrap_or_default();
+    let done = if resume {
+      read(&path, &settings)
+    } else {
+      Vec::new()
+    };
+    Journal {
+      path,
+      settings,
+      done: Mutex::new(done),
+    }
+  }
+
+  /// What the batch being resumed got for `source`, if it was converted and
+  /// its output is still there.
+  pub fn completed(&self, source: &Path) -> Option<ConversionResult> {
+    let source = source.display().to_string();
+    self
+      .lock()
+      .iter()
+      .find(|result| result.source == source)
+      .filter(|result| {
+        result
+          .output
+          .as_ref()
+          .is_some_and(|output| Path::new(output).exists())
+      })
+      .cloned()
+  }
+
+  /// Records a converted file and writes the journal out. Failures are
+  /// logged; at worst the file is converted again on resuming.
+  pub fn record(&self, result: &ConversionResult) {
+    let mut done = self.lock();
+    done.retain(|done| done.source != result.source);
+    done.push(result.clone());
+    let contents = Contents {
+      settings: self.settings.clone(),
+      done: done.clone(),
+    };
+    // Written aside and moved into place, so a crash mid-write leaves the
+    // journal as it was rather than half written.
+    let partial = self.path.with_extension("json.partial");
+    let written = serde_json::to_vec(&contents)
+      .map_err(std::io::Error::from)
+      .and_then(|json| fs::write(&partial, json))
+      .and_then(|()| fs::rename(&partial, &self.path));
+    if let Err(err) = written {
+      log::warn!("cannot write batch journal {}: {err}", self.path.display());
+    }
+  }
+
+  /// Removes the journal of a batch that ran to the end.
+  pub fn finish(self) {
+    match fs::remove_file(&self.path) {
+      Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+        log::warn!("cannot remove batch journal {}: {err}", self.path.display());
+      }
+      _ => {}
+    }
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, Vec<ConversionResult>> {
+    self.done.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+/// The files done according to the journal at `path`, if it is there, whole
+/// and for `settings`.
+fn read(path: &Path, settings: &str) -> Vec<ConversionResult> {
+  let Ok(text) = fs::read_to_string(path) else {
+    return Vec::new();
+  };
+  match serde_json::from_str::<Contents>(&text) {
+    Ok(contents) if contents.settings == settings => contents.done,
+    Ok(_) => {
+      log::info!(
+        "batch journal {} is for other settings; starting over",
+        path.display()
+      );
+      Vec::new()
+    }
+    Err(err) => {
+      log::warn!(
+        "batch journal {} is unreadable, starting over: {err}",
+        path.display()
+      );
+      Vec::new()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::output::WriteOutcome;
+
+  #[test]
+  fn resuming_skips_what_was_done_with_the_same_settings() {
+    let dir = std::env::temp_dir().join(format!("pdf2markdown-journal-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("a.md");
+    fs::write(&output, "# A").unwrap();
+    let result = ConversionResult {
+      source: dir.join("a.pdf").display().to_string(),
+      output: Some(output.display().to_string()),
+      outcome: Some(WriteOutcome::Written),
+      error: None,
+    };
+    Journal::open(&dir, &"markdown", false).record(&result);
+
+    let resumed = Journal::open(&dir, &"markdown", true);
+    assert!(resumed.completed(&dir.join("a.pdf")).is_some());
+    assert!(resumed.completed(&dir.join("b.pdf")).is_none());
+    assert!(Journal::open(&dir, &"html", true)
+      .completed(&dir.join("a.pdf"))
+      .is_none());
+
+    fs::write(dir.join(JOURNAL_FILE), "{\"settings\": \"mark").unwrap();
+    assert!(Journal::open(&dir, &"markdown", true)
+      .completed(&dir.join("a.pdf"))
+      .is_none());
+    resumed.finish();
+    assert!(!dir.join(JOURNAL_FILE).exists());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
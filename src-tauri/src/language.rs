@@ -0,0 +1,253 @@
+//! Telling what language a text is in, to choose the Tesseract language
+//! packs a scanned document is read with.
+//!
+//! Scripts other than Latin mostly give the language away by themselves;
+//! languages written in Latin are told apart by their commonest words.
+
+/// Fewer letters than this are too few to go by.
+const MIN_LETTERS: usize = 200;
+
+/// A script, or a language within Latin, has to make up at least this share
+/// to be counted, so a quotation or a name doesn't add a language.
+const MIN_SHARE: f32 = 0.2;
+
+/// A language written in Latin needs at least this many of its common words
+/// to be counted.
+const MIN_WORDS: usize = 5;
+
+/// Short, frequent words of each of these languages. None is a common word
+/// in any of the others, so a hit counts for one language only.
+const COMMON_WORDS: &[(&str, &[&str])] = &[
+  (
+    "eng",
+    &[
+      "the", "and", "that", "with", "this", "are", "which", "from", "were", "been", "would",
+      "their",
+    ],
+  ),
+  (
+    "deu",
+    &[
+      "der", "und", "das", "ist", "nicht", "mit", "ein", "eine", "sich", "auch", "wird", "dem",
+    ],
+  ),
+  (
+    "fra",
+    &[
+      "les", "et", "est", "une", "dans", "pour", "pas", "au", "sont", "avec", "sur", "cette",
+    ],
+  ),
+  (
+    "spa",
+    &[
+      "el", "los", "las", "y", "pero", "muy", "sus", "hay", "fue", "cuando", "según",
+    ],
+  ),
+  (
+    "ita",
+    &[
+      "il", "di", "che", "della", "delle", "gli", "sono", "nel", "alla", "anche", "dei", "questo",
+      "più",
+    ],
+  ),
+  (
+    "por",
+    &[
+      "os", "em", "não", "uma", "com", "são", "ao", "pelo", "pela", "também", "foi", "seu",
+    ],
+  ),
+  (
+    "nld",
+    &[
+      "het", "een", "van", "niet", "dat", "op", "zijn", "voor", "ook", "wordt", "naar", "bij",
+    ],
+  ),
+  (
+    "pol",
+    &[
+      "się", "jest", "że", "w", "z", "oraz", "jak", "przez", "od", "dla", "są", "który",
+    ],
+  ),
+];
+
+/// The Tesseract languages `text` is written in, most of it first and
+/// joined with `+` (`"eng+fra"`), or `None` if it can't be told: there is
+/// too little text, or Latin letters in none of the languages known here.
+pub fn detect(text: &str) -> Option<String> {
+  let mut counts: Vec<(Script, usize)> = Vec::new();
+  for script in text.chars().filter_map(script) {
+    match counts.iter_mut().find(|(seen, _)| *seen == script) {
+      Some((_, count)) => *count += 1,
+      None => counts.push((script, 1)),
+    }
+  }
+  let letters: usize = counts.iter().map(|(_, count)| count).sum();
+  if letters < MIN_LETTERS {
+    return None;
+  }
+  counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+  let kana = counts.iter().any(|(script, _)| *script == Script::Kana);
+  let mut languages: Vec<&str> = Vec::new();
+  for (script, count) in counts {
+    if (count as f32) < MIN_SHARE * letters as f32 {
+      break;
+    }
+    let found = match script {
+      Script::Latin => latin_languages(text)?,
+      // Japanese mixes kana with Han characters; Chinese has no kana.
+      Script::Han if kana => vec!["jpn"],
+      script => vec![script.language()],
+    };
+    for language in found {
+      if !languages.contains(&language) {
+        languages.push(language);
+      }
+    }
+  }
+  Some(languages.join("+"))
+}
+
+/// The language Tesseract's orientation and script detection (`--psm 0`)
+/// says a page is in, if its script points to one: not for Latin, which is
+/// shared by too many.
+pub fn from_osd(output: &str) -> Option<&'static str> {
+  let name = output
+    .lines()
+    .find_map(|line| line.trim().strip_prefix("Script:"))?
+    .trim();
+  let script = match name {
+    "Greek" => Script::Greek,
+    "Cyrillic" => Script::Cyrillic,
+    "Hebrew" => Script::Hebrew,
+    "Arabic" => Script::Arabic,
+    "Devanagari" => Script::Devanagari,
+    "Thai" => Script::Thai,
+    "Hangul" | "Korean" => Script::Hangul,
+    "Japanese" | "Katakana" | "Hiragana" => Script::Kana,
+    "Han" => Script::Han,
+    _ => return None,
+  };
+  Some(script.language())
+}
+
+/// The languages written in Latin that `text` is in, by their common words.
+fn latin_languages(text: &str) -> Option<Vec<&'static str>> {
+  let mut hits: Vec<(&str, usize)> = COMMON_WORDS.iter().map(|&(lang, _)| (lang, 0)).collect();
+  for word in text.split(|c: char| !c.is_alphabetic()) {
+    let word = word.to_lowercase();
+    if let Some(index) = COMMON_WORDS
+      .iter()
+      .position(|(_, words)| words.contains(&word.as_str()))
+    {
+      hits[index].1 += 1;
+    }
+  }
+  let total: usize = hits.iter().map(|(_, count)| count).sum();
+  hits.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+  let found: Vec<&str> = hits
+    .into_iter()
+    .filter(|&(_, count)| count >= MIN_WORDS && count as f32 >= MIN_SHARE * total as f32)
+    .map(|(lang, _)| lang)
+    .collect();
+  (!found.is_empty()).then_some(found)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+  Latin,
+  Greek,
+  Cyrillic,
+  Hebrew,
+  Arabic,
+  Devanagari,
+  Thai,
+  Hangul,
+  /// Hiragana and katakana.
+  Kana,
+  Han,
+}
+
+impl Script {
+  /// The Tesseract language a script is taken to stand for.
+  fn language(self) -> &'static str {
+    match self {
+      // Not asked for: languages in Latin are told apart by their words.
+      Script::Latin => "eng",
+      Script::Greek => "ell",
+      Script::Cyrillic => "rus",
+      Script::Hebrew => "heb",
+      Script::Arabic => "ara",
+      Script::Devanagari => "hin",
+      Script::Thai => "tha",
+      Script::Hangul => "kor",
+      Script::Kana => "jpn",
+      Script::Han => "chi_sim",
+    }
+  }
+}
+
+/// The script of the letter `c`, if it is a letter of one told apart here.
+fn script(c: char) -> Option<Script> {
+  let script = match c {
+    'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' => Script::Latin,
+    '\u{0370}'..='\u{03FF}' => Script::Greek,
+    '\u{0400}'..='\u{04FF}' => Script::Cyrillic,
+    '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+    '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}' => Script::Arabic,
+    '\u{0900}'..='\u{097F}' => Script::Devanagari,
+    '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+    '\u{1100}'..='\u{11FF}' | '\u{AC00}'..='\u{D7AF}' => Script::Hangul,
+    '\u{3040}'..='\u{30FF}' => Script::Kana,
+    '\u{4E00}'..='\u{9FFF}' => Script::Han,
+    _ => return None,
+  };
+  c.is_alphabetic().then_some(script)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn languages_are_told_by_script_and_common_words() {
+    let english = "The results of the survey show that most of the members are satisfied \
+                   with the service, and this is the basis of the plan which was agreed. "
+      .repeat(3);
+    let french = "Les résultats de l'enquête montrent que la plupart des membres sont \
+                  satisfaits du service et qui est dans une situation pour le plan. "
+      .repeat(2);
+    let russian = "Результаты опроса показывают, что большинство участников довольны \
+                   работой службы и планом на следующий год. "
+      .repeat(6);
+    let portuguese = "Os resultados da pesquisa mostram que a maioria dos membros está \
+                      satisfeita com o serviço, e também que o plano foi aprovado pela direção. \
+                      Não há dúvida de que uma nova fase começa, e os sócios já conhecem o seu \
+                      papel nas decisões que são tomadas ao longo do ano. ";
+    let italian = "I risultati dell'indagine mostrano che la maggior parte dei soci è \
+                   soddisfatta del servizio, e anche che il piano è stato approvato dalla \
+                   direzione. Non c'è dubbio che questo sia l'inizio di una nuova fase per gli \
+                   associati, che sono più coinvolti nelle decisioni della società. ";
+    assert_eq!(detect(&english).as_deref(), Some("eng"));
+    // Words Spanish shares with them (`por`, `para`, `del`, `con`) don't count.
+    assert_eq!(detect(portuguese).as_deref(), Some("por"));
+    assert_eq!(detect(italian).as_deref(), Some("ita"));
+    assert_eq!(
+      detect(&format!("{english}{french}")).as_deref(),
+      Some("eng+fra")
+    );
+    assert_eq!(
+      detect(&format!("{russian}{english}")).as_deref(),
+      Some("rus+eng")
+    );
+    // Too little to go by.
+    assert_eq!(detect("The end."), None);
+  }
+
+  #[test]
+  fn scripts_reported_by_tesseract_name_a_language() {
+    let osd =
+      "Page number: 0\nOrientation in degrees: 0\nScript: Cyrillic\nScript confidence: 4.2\n";
+    assert_eq!(from_osd(osd), Some("rus"));
+    assert_eq!(from_osd("Script: Latin\n"), None);
+  }
+}
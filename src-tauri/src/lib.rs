@@ -1,29 +1,177 @@
+mod batch;
+mod bidi;
+mod boilerplate;
+mod cache;
+mod cli;
+mod clipboard;
+mod code;
+mod columns;
+mod convert;
+mod document;
+mod encode;
+mod error;
+mod extract;
+mod file_drop;
+mod footnotes;
+mod frontmatter;
+mod headings;
+mod history;
+mod html;
+mod images;
+mod inline;
+mod inspect;
+mod jobs;
+mod journal;
+mod language;
+mod links;
+mod lists;
+mod logging;
+mod markdown;
+mod math;
+mod merge;
+mod ocr;
+mod open_requests;
+mod options;
+mod outline;
+mod output;
+mod plain_text;
+mod prewarm;
+mod progress;
+mod quotes;
+mod ranges;
+mod remote;
+mod save_as;
+mod settings;
+mod split;
+mod stats;
+mod tables;
+mod toc;
+mod tray;
+mod updater;
+mod watcher;
+mod whitespace;
+mod window_effect;
+mod window_state;
+mod words;
+
+use std::path::Path;
+
+use batch::convert_directory;
+use cache::clear_cache;
+use clipboard::{copy_file_as_markdown, copy_to_clipboard};
+use convert::{
+  convert_pdf_pages, convert_pdf_to_file, convert_pdf_to_markdown, convert_pdf_to_page_files,
+  preview_pdf,
+};
+use history::{clear_history, list_history, record_conversion};
+use images::convert_with_images;
+use inspect::inspect_pdf;
+use jobs::{cancel_conversion, ConversionRegistry};
+use logging::open_log_folder;
+use merge::convert_and_merge;
+use ocr::convert_with_ocr;
+use open_requests::{take_open_requests, OpenRequests};
+use prewarm::{backend_ready, BackendReady};
+use remote::convert_remote;
+use save_as::pick_and_convert;
+use settings::{load_settings, save_settings};
+use split::convert_split_by_outline;
+use stats::markdown_stats;
 use tauri::Manager;
+use updater::{check_for_update, install_update};
+use watcher::{start_watching, stop_watching, FolderWatch};
+use window_effect::set_window_effect;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  // `convert` and `--batch` run headless and exit; anything else opens the app.
+  if let Some(code) = cli::run(std::env::args_os().skip(1)) {
+    std::process::exit(code);
+  }
+
   tauri::Builder::default()
+    // Registered first: launching a second instance hands its arguments to
+    // this one and exits before anything else is set up.
+    .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+      let args = argv.get(1..).unwrap_or_default();
+      open_requests::request(app, open_requests::paths_from_args(args, Path::new(&cwd)));
+    }))
+    .plugin(logging::plugin())
     .plugin(tauri_plugin_fs::init())
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_shell::init())
     .plugin(tauri_plugin_http::init())
+    .plugin(tauri_plugin_clipboard_manager::init())
+    .plugin(tauri_plugin_updater::Builder::new().build())
+    .manage(ConversionRegistry::default())
+    .manage(FolderWatch::default())
+    .manage(OpenRequests::default())
+    .manage(BackendReady::default())
+    .invoke_handler(tauri::generate_handler![
+      convert_pdf_to_markdown,
+      convert_pdf_pages,
+      preview_pdf,
+      inspect_pdf,
+      convert_pdf_to_file,
+      convert_pdf_to_page_files,
+      convert_split_by_outline,
+      convert_with_ocr,
+      convert_with_images,
+      convert_directory,
+      convert_and_merge,
+      convert_remote,
+      pick_and_convert,
+      copy_to_clipboard,
+      copy_file_as_markdown,
+      markdown_stats,
+      cancel_conversion,
+      start_watching,
+      stop_watching,
+      clear_cache,
+      record_conversion,
+      list_history,
+      clear_history,
+      open_log_folder,
+      load_settings,
+      save_settings,
+      set_window_effect,
+      check_for_update,
+      install_update,
+      take_open_requests,
+      backend_ready
+    ])
     .setup(|app| {
-      #[cfg(target_os = "macos")]
-      {
-        use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
-        let window = app.get_webview_window("main").unwrap();
-        apply_vibrancy(&window, NSVisualEffectMaterial::FullScreenUI, None, None)
-          .expect("Unsupported platform! 'apply_vibrancy' is only supported on macOS");
-      }
+      let window = app.get_webview_window("main").unwrap();
+      window_state::track(&window);
+      file_drop::listen(&window);
 
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
+      window_effect::restore(app.handle(), &window);
+      // Without a tray icon, hiding the window would leave no way back to it.
+      match tray::create(app.handle()) {
+        Ok(()) => tray::hide_on_close(&window),
+        Err(err) => log::warn!("cannot create tray icon: {err}"),
       }
+
+      // Windows and Linux pass the PDFs to open ("Open With") as arguments.
+      let args: Vec<String> = std::env::args().skip(1).collect();
+      let cwd = std::env::current_dir().unwrap_or_default();
+      open_requests::request(app.handle(), open_requests::paths_from_args(&args, &cwd));
+
+      // Off the main thread, so the window shows while it runs.
+      prewarm::start(app.handle());
       Ok(())
     })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|_app, _event| {
+      // macOS sends them as events instead, also while the app is running.
+      #[cfg(any(target_os = "macos", target_os = "ios"))]
+      if let tauri::RunEvent::Opened { urls } = _event {
+        let paths = urls
+          .iter()
+          .filter_map(|url| url.to_file_path().ok())
+          .collect();
+        open_requests::request(_app, paths);
+      }
+    });
 }
@@ -0,0 +1,246 @@
+//! Hyperlinks.
+//!
+//! Extraction tags every glyph that falls inside a link annotation's
+//! rectangle with the link's URL, so linked text ends up in spans of its own.
+//! Inline rendering turns runs of linked spans into links, and URLs written
+//! out in the text into links to themselves.
+//!
+//! A link to another place in the document gets the URL of the page it opens,
+//! `#page=3`, until the document is converted and
+//! [`toc::link_headings`](crate::toc::link_headings) points it at the heading
+//! there.
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::document::Inline;
+use crate::outline::Destinations;
+
+/// What the URL of a link to page `n` of the document starts with, followed
+/// by `n`.
+const PAGE_LINK: &str = "#page=";
+
+/// A link annotation.
+#[derive(Debug, Clone)]
+pub struct Link {
+  /// `[left, bottom, right, top]`, in page space.
+  pub rect: [f32; 4],
+  /// Where the link goes: a URL, or a page of the document as
+  /// [`page_link`] writes it.
+  pub uri: String,
+}
+
+impl Link {
+  /// Whether a glyph centred at `x` on a baseline at `y` lies on the link.
+  pub fn covers(&self, x: f32, y: f32, font_size: f32) -> bool {
+    let [left, bottom, right, top] = self.rect;
+    // Baselines often sit right on the rectangle's bottom edge, so test the
+    // middle of the glyph instead.
+    let middle = y + 0.3 * font_size;
+    (left..=right).contains(&x) && (bottom..=top).contains(&middle)
+  }
+}
+
+/// The links on a page, to URLs and to the other pages of the document.
+pub fn page_links(doc: &Document, page_id: ObjectId) -> Vec<Link> {
+  let Ok(annotations) = doc.get_page_annotations(page_id) else {
+    return Vec::new();
+  };
+  // Only needed for links within the document, which many PDFs don't have.
+  let mut destinations = None;
+  annotations
+    .into_iter()
+    .filter_map(|annotation| {
+      if annotation.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Link") {
+        return None;
+      }
+      let uri = match uri(doc, annotation) {
+        Some(uri) => uri,
+        None => {
+          let destinations = destinations.get_or_insert_with(|| Destinations::new(doc));
+          page_link(destinations.as_ref()?.target(annotation)?)
+        }
+      };
+      let rect = annotation
+        .get_deref(b"Rect", doc)
+        .and_then(Object::as_array)
+        .ok()?;
+      let mut corners = rect
+        .iter()
+        .map(|n| doc.dereference(n).ok().and_then(|(_, n)| n.as_float().ok()));
+      let mut next = || corners.next().flatten();
+      let (x1, y1, x2, y2) = (next()?, next()?, next()?, next()?);
+      (!uri.is_empty()).then(|| Link {
+        rect: [x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)],
+        uri,
+      })
+    })
+    .collect()
+}
+
+/// The URL a link annotation's `URI` action opens, if that is what it does.
+fn uri(doc: &Document, annotation: &Dictionary) -> Option<String> {
+  let action = annotation
+    .get_deref(b"A", doc)
+    .and_then(Object::as_dict)
+    .ok()?;
+  if action.get(b"S").and_then(Object::as_name).ok() != Some(b"URI") {
+    return None;
+  }
+  let uri = action
+    .get_deref(b"URI", doc)
+    .and_then(Object::as_str)
+    .ok()?;
+  Some(String::from_utf8_lossy(uri).trim().to_string())
+}
+
+/// The URL of a link to page `number` of the document.
+pub fn page_link(number: u32) -> String {
+  format!("{PAGE_LINK}{number}")
+}
+
+/// The page a URL written by [`page_link`] leads to.
+pub fn linked_page(url: &str) -> Option<u32> {
+  url.strip_prefix(PAGE_LINK)?.parse().ok()
+}
+
+/// Whether any page of `doc` links to another place in it.
+pub fn has_page_links(doc: &Document) -> bool {
+  doc.get_pages().into_values().any(|id| {
+    page_links(doc, id)
+      .iter()
+      .any(|link| linked_page(&link.uri).is_some())
+  })
+}
+
+/// Splits plain text into text and the URLs written out in it.
+pub fn bare_urls(text: &str) -> Vec<Inline> {
+  fn push_text(out: &mut Vec<Inline>, text: &str) {
+    match out.last_mut() {
+      Some(Inline::Text(last)) => last.push_str(text),
+      _ if text.is_empty() => {}
+      _ => out.push(Inline::Text(text.to_string())),
+    }
+  }
+
+  let mut out = Vec::new();
+  for (i, word) in text.split(' ').enumerate() {
+    if i > 0 {
+      push_text(&mut out, " ");
+    }
+    let start = word.find("http://").or_else(|| word.find("https://"));
+    let Some(start) = start.filter(|&s| word[..s].chars().all(|c| "([\"'".contains(c))) else {
+      push_text(&mut out, word);
+      continue;
+    };
+    // Sentence punctuation after a URL is almost never part of it.
+    let url = word[start..].trim_end_matches(|c| ".,;:!?'\")]".contains(c));
+    if url.len() <= "https://".len() {
+      push_text(&mut out, word);
+      continue;
+    }
+    let end = start + url.len();
+    push_text(&mut out, &word[..start]);
+    out.push(Inline::Url(url.to_string()));
+    push_text(&mut out, &word[end..]);
+  }
+  out
+}
+
+pub fn looks_like_url(text: &str) -> bool {
+  ["http://", "https://", "www.", "mailto:"]
+    .iter()
+    .any(|prefix| text.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+  use lopdf::dictionary;
+
+  use super::*;
+
+  #[test]
+  fn links_within_the_document_lead_to_pages() {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let [first, second] = [0, 1].map(|_| doc.new_object_id());
+    let rect = || vec![72.into(), 700.into(), 144.into(), 712.into()];
+    let annotations: Vec<Object> = [
+      dictionary! {
+        "Subtype" => "Link",
+        "Rect" => rect(),
+        "A" => dictionary! { "S" => "URI", "URI" => Object::string_literal("https://example.com") },
+      },
+      dictionary! {
+        "Subtype" => "Link",
+        "Rect" => rect(),
+        "A" => dictionary! { "S" => "GoTo", "D" => Object::string_literal("results") },
+      },
+      dictionary! {
+        "Subtype" => "Link",
+        "Rect" => rect(),
+        "Dest" => vec![second.into(), "Fit".into()],
+      },
+      // A link to another file.
+      dictionary! {
+        "Subtype" => "Link",
+        "Rect" => rect(),
+        "A" => dictionary! { "S" => "GoToR", "F" => Object::string_literal("other.pdf") },
+      },
+    ]
+    .into_iter()
+    .map(|annotation| doc.add_object(annotation).into())
+    .collect();
+    for (id, annots) in [(first, annotations), (second, Vec::new())] {
+      doc.objects.insert(
+        id,
+        Object::Dictionary(
+          dictionary! { "Type" => "Page", "Parent" => pages_id, "Annots" => annots },
+        ),
+      );
+    }
+    doc.objects.insert(
+      pages_id,
+      Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![first.into(), second.into()],
+        "Count" => 2,
+      }),
+    );
+    let dests = dictionary! { "results" => vec![second.into(), "XYZ".into()] };
+    let catalog_id = doc.add_object(dictionary! {
+      "Type" => "Catalog",
+      "Pages" => pages_id,
+      "Dests" => dests,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let uris: Vec<String> = page_links(&doc, first)
+      .into_iter()
+      .map(|link| link.uri)
+      .collect();
+    assert_eq!(uris, ["https://example.com", "#page=2", "#page=2"]);
+    assert_eq!(linked_page(&uris[1]), Some(2));
+    assert_eq!(linked_page(&uris[0]), None);
+    assert!(has_page_links(&doc));
+  }
+
+  #[test]
+  fn finds_bare_urls() {
+    let text = |s: &str| Inline::Text(s.to_string());
+    let url = |s: &str| Inline::Url(s.to_string());
+    assert_eq!(
+      bare_urls("Docs at https://example.com/docs. (https://x.org)"),
+      vec![
+        text("Docs at "),
+        url("https://example.com/docs"),
+        text(". ("),
+        url("https://x.org"),
+        text(")"),
+      ]
+    );
+    assert_eq!(
+      bare_urls("see https:// for"),
+      vec![text("see https:// for")]
+    );
+  }
+}
@@ -0,0 +1,371 @@
+//! List detection.
+//!
+//! A line starting with a bullet (`•`, `◦`, `-`, `*`, ...) or an enumerator
+//! (`1.`, `a)`, `iv.`) starts a list item; following lines indented past the
+//! marker continue it. Items whose marker sits further right than the one
+//! before are nested under it. A list ends at the first line that neither
+//! starts an item nor continues one, so numbering starts over in the next.
+
+use std::ops::Range;
+
+use crate::document::{Block, List, ListItem};
+use crate::extract::{TextLine, TextSpan};
+use crate::options::ConvertOptions;
+use crate::words::Vocabulary;
+use crate::{headings, inline};
+
+/// Glyphs that always mark an item, even when the text follows without a space.
+/// U+F0B7 is the bullet of the Symbol font as Word exports it.
+const BULLETS: &[char] = &['•', '◦', '▪', '▫', '●', '○', '‣', '⁃', '\u{f0b7}'];
+
+/// Markers that need a space after them, since they also occur in running text.
+const ASCII_BULLETS: &[char] = &['-', '*'];
+
+/// Lines further apart than this, relative to the font size, are not one list.
+const MAX_LINE_GAP: f32 = 2.0;
+
+/// How far right of a marker, relative to the font size, a line must start to
+/// belong to that item (continuation lines) or be nested under it (markers).
+const INDENT: f32 = 0.3;
+
+/// A list found among a page's lines.
+#[derive(Debug, Clone)]
+pub struct ListBlock {
+  /// The lines the list replaces.
+  pub lines: Range<usize>,
+  /// A [`Block::List`].
+  pub block: Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Marker {
+  Bullet,
+  Number(u32),
+}
+
+/// An item's marker, where it is, and the lines of its text.
+struct Item {
+  marker: Marker,
+  x: f32,
+  depth: usize,
+  lines: Vec<TextLine>,
+}
+
+/// Finds the lists in `lines`, which must be ordered top to bottom. Numbered
+/// lines that would make headings (`1. Introduction`) are left to be headings.
+pub fn find_lists(
+  lines: &[TextLine],
+  options: &ConvertOptions,
+  body_size: f32,
+  words: &Vocabulary,
+) -> Vec<ListBlock> {
+  let marker_of = |line: &TextLine| {
+    let (marker, len) = marker(&line.text())?;
+    let heading = || {
+      headings::heading_level(
+        std::slice::from_ref(line),
+        body_size,
+        options.heading_sensitivity,
+      )
+      .is_some()
+    };
+    if matches!(marker, Marker::Number(_)) && heading() {
+      return None;
+    }
+    Some((marker, len))
+  };
+  let close = |a: &TextLine, b: &TextLine| {
+    let gap = a.y() - b.y();
+    gap > 0.0 && gap <= MAX_LINE_GAP * a.font_size().max(b.font_size())
+  };
+
+  let mut lists = Vec::new();
+  let mut start = 0;
+  while start < lines.len() {
+    let Some((first, len)) = marker_of(&lines[start]) else {
+      start += 1;
+      continue;
+    };
+    let mut items = vec![Item {
+      marker: first,
+      x: left(&lines[start]),
+      depth: 0,
+      lines: vec![strip_marker(&lines[start], len)],
+    }];
+    // Marker positions of the open levels, outermost first.
+    let mut levels = vec![left(&lines[start])];
+    let mut end = start + 1;
+    while end < lines.len() && close(&lines[end - 1], &lines[end]) {
+      let line = &lines[end];
+      let x = left(line);
+      let tolerance = INDENT * line.font_size();
+      match marker_of(line) {
+        Some((marker, len)) => {
+          while levels.last().is_some_and(|&level| x < level - tolerance) {
+            levels.pop();
+          }
+          if !levels.last().is_some_and(|&level| x <= level + tolerance) {
+            levels.push(x);
+          }
+          let depth = levels.len() - 1;
+          // Switching between bullets and numbers at the top starts a new list.
+          if depth == 0 && is_numbered(marker) != is_numbered(first) {
+            break;
+          }
+          items.push(Item {
+            marker,
+            x,
+            depth,
+            lines: vec![strip_marker(line, len)],
+          });
+        }
+        None => {
+          let item = items.last_mut().expect("a list has at least one item");
+          if x <= item.x + tolerance {
+            break;
+          }
+          item.lines.push(line.clone());
+        }
+      }
+      end += 1;
+    }
+
+    let mut items = items.into_iter().peekable();
+    let list = build(&mut items, 0, options, words);
+    lists.push(ListBlock {
+      lines: start..end,
+      block: Block::List(list),
+    });
+    start = end;
+  }
+  lists
+}
+
+/// Builds the list at `depth` from the items that follow, with deeper items
+/// as nested lists. Stops at a shallower item or a change of marker kind.
+fn build(
+  items: &mut std::iter::Peekable<impl Iterator<Item = Item>>,
+  depth: usize,
+  options: &ConvertOptions,
+  words: &Vocabulary,
+) -> List {
+  let first = items.peek().map(|item| item.marker);
+  let mut list = List {
+    start: match first {
+      Some(Marker::Number(n)) => Some(n),
+      _ => None,
+    },
+    items: Vec::new(),
+  };
+  while let Some(item) = items.next_if(|item| {
+    item.depth == depth && first.is_some_and(|m| is_numbered(item.marker) == is_numbered(m))
+  }) {
+    let mut children = Vec::new();
+    while let Some(child_depth) = items.peek().map(|item| item.depth).filter(|&d| d > depth) {
+      children.push(build(items, child_depth, options, words));
+    }
+    list.items.push(ListItem {
+      content: inline::inlines(&item.lines, options, words),
+      children,
+    });
+  }
+  list
+}
+
+fn is_numbered(marker: Marker) -> bool {
+  matches!(marker, Marker::Number(_))
+}
+
+/// The item marker `text` starts with, and its length in bytes counting any
+/// leading whitespace.
+fn marker(text: &str) -> Option<(Marker, usize)> {
+  let trimmed = text.trim_start();
+  let lead = text.len() - trimmed.len();
+  let first = trimmed.chars().next()?;
+  let (marker, len) = if BULLETS.contains(&first) {
+    (Marker::Bullet, first.len_utf8())
+  } else if ASCII_BULLETS.contains(&first) {
+    if !trimmed[1..].starts_with(char::is_whitespace) {
+      return None;
+    }
+    (Marker::Bullet, 1)
+  } else {
+    let end = trimmed.find(['.', ')'])?;
+    let rest = &trimmed[end + 1..];
+    if !rest.starts_with(char::is_whitespace) {
+      return None;
+    }
+    // `s) 60` is more likely a stray label than a lettered list.
+    let lettered = !trimmed[..end].bytes().all(|b| b.is_ascii_digit());
+    if lettered && !rest.chars().any(char::is_alphabetic) {
+      return None;
+    }
+    (Marker::Number(enumerator(&trimmed[..end])?), end + 1)
+  };
+  // An item needs text after its marker.
+  if trimmed[len..].trim().is_empty() {
+    return None;
+  }
+  Some((marker, lead + len))
+}
+
+/// The value of a list enumerator: `3`, `c` or `iii` are all 3. A lone `i`
+/// is taken as a roman numeral, since lettered lists rarely get that far.
+fn enumerator(token: &str) -> Option<u32> {
+  if token.is_empty() {
+    return None;
+  }
+  if token.len() <= 3 && token.bytes().all(|b| b.is_ascii_digit()) {
+    return token.parse().ok();
+  }
+  let mut chars = token.chars();
+  if let (Some(c @ 'a'..='z'), None) = (chars.next(), chars.next()) {
+    if c != 'i' {
+      return Some(c as u32 - 'a' as u32 + 1);
+    }
+  }
+  roman(token)
+}
+
+/// Parses a lowercase roman numeral up to 39 (`xxxix`), which is as far as
+/// lists go.
+fn roman(token: &str) -> Option<u32> {
+  if token.len() > 6 {
+    return None;
+  }
+  let digit = |c| match c {
+    'i' => Some(1),
+    'v' => Some(5),
+    'x' => Some(10),
+    _ => None,
+  };
+  let digits: Vec<u32> = token.chars().map(digit).collect::<Option<_>>()?;
+  let mut value = 0;
+  for (i, &d) in digits.iter().enumerate() {
+    if digits.get(i + 1).is_some_and(|&next| next > d) {
+      value -= d as i32;
+    } else {
+      value += d as i32;
+    }
+  }
+  u32::try_from(value).ok().filter(|&v| v > 0)
+}
+
+/// The left edge of the line's text.
+fn left(line: &TextLine) -> f32 {
+  line
+    .spans
+    .iter()
+    .find(|span| !span.text.trim().is_empty())
+    .map_or(0.0, |span| span.x)
+}
+
+/// `line` without its first `len` bytes (the marker) and the space after it.
+fn strip_marker(line: &TextLine, len: usize) -> TextLine {
+  let mut remaining = len;
+  let mut spans: Vec<TextSpan> = Vec::with_capacity(line.spans.len());
+  for span in &line.spans {
+    if remaining >= span.text.len() {
+      remaining -= span.text.len();
+      continue;
+    }
+    let mut span = span.clone();
+    if remaining > 0 || spans.is_empty() {
+      span.text = span.text[remaining..].trim_start().to_string();
+      remaining = 0;
+    }
+    if !span.text.is_empty() {
+      spans.push(span);
+    }
+  }
+  TextLine { spans }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::markdown;
+
+  fn line(x: f32, y: f32, text: &str) -> TextLine {
+    TextLine {
+      spans: vec![TextSpan {
+        text: text.to_string(),
+        x,
+        y,
+        width: 5.0 * text.len() as f32,
+        font_size: 10.0,
+        font_name: "Helvetica".into(),
+        link: None,
+        style: Default::default(),
+        footnote: None,
+      }],
+    }
+  }
+
+  fn render(lines: &[TextLine]) -> Vec<(Range<usize>, String)> {
+    find_lists(
+      lines,
+      &ConvertOptions::default(),
+      10.0,
+      &Vocabulary::default(),
+    )
+    .into_iter()
+    .map(|list| (list.lines, markdown::block(&list.block)))
+    .collect()
+  }
+
+  #[test]
+  fn bullets_with_wrapped_and_nested_items() {
+    let lines = [
+      line(72.0, 700.0, "Shopping:"),
+      line(72.0, 688.0, "• Fruit, picked ripe and"),
+      line(82.0, 676.0, "eaten the same day"),
+      line(82.0, 664.0, "◦ apples"),
+      line(82.0, 652.0, "◦ pears"),
+      line(72.0, 640.0, "- Bread"),
+      line(72.0, 628.0, "That was all."),
+    ];
+    assert_eq!(
+      render(&lines),
+      vec![(
+        1..6,
+        "- Fruit, picked ripe and eaten the same day\n  - apples\n  - pears\n- Bread".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn numbering_restarts_in_each_list() {
+    let lines = [
+      line(72.0, 700.0, "1. First"),
+      line(72.0, 688.0, "2. Second"),
+      line(72.0, 676.0, "Some text in between."),
+      line(72.0, 664.0, "1) Again"),
+      line(82.0, 652.0, "a) lettered"),
+      line(82.0, 640.0, "b) and more"),
+      line(72.0, 628.0, "iii. Third, in roman"),
+    ];
+    assert_eq!(
+      render(&lines),
+      vec![
+        (0..2, "1. First\n2. Second".to_string()),
+        (
+          3..7,
+          "1. Again\n   1. lettered\n   2. and more\n2. Third, in roman".to_string()
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn markers_need_text_and_running_text_is_left_alone() {
+    assert_eq!(marker("•Tight bullet"), Some((Marker::Bullet, 3)));
+    assert_eq!(marker("  iv. Fourth"), Some((Marker::Number(4), 5)));
+    assert_eq!(marker("-5 degrees"), None);
+    assert_eq!(marker("3.5 percent"), None);
+    assert_eq!(marker("e.g. this"), None);
+    assert_eq!(marker("2024. A year"), None);
+    assert_eq!(marker("s) 60"), None);
+    assert_eq!(marker("•"), None);
+  }
+}
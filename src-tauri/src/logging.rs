@@ -0,0 +1,58 @@
+//! Persistent logging.
+//!
+//! Logs go to a rotating file in the app's log directory in every build, so
+//! there is something to look at when a conversion goes wrong on a user's
+//! machine. Debug builds also log to stdout.
+
+use std::fs;
+
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind, TimezoneStrategy};
+use tauri_plugin_shell::ShellExt;
+
+use crate::error::ConversionError;
+
+/// A log file is rotated once it grows past this many bytes.
+const MAX_LOG_SIZE: u128 = 2 * 1024 * 1024;
+
+/// Rotated log files kept alongside the current one.
+const KEEP_LOGS: usize = 2;
+
+/// The log plugin, writing `[time][level][module] message` lines.
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+  let mut targets = vec![Target::new(TargetKind::LogDir { file_name: None })];
+  if cfg!(debug_assertions) {
+    targets.push(Target::new(TargetKind::Stdout));
+  }
+  tauri_plugin_log::Builder::new()
+    .clear_targets()
+    .targets(targets)
+    .level(log::LevelFilter::Info)
+    .timezone_strategy(TimezoneStrategy::UseLocal)
+    .max_file_size(MAX_LOG_SIZE)
+    .rotation_strategy(RotationStrategy::KeepSome(KEEP_LOGS))
+    .build()
+}
+
+/// Shows the log directory in the system file manager.
+#[tauri::command]
+pub fn open_log_folder(app: AppHandle) -> Result<(), ConversionError> {
+  let dir = app.path().app_log_dir()?;
+  fs::create_dir_all(&dir)
+    .map_err(|e| ConversionError::io(format_args!("cannot create {}", dir.display()), e))?;
+  let program = if cfg!(target_os = "macos") {
+    "open"
+  } else if cfg!(windows) {
+    "explorer"
+  } else {
+    "xdg-open"
+  };
+  // Not waited on: `explorer` reports failure even when it opened the folder.
+  app
+    .shell()
+    .command(program)
+    .arg(&dir)
+    .spawn()
+    .map_err(|e| ConversionError::Internal(format!("cannot open {}: {e}", dir.display())))?;
+  Ok(())
+}
@@ -0,0 +1,362 @@
+//! Markdown rendering of the document model (GitHub-flavored for tables).
+
+use crate::document::{Block, Footnote, Inline, List};
+use crate::frontmatter;
+
+/// The whole document: YAML front matter if there is metadata, then `body`.
+pub fn document(metadata: &[(String, String)], body: &str) -> String {
+  format!("{}{body}{}", head(metadata), tail(metadata))
+}
+
+/// What comes before the body: the front matter.
+pub fn head(metadata: &[(String, String)]) -> String {
+  frontmatter::yaml(metadata)
+}
+
+pub fn tail(_metadata: &[(String, String)]) -> String {
+  "\n".to_string()
+}
+
+pub fn block(block: &Block) -> String {
+  match block {
+    Block::Heading { level, content } => heading(*level, content, None),
+    Block::Paragraph(content) => paragraph(content),
+    Block::List(items) => list(items, 0),
+    Block::Table(rows) => table(rows),
+    Block::Code(text) => {
+      let fence = fence_for(text, '`', 3);
+      format!("{fence}\n{text}\n{fence}")
+    }
+    Block::Image { src } => format!("![]({src})"),
+    Block::Math(tex) => format!("$$\n{tex}\n$$"),
+    Block::Quote(blocks) => quote(blocks),
+  }
+}
+
+/// A heading, with an `<a id>` in front of its text if it has an `id` of its
+/// own rather than the one GitHub gives it.
+pub fn heading(level: usize, content: &[Inline], id: Option<&str>) -> String {
+  let anchor = id.map_or_else(String::new, |id| format!("<a id=\"{id}\"></a>"));
+  format!("{} {anchor}{}", "#".repeat(level), inlines(content))
+}
+
+pub fn inlines(content: &[Inline]) -> String {
+  render_inlines(content, false)
+}
+
+/// The content of a paragraph, list item or footnote, where text at the start
+/// of a line could also read as a heading, list item or quote.
+fn paragraph(content: &[Inline]) -> String {
+  render_inlines(content, true)
+}
+
+fn render_inlines(content: &[Inline], block: bool) -> String {
+  // Next to math, a `$` in the text would read as the start of more of it.
+  let math = content
+    .iter()
+    .any(|inline| matches!(inline, Inline::Math(_)));
+  let mut out = String::new();
+  for inline in content {
+    match inline {
+      Inline::Text(text) => {
+        let line_start = block && (out.is_empty() || out.ends_with('\n'));
+        out.push_str(&escape_text(text, math, block, line_start));
+      }
+      other => out.push_str(&self::inline(other)),
+    }
+  }
+  out
+}
+
+fn inline(inline: &Inline) -> String {
+  match inline {
+    Inline::Text(text) => escape_text(text, false, false, false),
+    Inline::Code(code) => inline_code(code),
+    Inline::Link { content, url } => {
+      let label: String = content
+        .iter()
+        .map(|inline| match inline {
+          Inline::Text(text) => escape_anchor(text),
+          other => self::inline(other),
+        })
+        .collect();
+      format!("[{label}]({})", escape_url(url))
+    }
+    Inline::Url(url) => format!("<{}>", escape_url(url)),
+    Inline::Math(tex) => format!("${tex}$"),
+    Inline::Strong(content) => format!("**{}**", inlines(content)),
+    Inline::Emphasis(content) => format!("*{}*", inlines(content)),
+    Inline::FootnoteRef(label) => format!("[^{label}]"),
+  }
+}
+
+/// The footnote definitions, one per line: `[^1]: text`.
+pub fn footnotes(footnotes: &[Footnote]) -> String {
+  footnotes
+    .iter()
+    .map(|note| format!("[^{}]: {}", note.label, paragraph(&note.content)))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// One item per line, nested lists indented under their item's text.
+fn list(list: &List, indent: usize) -> String {
+  let mut out = Vec::new();
+  for (index, item) in list.items.iter().enumerate() {
+    let marker = list.marker(index);
+    out.push(format!(
+      "{}{marker} {}",
+      " ".repeat(indent),
+      paragraph(&item.content)
+    ));
+    for child in &item.children {
+      out.push(self::list(child, indent + marker.len() + 1));
+    }
+  }
+  out.join("\n")
+}
+
+/// Every line of the blocks quoted with `> `; a nested quote's `>` goes
+/// straight after, making `>> `.
+fn quote(blocks: &[Block]) -> String {
+  let inner: Vec<String> = blocks.iter().map(self::block).collect();
+  inner
+    .join("\n\n")
+    .lines()
+    .map(|line| match line {
+      "" => ">".to_string(),
+      line if line.starts_with('>') => format!(">{line}"),
+      line => format!("> {line}"),
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn table(rows: &[Vec<String>]) -> String {
+  let Some(header) = rows.first() else {
+    return String::new();
+  };
+  let row = |cells: &[String]| {
+    let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+    format!("| {} |", cells.join(" | "))
+  };
+  let mut out = vec![row(header)];
+  out.push(format!("|{}", "---|".repeat(header.len())));
+  out.extend(rows[1..].iter().map(|cells| row(cells)));
+  out.join("\n")
+}
+
+/// Wraps `text` in backticks as an inline code span.
+fn inline_code(text: &str) -> String {
+  let fence = fence_for(text, '`', 1);
+  // A space keeps backticks at the edges from joining the fence.
+  if text.starts_with('`') || text.ends_with('`') {
+    format!("{fence} {text} {fence}")
+  } else {
+    format!("{fence}{text}{fence}")
+  }
+}
+
+/// A run of `marker` longer than any in `text`, and at least `min` long.
+fn fence_for(text: &str, marker: char, min: usize) -> String {
+  let mut longest = 0;
+  let mut current = 0;
+  for c in text.chars() {
+    if c == marker {
+      current += 1;
+      longest = longest.max(current);
+    } else {
+      current = 0;
+    }
+  }
+  marker.to_string().repeat(min.max(longest + 1))
+}
+
+/// Escapes what would otherwise be read as Markdown in `text`: emphasis, code,
+/// links and HTML tags, `$` if there is `math` around, and with `block`, the
+/// `#`, `>`, `-`, `+` and `1.` that start headings, quotes and list items at
+/// the start of a line. The first line is only at one if `line_start`.
+fn escape_text(text: &str, math: bool, block: bool, line_start: bool) -> String {
+  let chars: Vec<char> = text.chars().collect();
+  let mut out = String::with_capacity(text.len());
+  let mut marker = if line_start {
+    block_marker(&chars)
+  } else {
+    None
+  };
+  for (i, &c) in chars.iter().enumerate() {
+    let before = i.checked_sub(1).map(|i| chars[i]);
+    let after = chars.get(i + 1).copied();
+    let escape = match c {
+      '*' | '`' | '[' => true,
+      '$' => math,
+      // An underscore inside a word never starts emphasis.
+      '_' => {
+        !(before.is_some_and(char::is_alphanumeric) && after.is_some_and(char::is_alphanumeric))
+      }
+      '\\' => after.is_some_and(|c| c.is_ascii_punctuation()),
+      '<' => after.is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?')),
+      _ => marker == Some(i),
+    };
+    if escape {
+      out.push('\\');
+    }
+    out.push(c);
+    if c == '\n' && block {
+      marker = block_marker(&chars[i + 1..]).map(|at| at + i + 1);
+    }
+  }
+  out
+}
+
+/// Where in `line` the character to escape is if it starts with what would
+/// make it a heading, quote, list item or the underline of a heading.
+fn block_marker(line: &[char]) -> Option<usize> {
+  let end = line.iter().position(|&c| c == '\n').unwrap_or(line.len());
+  let line = &line[..end];
+  let start = line.iter().take_while(|&&c| c == ' ').count();
+  let rest = &line[start..];
+  let spaced = |at: usize| rest.get(at).map_or(true, |&c| c == ' ');
+  let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+  let marker = match rest.first()? {
+    '#' => {
+      let hashes = rest.iter().take_while(|&&c| c == '#').count();
+      (hashes <= 6 && spaced(hashes)).then_some(0)
+    }
+    '>' => Some(0),
+    '-' | '+' if spaced(1) => Some(0),
+    '-' | '=' if rest.iter().all(|&c| c == rest[0] || c == ' ') => Some(0),
+    _ if (1..=9).contains(&digits)
+      && matches!(rest.get(digits), Some('.' | ')'))
+      && spaced(digits + 1) =>
+    {
+      Some(digits)
+    }
+    _ => None,
+  };
+  marker.map(|at| start + at)
+}
+
+fn escape_anchor(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for c in text.chars() {
+    if matches!(c, '[' | ']' | '\\') {
+      out.push('\\');
+    }
+    out.push(c);
+  }
+  out
+}
+
+fn escape_url(url: &str) -> String {
+  url
+    .replace(' ', "%20")
+    .replace('(', "%28")
+    .replace(')', "%29")
+    .replace('<', "%3C")
+    .replace('>', "%3E")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::document::ListItem;
+
+  #[test]
+  fn code_fences_outgrow_the_backticks_inside() {
+    assert_eq!(inline(&Inline::Code("ls -la".into())), "`ls -la`");
+    assert_eq!(inline(&Inline::Code("a`b".into())), "``a`b``");
+    assert_eq!(inline(&Inline::Code("`x`".into())), "`` `x` ``");
+    assert_eq!(
+      block(&Block::Code("```\nnested\n```".into())),
+      "````\n```\nnested\n```\n````"
+    );
+  }
+
+  #[test]
+  fn link_text_and_destinations_are_escaped() {
+    let link = Inline::Link {
+      content: vec![Inline::Text("annual [2021] report".into())],
+      url: "https://example.com/a b (1)".into(),
+    };
+    assert_eq!(
+      inline(&link),
+      "[annual \\[2021\\] report](https://example.com/a%20b%20%281%29)"
+    );
+    assert_eq!(
+      inline(&Inline::Url("https://example.com/x y".into())),
+      "<https://example.com/x%20y>"
+    );
+  }
+
+  #[test]
+  fn text_that_reads_as_markdown_is_escaped() {
+    let text = |text: &str| Inline::Text(text.into());
+    let paragraph = |content| block(&Block::Paragraph(content));
+    assert_eq!(
+      paragraph(vec![text(
+        "a *b* [c](d) `e` <br> _f_ snake_case 1 < 2 C:\\dir"
+      )]),
+      "a \\*b\\* \\[c](d) \\`e\\` \\<br> \\_f\\_ snake_case 1 < 2 C:\\dir"
+    );
+    assert_eq!(
+      paragraph(vec![text(
+        "# Not a heading\n1. not a list\n> not a quote\n- or\n---"
+      )]),
+      "\\# Not a heading\n1\\. not a list\n\\> not a quote\n\\- or\n\\---"
+    );
+    // Only text that starts a line, and never inside code or math.
+    assert_eq!(
+      paragraph(vec![
+        Inline::Code("*x*".into()),
+        text(" # 1. > after $5"),
+        Inline::Math("a_1 * b".into()),
+      ]),
+      "`*x*` # 1. > after \\$5$a_1 * b$"
+    );
+    assert_eq!(
+      block(&Block::Heading {
+        level: 2,
+        content: vec![text("1. Introduction")]
+      }),
+      "## 1. Introduction"
+    );
+  }
+
+  #[test]
+  fn nested_lists_are_indented_under_their_item() {
+    let item = |text: &str, children| ListItem {
+      content: vec![Inline::Text(text.into())],
+      children,
+    };
+    let bullets = List {
+      start: None,
+      items: vec![item("nested", Vec::new())],
+    };
+    let numbered = List {
+      start: Some(9),
+      items: vec![item("nine", vec![bullets]), item("ten", Vec::new())],
+    };
+    assert_eq!(list(&numbered, 0), "9. nine\n   - nested\n10. ten");
+  }
+
+  #[test]
+  fn table_cells_escape_pipes() {
+    let rows = vec![vec!["a|b".to_string()], vec!["c".to_string()]];
+    assert_eq!(table(&rows), "| a\\|b |\n|---|\n| c |");
+  }
+
+  #[test]
+  fn nested_quotes_prefix_every_line() {
+    let paragraph = |text: &str| Block::Paragraph(vec![Inline::Text(text.into())]);
+    let quote = Block::Quote(vec![
+      paragraph("Outer"),
+      Block::Quote(vec![paragraph("Inner\nline")]),
+      paragraph("Outer again"),
+    ]);
+    assert_eq!(
+      block(&quote),
+      "> Outer\n>\n>> Inner\n>> line\n>\n> Outer again"
+    );
+  }
+}
@@ -0,0 +1,686 @@
+//! Math, written out as LaTeX.
+//!
+//! A span is math if its font is a math font (Computer Modern's `CMMI`,
+//! `CMSY` and `CMEX`, the AMS `MSAM` and `MSBM`, Cambria Math, Symbol, ...)
+//! or its text is made of math symbols alone. Math inside a line of prose
+//! becomes inline math; lines with nothing but math become display equations.
+//!
+//! Only the structure the layout shows is rebuilt: smaller text raised or
+//! lowered from its line makes superscripts and subscripts, full-size text
+//! stacked above and below an equation's line makes fractions, and small text
+//! over or under a symbol makes its limits. The symbols themselves are kept,
+//! as LaTeX commands where there is one. Glyphs a font gives no Unicode value
+//! stay U+FFFD rather than being dropped.
+//!
+//! A `$` never makes math by itself, so prices in ordinary text stay text.
+
+use std::ops::Range;
+
+use crate::document::Block;
+use crate::extract::{TextLine, TextSpan};
+
+/// Font names containing one of these (ignoring case) are math fonts.
+const MATH_FONTS: &[&str] = &[
+  "cmmi", "cmsy", "cmex", "cmbsy", "msam", "msbm", "eufm", "rsfs", "math", "symbol", "mtextra",
+  "esint", "wasy",
+];
+
+/// Text smaller than this, relative to its line, may be a superscript or
+/// subscript.
+const SCRIPT_SIZE: f32 = 0.9;
+
+/// How far, relative to the font size, a script is raised or lowered.
+const SCRIPT_SHIFT: f32 = 0.15;
+
+/// Lines of one display this far apart, relative to the font size, are
+/// separate equations. A fraction's numerator and denominator sit closer.
+const ROW_GAP: f32 = 1.0;
+
+/// How far, relative to the font size, the limits of a sum or integral sit
+/// from its line.
+const LIMIT_GAP: f32 = 1.6;
+
+/// Symbols that make a line an equation of its own.
+const RELATIONS: &[char] = &['=', '<', '>', '≤', '≥', '≈', '≠', '≡', '→'];
+
+/// A display equation found among a page's lines.
+#[derive(Debug, Clone)]
+pub struct MathBlock {
+  /// The lines the equation replaces.
+  pub lines: Range<usize>,
+  /// A [`Block::Math`].
+  pub block: Block,
+}
+
+/// Whether `font_name` is that of a math font.
+pub fn is_math_font(font_name: &str) -> bool {
+  let name = font_name.to_ascii_lowercase();
+  MATH_FONTS.iter().any(|font| name.contains(font))
+}
+
+/// Whether `span` is math: set in a math font, or nothing but math symbols
+/// with digits and operators between them. Greek letters count as symbols
+/// only one at a time and next to an operator (`α = 2`) or alone (`θ`), so
+/// words and headings in Greek (`και`, `ΕΙΣΑΓΩΓΗ`) stay text.
+pub fn is_math(span: &TextSpan) -> bool {
+  let text = span.text.trim();
+  if text.is_empty() {
+    return false;
+  }
+  if is_math_font(&span.font_name) {
+    return true;
+  }
+  if !text
+    .chars()
+    .all(|c| is_symbol(c) || is_greek(c) || is_glue(c))
+  {
+    return false;
+  }
+  let word = text
+    .split(|c| !is_greek(c))
+    .any(|run| run.chars().count() > 1);
+  let operator = text
+    .chars()
+    .any(|c| "+-=<>/|^*".contains(c) || is_symbol(c));
+  let greek = text.chars().any(is_greek) && !word && (operator || text.chars().count() == 1);
+  text.chars().any(is_symbol) || greek
+}
+
+/// Characters found in formulas but hardly ever in prose.
+fn is_symbol(c: char) -> bool {
+  matches!(c,
+    '\u{2200}'..='\u{22ff}'
+    | '\u{27c0}'..='\u{27ef}'
+    | '\u{2980}'..='\u{2aff}'
+    | '\u{1d400}'..='\u{1d7ff}'
+    | 'ℂ' | 'ℎ' | 'ℓ' | 'ℕ' | 'ℚ' | 'ℝ' | 'ℤ'
+    | '±' | '×' | '÷'
+  )
+}
+
+/// Unaccented Greek letters, which formulas use as variables.
+fn is_greek(c: char) -> bool {
+  matches!(c, 'Α'..='Ω' | 'α'..='ω')
+}
+
+/// Characters that may come between math symbols: digits and ASCII
+/// operators. On their own they are not math; `$` never is.
+fn is_glue(c: char) -> bool {
+  c.is_ascii_digit() || c.is_whitespace() || "+-=<>()[]{}|/.,:;!'*".contains(c)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script {
+  Super,
+  Sub,
+}
+
+/// Whether `span` is raised or lowered from `baseline`, being smaller than
+/// the line's `size`.
+fn script(span: &TextSpan, baseline: f32, size: f32) -> Option<Script> {
+  if span.font_size >= SCRIPT_SIZE * size {
+    return None;
+  }
+  let shift = span.y - baseline;
+  if shift > SCRIPT_SHIFT * size {
+    Some(Script::Super)
+  } else if shift < -SCRIPT_SHIFT * size {
+    Some(Script::Sub)
+  } else {
+    None
+  }
+}
+
+/// Whether `span` is raised from `line` as a superscript, being smaller than
+/// the rest of it.
+pub fn is_superscript(span: &TextSpan, line: &TextLine) -> bool {
+  script(span, baseline(line), line.font_size()) == Some(Script::Super)
+}
+
+/// The baseline of the line's largest text, which scripts are raised or
+/// lowered from.
+fn baseline(line: &TextLine) -> f32 {
+  let size = line.font_size();
+  line
+    .spans
+    .iter()
+    .find(|span| span.font_size == size)
+    .map_or(line.y(), |span| span.y)
+}
+
+/// A math span of `line` as LaTeX, made a superscript or subscript if it is
+/// one.
+pub fn span_latex(span: &TextSpan, line: &TextLine) -> String {
+  let marker = match script(span, baseline(line), line.font_size()) {
+    Some(Script::Super) => '^',
+    Some(Script::Sub) => '_',
+    None => return latex(&span.text),
+  };
+  // Spaces around the script stay outside its braces.
+  let text = span.text.trim();
+  let trail = &span.text[span.text.trim_end().len()..];
+  format!("{marker}{{{}}}{trail}", latex(text).trim_end())
+}
+
+/// `text` with its symbols replaced by LaTeX commands and LaTeX's special
+/// characters escaped. Symbols without a command are kept as they are.
+pub fn latex(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    if let Some(command) = command(c) {
+      out.push_str(command);
+      // Keep the command's name apart from letters that follow: anything
+      // not ASCII may turn into one, or be taken for one.
+      let word = command.ends_with(|c: char| c.is_ascii_alphabetic());
+      let joins = |next: &char| !next.is_ascii() || next.is_ascii_alphanumeric();
+      if word && chars.peek().map_or(true, joins) {
+        out.push(' ');
+      }
+      continue;
+    }
+    match c {
+      '{' | '}' | '%' | '#' | '&' | '$' | '_' => {
+        out.push('\\');
+        out.push(c);
+      }
+      '\\' => out.push_str("\\backslash "),
+      '^' => out.push_str("\\wedge "),
+      '\u{2212}' => out.push('-'),
+      '′' => out.push('\''),
+      'ℎ' => out.push('h'),
+      // Mathematical italic letters, which fonts encode as such.
+      '\u{1d434}'..='\u{1d467}' => {
+        let index = c as u32 - 0x1d434;
+        let letter = if index < 26 {
+          b'A' + index as u8
+        } else {
+          b'a' + (index - 26) as u8
+        };
+        out.push(letter as char);
+      }
+      c => out.push(c),
+    }
+  }
+  out
+}
+
+/// The LaTeX command for a symbol, if it has one.
+fn command(c: char) -> Option<&'static str> {
+  Some(match c {
+    'α' => "\\alpha",
+    'β' => "\\beta",
+    'γ' => "\\gamma",
+    'δ' => "\\delta",
+    'ε' => "\\varepsilon",
+    'ϵ' => "\\epsilon",
+    'ζ' => "\\zeta",
+    'η' => "\\eta",
+    'θ' => "\\theta",
+    'ϑ' => "\\vartheta",
+    'ι' => "\\iota",
+    'κ' => "\\kappa",
+    'λ' => "\\lambda",
+    'μ' => "\\mu",
+    'ν' => "\\nu",
+    'ξ' => "\\xi",
+    'π' => "\\pi",
+    'ρ' => "\\rho",
+    'σ' => "\\sigma",
+    'ς' => "\\varsigma",
+    'τ' => "\\tau",
+    'υ' => "\\upsilon",
+    'φ' => "\\varphi",
+    'ϕ' => "\\phi",
+    'χ' => "\\chi",
+    'ψ' => "\\psi",
+    'ω' => "\\omega",
+    'Γ' => "\\Gamma",
+    'Δ' => "\\Delta",
+    'Θ' => "\\Theta",
+    'Λ' => "\\Lambda",
+    'Ξ' => "\\Xi",
+    'Π' => "\\Pi",
+    'Σ' => "\\Sigma",
+    'Υ' => "\\Upsilon",
+    'Φ' => "\\Phi",
+    'Ψ' => "\\Psi",
+    'Ω' => "\\Omega",
+    '±' => "\\pm",
+    '∓' => "\\mp",
+    '×' => "\\times",
+    '÷' => "\\div",
+    '·' | '⋅' => "\\cdot",
+    '∗' => "\\ast",
+    '∘' => "\\circ",
+    '≤' => "\\leq",
+    '≥' => "\\geq",
+    '≠' => "\\neq",
+    '≈' => "\\approx",
+    '≡' => "\\equiv",
+    '∼' => "\\sim",
+    '≃' => "\\simeq",
+    '≅' => "\\cong",
+    '∝' => "\\propto",
+    '≪' => "\\ll",
+    '≫' => "\\gg",
+    '∈' => "\\in",
+    '∉' => "\\notin",
+    '∋' => "\\ni",
+    '⊂' => "\\subset",
+    '⊃' => "\\supset",
+    '⊆' => "\\subseteq",
+    '⊇' => "\\supseteq",
+    '∪' => "\\cup",
+    '∩' => "\\cap",
+    '∖' => "\\setminus",
+    '∅' => "\\emptyset",
+    '∀' => "\\forall",
+    '∃' => "\\exists",
+    '¬' => "\\neg",
+    '∧' => "\\wedge",
+    '∨' => "\\vee",
+    '⊕' => "\\oplus",
+    '⊗' => "\\otimes",
+    '⊥' => "\\perp",
+    '∥' => "\\parallel",
+    '∣' => "\\mid",
+    '∑' => "\\sum",
+    '∏' => "\\prod",
+    '∫' => "\\int",
+    '∮' => "\\oint",
+    '√' => "\\sqrt",
+    '∞' => "\\infty",
+    '∂' => "\\partial",
+    '∇' => "\\nabla",
+    '→' => "\\to",
+    '←' => "\\leftarrow",
+    '↔' => "\\leftrightarrow",
+    '⇒' => "\\Rightarrow",
+    '⇐' => "\\Leftarrow",
+    '⇔' => "\\Leftrightarrow",
+    '↦' => "\\mapsto",
+    '…' => "\\ldots",
+    '⋯' => "\\cdots",
+    '⋮' => "\\vdots",
+    '⋱' => "\\ddots",
+    '⟨' => "\\langle",
+    '⟩' => "\\rangle",
+    '⌊' => "\\lfloor",
+    '⌋' => "\\rfloor",
+    '⌈' => "\\lceil",
+    '⌉' => "\\rceil",
+    'ℓ' => "\\ell",
+    'ℏ' => "\\hbar",
+    'ℝ' => "\\mathbb{R}",
+    'ℕ' => "\\mathbb{N}",
+    'ℤ' => "\\mathbb{Z}",
+    'ℚ' => "\\mathbb{Q}",
+    'ℂ' => "\\mathbb{C}",
+    _ => return None,
+  })
+}
+
+/// The equation number of a display line, `(3)` or `(2a)`, if its last span
+/// is one.
+fn equation_number(span: &TextSpan) -> Option<&str> {
+  let number = span.text.trim().strip_prefix('(')?.strip_suffix(')')?;
+  let digits = number.trim_end_matches(|c: char| c.is_ascii_lowercase());
+  (!digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())).then_some(number)
+}
+
+/// Which spans of `line` are math: those [`is_math`] says are, and digits
+/// or operators in another font that are scripts to math or come between
+/// math spans (`x` `= 2` `y`).
+pub fn math_spans(line: &TextLine) -> Vec<bool> {
+  let math: Vec<bool> = line.spans.iter().map(is_math).collect();
+  let (base, size) = (baseline(line), line.font_size());
+  line
+    .spans
+    .iter()
+    .enumerate()
+    .map(|(i, span)| {
+      if math[i] {
+        return true;
+      }
+      if !span.text.chars().all(is_glue) {
+        return false;
+      }
+      let before = i > 0 && math[i - 1];
+      if before && script(span, base, size).is_some() {
+        return true;
+      }
+      // Punctuation between two formulas more likely ends the first.
+      let after = math.get(i + 1).copied().unwrap_or(false);
+      let punctuation = span.text.contains(['.', ',', ';', ':', '!']);
+      before && after && !punctuation
+    })
+    .collect()
+}
+
+/// Whether `line` holds nothing but math, allowing for an equation number at
+/// its end.
+fn is_math_line(line: &TextLine) -> bool {
+  let math = math_spans(line);
+  let mut spans = line
+    .spans
+    .iter()
+    .zip(math)
+    .filter(|(span, _)| !span.text.trim().is_empty());
+  let Some((last, last_math)) = spans.next_back() else {
+    return false;
+  };
+  let (mut any, mut all) = (last_math, true);
+  for (_, math) in spans {
+    any |= math;
+    all &= math;
+  }
+  any && all && (last_math || equation_number(last).is_some())
+}
+
+/// Finds the display equations in `lines`, which must be ordered top to
+/// bottom: runs of lines holding only math, one equation per row.
+pub fn find_display_math(lines: &[TextLine]) -> Vec<MathBlock> {
+  let mut rows: Vec<Range<usize>> = Vec::new();
+  for (i, line) in lines.iter().enumerate() {
+    if !is_math_line(line) {
+      continue;
+    }
+    match rows.last_mut() {
+      Some(row) if row.end == i && same_row(&lines[row.clone()], line) => row.end = i + 1,
+      _ => rows.push(i..i + 1),
+    }
+  }
+  rows
+    .into_iter()
+    .map(|row| MathBlock {
+      block: Block::Math(render_row(&lines[row.clone()])),
+      lines: row,
+    })
+    .collect()
+}
+
+/// Whether `line` continues the equation in `row`: its numerator, its
+/// denominator, or the limits of one of its symbols.
+fn same_row(row: &[TextLine], line: &TextLine) -> bool {
+  let size = row
+    .iter()
+    .map(TextLine::font_size)
+    .fold(line.font_size(), f32::max);
+  let is_main = |line: &TextLine| line.font_size() >= SCRIPT_SIZE * size;
+  let Some(main) = row.iter().rev().find(|line| is_main(line)) else {
+    return row[row.len() - 1].y() - line.y() < LIMIT_GAP * size;
+  };
+  let gap = main.y() - line.y();
+  if !is_main(line) {
+    return gap < LIMIT_GAP * size;
+  }
+  // Two lines that each state a relation are two equations, however close.
+  let relation = |line: &TextLine| line.text().contains(RELATIONS);
+  gap < ROW_GAP * size && !(row.iter().any(relation) && relation(line))
+}
+
+/// Full-size text above or below an equation's line, or smaller text
+/// attached to a symbol on it.
+struct Part {
+  x: f32,
+  right: f32,
+  above: bool,
+  small: bool,
+  latex: String,
+}
+
+/// A piece of the equation's line, with the scripts attached to it.
+struct Item {
+  x: f32,
+  right: f32,
+  latex: String,
+  sub: Vec<String>,
+  sup: Vec<String>,
+}
+
+/// One equation as LaTeX. The line with the most text is the equation's; full
+/// text stacked above and below it make fractions, and smaller text over or
+/// under one of its pieces is that piece's limits.
+fn render_row(lines: &[TextLine]) -> String {
+  let size = lines.iter().map(TextLine::font_size).fold(0.0, f32::max);
+  let chars = |line: &TextLine| line.text().chars().filter(|c| !c.is_whitespace()).count();
+  let main = lines
+    .iter()
+    .enumerate()
+    .filter(|(_, line)| line.font_size() >= SCRIPT_SIZE * size)
+    .max_by_key(|(_, line)| chars(line))
+    .map_or(0, |(i, _)| i);
+  let main_line = &lines[main];
+
+  let mut tag = None;
+  let mut items: Vec<Item> = Vec::new();
+  for (span, math) in main_line.spans.iter().zip(math_spans(main_line)) {
+    if !math {
+      tag = tag.or(equation_number(span));
+      continue;
+    }
+    items.push(Item {
+      x: span.x,
+      right: span.right(),
+      latex: span_latex(span, main_line),
+      sub: Vec::new(),
+      sup: Vec::new(),
+    });
+  }
+
+  let mut parts: Vec<Part> = Vec::new();
+  for (i, line) in lines.iter().enumerate() {
+    if i == main {
+      continue;
+    }
+    for spans in clusters(line, size) {
+      parts.push(Part {
+        x: spans[0].x,
+        right: spans[spans.len() - 1].right(),
+        above: line.y() > main_line.y(),
+        small: line.font_size() < SCRIPT_SIZE * size,
+        latex: spans.iter().map(|span| span_latex(span, line)).collect(),
+      });
+    }
+  }
+
+  // A numerator and a denominator overlap each other, over a gap in the
+  // equation's line where the fraction bar is.
+  let overlaps = |a: (f32, f32), b: (f32, f32)| a.0 < b.1 && b.0 < a.1;
+  let on_bar: Vec<bool> = parts
+    .iter()
+    .map(|part| {
+      !part.small
+        && !items
+          .iter()
+          .any(|item| overlaps((item.x, item.right), (part.x, part.right)))
+    })
+    .collect();
+  let mut used = vec![false; parts.len()];
+  for n in 0..parts.len() {
+    let numerator = &parts[n];
+    if !numerator.above || !on_bar[n] || used[n] {
+      continue;
+    }
+    let denominator = (0..parts.len()).find(|&d| {
+      let part = &parts[d];
+      !part.above
+        && on_bar[d]
+        && !used[d]
+        && overlaps((numerator.x, numerator.right), (part.x, part.right))
+    });
+    if let Some(d) = denominator {
+      used[n] = true;
+      used[d] = true;
+      items.push(Item {
+        x: parts[n].x.min(parts[d].x),
+        right: parts[n].right.max(parts[d].right),
+        latex: format!(
+          "\\frac{{{}}}{{{}}}",
+          parts[n].latex.trim(),
+          parts[d].latex.trim()
+        ),
+        sub: Vec::new(),
+        sup: Vec::new(),
+      });
+    }
+  }
+  items.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+  // Everything else attaches to the piece it sits over or under, or else
+  // the one before it.
+  for (part, _) in parts.iter().zip(&used).filter(|(_, &used)| !used) {
+    let owner = items
+      .iter()
+      .position(|item| overlaps((item.x, item.right), (part.x, part.right)))
+      .or_else(|| items.iter().rposition(|item| item.x <= part.x));
+    let latex = part.latex.trim().to_string();
+    match owner {
+      Some(i) if part.above => items[i].sup.push(latex),
+      Some(i) => items[i].sub.push(latex),
+      None => items.insert(
+        0,
+        Item {
+          x: part.x,
+          right: part.right,
+          latex: if part.above {
+            format!("{{}}^{{{latex}}}")
+          } else {
+            format!("{{}}_{{{latex}}}")
+          },
+          sub: Vec::new(),
+          sup: Vec::new(),
+        },
+      ),
+    }
+  }
+
+  let mut out = String::new();
+  let mut right = f32::MIN;
+  for item in items {
+    // LaTeX ignores spaces in math, but they keep the source readable.
+    if item.x - right > SCRIPT_SHIFT * size && !item.latex.starts_with(['^', '_']) {
+      out.push(' ');
+    }
+    right = item.right;
+    // Scripts go right after what they belong to.
+    if item.latex.starts_with(['^', '_']) {
+      out.truncate(out.trim_end().len());
+    }
+    if item.sub.is_empty() && item.sup.is_empty() {
+      out.push_str(&item.latex);
+    } else {
+      out.push_str(item.latex.trim_end());
+    }
+    if !item.sub.is_empty() {
+      out.push_str(&format!("_{{{}}}", item.sub.join(" ")));
+    }
+    if !item.sup.is_empty() {
+      out.push_str(&format!("^{{{}}}", item.sup.join(" ")));
+    }
+  }
+  let mut out = out.split_whitespace().collect::<Vec<_>>().join(" ");
+  if let Some(number) = tag {
+    out.push_str(&format!(" \\tag{{{number}}}"));
+  }
+  out
+}
+
+/// The spans of `line` in groups separated by wide gaps, each of which is
+/// one numerator, denominator or limit.
+fn clusters(line: &TextLine, size: f32) -> Vec<&[TextSpan]> {
+  let mut out = Vec::new();
+  let mut start = 0;
+  for i in 1..line.spans.len() {
+    if line.spans[i].x - line.spans[i - 1].right() > size {
+      out.push(&line.spans[start..i]);
+      start = i;
+    }
+  }
+  if start < line.spans.len() {
+    out.push(&line.spans[start..]);
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn span(text: &str, font: &str, x: f32, y: f32, size: f32) -> TextSpan {
+    TextSpan {
+      text: text.to_string(),
+      x,
+      y,
+      width: text.chars().count() as f32 * size * 0.5,
+      font_size: size,
+      font_name: font.to_string(),
+      link: None,
+      style: Default::default(),
+      footnote: None,
+    }
+  }
+
+  #[test]
+  fn symbols_become_latex_commands() {
+    assert_eq!(latex("α ≤ β"), "\\alpha \\leq \\beta ");
+    assert_eq!(latex("x∈ℝ"), "x\\in \\mathbb{R}");
+    assert_eq!(latex("{𝑥} 50%"), "\\{x\\} 50\\%");
+    assert!(is_math(&span("∑ ≠", "Helvetica", 0.0, 0.0, 10.0)));
+    assert!(is_math(&span("x", "CMMI10", 0.0, 0.0, 10.0)));
+    assert!(!is_math(&span("$5", "Helvetica", 0.0, 0.0, 10.0)));
+    assert!(!is_math(&span("(1)", "Helvetica", 0.0, 0.0, 10.0)));
+  }
+
+  #[test]
+  fn greek_prose_is_not_math() {
+    assert!(is_math(&span("α = 2", "Helvetica", 0.0, 0.0, 10.0)));
+    assert!(is_math(&span("θ", "Helvetica", 0.0, 0.0, 10.0)));
+    assert!(!is_math(&span("ΕΙΣΑΓΩΓΗ", "Helvetica", 0.0, 0.0, 10.0)));
+    assert!(!is_math(&span("και", "Helvetica", 0.0, 0.0, 10.0)));
+    assert!(!is_math(&span(
+      "ΜΕΡΟΣ Α - ΓΕΝΙΚΑ",
+      "Helvetica",
+      0.0,
+      0.0,
+      10.0
+    )));
+    let heading = TextLine {
+      spans: vec![span(
+        "ΚΕΦΑΛΑΙΟ 1: ΕΙΣΑΓΩΓΗ",
+        "Helvetica-Bold",
+        0.0,
+        0.0,
+        16.0,
+      )],
+    };
+    assert!(find_display_math(&[heading]).is_empty());
+  }
+
+  #[test]
+  fn stacked_lines_make_fractions_and_limits() {
+    let line = |spans: Vec<TextSpan>| TextLine { spans };
+    let lines = [
+      line(vec![span("a + b", "CMMI10", 40.0, 108.0, 10.0)]),
+      line(vec![
+        span("y =", "CMMI10", 20.0, 100.0, 10.0),
+        span("∑", "CMEX10", 70.0, 100.0, 10.0),
+        span("x", "CMMI10", 80.0, 100.0, 10.0),
+        span("2", "CMR7", 85.0, 104.0, 7.0),
+        span("(1)", "Times-Roman", 200.0, 100.0, 10.0),
+      ]),
+      line(vec![
+        span("c", "CMMI10", 45.0, 92.0, 10.0),
+        span("i", "CMMI7", 71.0, 91.0, 7.0),
+      ]),
+    ];
+    let found = find_display_math(&lines);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].lines, 0..3);
+    assert_eq!(
+      found[0].block,
+      Block::Math("y = \\frac{a + b}{c} \\sum_{i} x^{2} \\tag{1}".into())
+    );
+  }
+}
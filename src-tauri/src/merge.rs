@@ -0,0 +1,116 @@
+//! Converting several PDFs into a single document.
+
+use std::path::Path;
+
+use tauri::{AppHandle, State};
+
+use crate::cache::{self, Cache};
+use crate::convert;
+use crate::document::{self, OutputFormat};
+use crate::error::ConversionError;
+use crate::footnotes::Numbering;
+use crate::jobs::ConversionRegistry;
+use crate::options::ConvertOptions;
+
+/// Converts `paths` in order and joins them into one document in `format`,
+/// separated by `separator`. The default separator is a horizontal rule in
+/// Markdown and HTML and a blank line in plain text.
+///
+/// Front matter, if enabled, comes from the first document only, and
+/// footnotes are numbered through all of them. If any
+/// input can't be converted the whole merge fails, naming that file.
+#[tauri::command]
+pub async fn convert_and_merge(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  paths: Vec<String>,
+  separator: Option<String>,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  job_id: Option<String>,
+) -> Result<String, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let separator = separator.unwrap_or_else(|| default_separator(format).to_string());
+  let job = registry.start(job_id).announce(&app);
+  let cache = Cache::open(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut documents = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+      let options = ConvertOptions {
+        emit_frontmatter: options.emit_frontmatter && index == 0,
+        ..options.clone()
+      };
+      let source = Path::new(path);
+      // Shares entries with `convert_pdf_to_markdown`.
+      let document = cache::cached(cache.as_ref(), source, &("document", &options), || {
+        convert::convert_file(source, None, &options, job.token())
+      })
+      .map_err(|err| err.in_file(source))?;
+      documents.push(document);
+    }
+    // Each document numbers its footnotes from 1; merged, they go on from
+    // the document before.
+    let mut numbering = Numbering::default();
+    for page in documents
+      .iter_mut()
+      .flat_map(|document| &mut document.pages)
+    {
+      numbering.page(page);
+    }
+    Ok(document::render_merged(&documents, &separator, format))
+  })
+  .await?
+}
+
+fn default_separator(format: OutputFormat) -> &'static str {
+  match format {
+    OutputFormat::Markdown => "\n\n---\n\n",
+    OutputFormat::Html => "\n\n<hr>\n\n",
+    OutputFormat::PlainText => "\n\n",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::document::{Block, Document, Inline, Page};
+
+  fn heading(text: &str) -> Document {
+    Document {
+      metadata: Vec::new(),
+      pages: vec![Page {
+        number: 1,
+        blocks: vec![Block::Heading {
+          level: 1,
+          content: vec![Inline::Text(text.to_string())],
+        }],
+        footnotes: Vec::new(),
+      }],
+      page_separator: None,
+      tight_output: false,
+    }
+  }
+
+  #[test]
+  fn joins_documents_with_the_separator() {
+    let documents = [heading("One"), Document::default(), heading("Two")];
+    let separator = default_separator(OutputFormat::Markdown);
+    assert_eq!(
+      document::render_merged(&documents, separator, OutputFormat::Markdown),
+      "# One\n\n---\n\n# Two\n"
+    );
+    assert_eq!(
+      document::render_merged(&documents, "\n\n", OutputFormat::Markdown),
+      "# One\n\n# Two\n"
+    );
+    assert_eq!(
+      document::render_merged(
+        &documents,
+        default_separator(OutputFormat::Html),
+        OutputFormat::Html
+      ),
+      "<h1 id=\"one\">One</h1>\n\n<hr>\n\n<h1 id=\"two\">Two</h1>\n"
+    );
+  }
+}
@@ -0,0 +1,535 @@
+//! OCR fallback for scanned pages.
+//!
+//! Pages that yield no text are rasterized with `pdftoppm` (poppler) and the
+//! image is run through the `tesseract` CLI. Both are external programs
+//! launched through the shell plugin. A PDF that needs a password is
+//! decrypted into a private scratch directory first, rather than handing the
+//! password to `pdftoppm` where any process could read it.
+//!
+//! Unless told which language to read, the text of the other pages, or a
+//! quick pass over the first scanned one, decides it.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_shell::ShellExt;
+
+use crate::cache::Cache;
+use crate::convert;
+use crate::document::{self, Block, Inline, OutputFormat};
+use crate::error::ConversionError;
+use crate::jobs::ConversionRegistry;
+use crate::options::ConvertOptions;
+use crate::words::Vocabulary;
+use crate::{
+  boilerplate, footnotes, frontmatter, headings, language, outline, plain_text, settings, toc,
+};
+
+/// Rasterization resolution; Tesseract is most accurate around 300 DPI.
+const OCR_DPI: u32 = 300;
+
+/// Resolution of the quick pass that tells the language of a document with
+/// no text to go by.
+const DETECT_DPI: u32 = 150;
+
+/// How much extracted text, in bytes, is looked at to tell the language.
+const SAMPLE_LEN: usize = 20_000;
+
+/// A conversion with OCR and the language its scanned pages were read in.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrConversion {
+  pub output: String,
+  /// Tesseract's code for the language, e.g. `"eng"` or `"eng+fra"`.
+  pub lang: String,
+  /// Whether `lang` was detected rather than given, so the UI can offer to
+  /// convert again in another if the text comes out garbled.
+  pub detected: bool,
+}
+
+/// Payload of the `ocr-progress` event, sent before each page is recognized.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrProgress {
+  /// One-based number of the page being recognized.
+  pub page: u32,
+  /// How many pages have been recognized so far, out of `total`.
+  pub current: usize,
+  pub total: usize,
+}
+
+/// Converts a PDF, running OCR on every page without extractable text.
+///
+/// `lang` takes Tesseract codes (`"eng"`, `"deu"`, `"eng+fra"`) or the common
+/// two-letter ISO codes. Without it, the language is detected among the
+/// installed ones, falling back to the one in the settings when it can't be
+/// told; the result says which was used. Results are cached per language
+/// until the file changes, except for conversions that need a `password`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn convert_with_ocr(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  lang: Option<String>,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  password: Option<String>,
+  job_id: Option<String>,
+) -> Result<OcrConversion, ConversionError> {
+  // The language asked for, or the one detection falls back to.
+  let (lang, detected) = match lang.as_deref().map(str::trim) {
+    Some(lang) if !lang.is_empty() => (tesseract_lang(lang)?, false),
+    _ => (
+      tesseract_lang(&settings::load_settings(app.clone()).ocr_language)?,
+      true,
+    ),
+  };
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let source = PathBuf::from(&path);
+  let job = registry.start(job_id).report_progress(&app);
+  let cancel = job.token().clone();
+
+  // Hashing reads the whole file, so look the result up off the async runtime.
+  let cache = Cache::open(&app).filter(|_| password.is_none());
+  let (lookup_source, variant) = (
+    source.clone(),
+    ("ocr", lang.clone(), detected, options.clone()),
+  );
+  let slot = tauri::async_runtime::spawn_blocking(move || {
+    let slot = cache?.slot(&lookup_source, &variant)?;
+    let cached = slot.load::<(document::Document, String)>();
+    Some((slot, cached))
+  })
+  .await?;
+  let slot = match slot {
+    Some((_, Some((output, lang)))) => {
+      return Ok(OcrConversion {
+        output: output.render(format),
+        lang,
+        detected,
+      })
+    }
+    Some((slot, None)) => Some(slot),
+    None => None,
+  };
+
+  let workdir = WorkDir::create()?;
+  let (extract_from, decrypted) = (source.clone(), workdir.0.join("decrypted.pdf"));
+  let tight_output = options.tight_output;
+  let (metadata, page_separator, bookmarks, mut pages, raster_source) =
+    tauri::async_runtime::spawn_blocking(move || {
+      let mut doc = convert::load_document(&extract_from, password.as_deref())?;
+      // Pages are rendered from a decrypted copy, so that the password is not
+      // on a command line other users can read.
+      let raster_source = match password {
+        Some(_) => {
+          doc.save(&decrypted)?;
+          decrypted
+        }
+        None => extract_from,
+      };
+      let mut pages = convert::extract_pages(&doc, doc.get_pages().into_keys(), &cancel)?;
+      let body_size = headings::body_font_size(pages.iter().map(|(_, page)| page));
+      let words = Vocabulary::of(pages.iter().map(|(_, page)| page));
+      if options.strip_boilerplate {
+        boilerplate::strip(&mut pages, options.boilerplate_threshold);
+      }
+      let rendered = pages
+        .iter()
+        .map(|(number, page)| {
+          convert::render_page(*number, page, Vec::new(), &options, body_size, &words)
+        })
+        .collect::<Vec<_>>();
+      let metadata = frontmatter::metadata(&doc, &options);
+      // The table of contents waits until OCR has filled in the scanned pages,
+      // the first of which it goes on.
+      let bookmarks = options.generate_toc.then(|| outline::bookmarks(&doc));
+      Ok::<_, ConversionError>((
+        metadata,
+        options.page_separator.clone(),
+        bookmarks,
+        rendered,
+        raster_source,
+      ))
+    })
+    .await??;
+
+  let scanned: Vec<usize> = (0..pages.len())
+    .filter(|&i| {
+      pages[i]
+        .blocks
+        .iter()
+        .all(|block| plain_text::block(block).trim().is_empty())
+    })
+    .collect();
+  // Pages with text are done once extracted; the time left is estimated
+  // from the scanned ones alone, which take far longer.
+  job.token().expect_pages(pages.len(), 1);
+  let last_with_text = (0..pages.len())
+    .rev()
+    .find(|i| !scanned.contains(i))
+    .map(|i| pages[i].number);
+  if let Some(last) = last_with_text {
+    job.token().pages_skipped(pages.len() - scanned.len(), last);
+  }
+  let lang = if detected {
+    let found = detect_language(
+      &app,
+      &raster_source,
+      &text_sample(&pages, &scanned),
+      scanned.first().map(|&index| pages[index].number),
+      &lang,
+      &workdir.0,
+    )
+    .await;
+    let lang = installed(&app, found).await.unwrap_or(lang);
+    log::info!("reading {} in {lang}", source.display());
+    lang
+  } else {
+    lang
+  };
+  if !scanned.is_empty() {
+    for (current, &index) in scanned.iter().enumerate() {
+      job.token().check()?;
+      let number = pages[index].number;
+      let progress = OcrProgress {
+        page: number,
+        current,
+        total: scanned.len(),
+      };
+      if let Err(err) = app.emit("ocr-progress", progress) {
+        log::warn!("failed to emit OCR progress: {err}");
+      }
+      pages[index].blocks = ocr_page(&app, &raster_source, number, &lang, &workdir.0).await?;
+      job.token().page_done(number);
+    }
+  }
+
+  footnotes::number(&mut pages);
+  let headings = toc::document_headings(&pages);
+  toc::link_headings(&mut pages, &headings);
+  if let Some(bookmarks) = bookmarks {
+    toc::prepend(bookmarks, &mut pages);
+  }
+  let output = document::Document {
+    metadata,
+    pages,
+    page_separator,
+    tight_output,
+  };
+  if let Some(slot) = slot {
+    slot.store(&(&output, &lang));
+  }
+  Ok(OcrConversion {
+    output: output.render(format),
+    lang,
+    detected,
+  })
+}
+
+/// Text of the pages that have some, up to [`SAMPLE_LEN`] of it.
+fn text_sample(pages: &[document::Page], scanned: &[usize]) -> String {
+  let mut sample = String::new();
+  for (index, page) in pages.iter().enumerate() {
+    if scanned.contains(&index) {
+      continue;
+    }
+    for block in &page.blocks {
+      if sample.len() >= SAMPLE_LEN {
+        return sample;
+      }
+      sample.push_str(&plain_text::block(block));
+      sample.push('\n');
+    }
+  }
+  sample
+}
+
+/// The languages a document is in: told from its extracted `sample` if
+/// there is enough of it, or else from the page numbered `scanned` in a
+/// quick pass, first for its script and then, for Latin, by reading it in
+/// `default`. `None` if it can't be told.
+async fn detect_language(
+  app: &AppHandle,
+  source: &Path,
+  sample: &str,
+  scanned: Option<u32>,
+  default: &str,
+  workdir: &Path,
+) -> Option<String> {
+  if let Some(lang) = language::detect(sample) {
+    return Some(lang);
+  }
+  let number = scanned?;
+  let image = match rasterize(app, source, number, DETECT_DPI, workdir).await {
+    Ok(image) => image,
+    Err(err) => {
+      log::warn!("cannot tell the language of page {number}: {err}");
+      return None;
+    }
+  };
+  // Script detection needs Tesseract's `osd` data, which may not be there.
+  let script = match recognize(app, &image, &["--psm", "0", "-l", "osd"]).await {
+    Ok(osd) => language::from_osd(&osd),
+    Err(_) => None,
+  };
+  let found = match script {
+    Some(lang) => Some(lang.to_string()),
+    None => recognize(app, &image, &["-l", default])
+      .await
+      .ok()
+      .and_then(|text| language::detect(&text)),
+  };
+  let _ = fs::remove_file(&image);
+  found
+}
+
+/// The languages of `found` that Tesseract has packs for, or `None` if none
+/// of them has one. Kept as they are if the installed ones can't be listed.
+async fn installed(app: &AppHandle, found: Option<String>) -> Option<String> {
+  let found = found?;
+  let Ok(listed) = list_langs(app).await else {
+    return Some(found);
+  };
+  // The first line says where the packs are.
+  let installed: Vec<&str> = listed.lines().skip(1).map(str::trim).collect();
+  let usable: Vec<&str> = found
+    .split('+')
+    .filter(|lang| installed.contains(lang))
+    .collect();
+  if usable.len() < found.split('+').count() {
+    log::info!("detected {found}, but only {usable:?} are installed");
+  }
+  (!usable.is_empty()).then(|| usable.join("+"))
+}
+
+/// What `tesseract --list-langs` prints: where the language packs are, then
+/// one installed language per line.
+async fn list_langs(app: &AppHandle) -> Result<String, ConversionError> {
+  let output = app
+    .shell()
+    .command("tesseract")
+    .arg("--list-langs")
+    .output()
+    .await
+    .map_err(|e| missing_tool(e, "tesseract", "tesseract"))?;
+  if !output.status.success() {
+    return Err(ConversionError::Ocr(
+      String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ));
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Checks that Tesseract is there and reads the packs of `lang`, the default
+/// language, so the first OCR finds them in the disk cache. Whatever is
+/// missing is logged and left for OCR to report.
+pub async fn prewarm(app: &AppHandle, lang: &str) {
+  let lang = match tesseract_lang(lang) {
+    Ok(lang) => lang,
+    Err(err) => {
+      log::warn!("OCR not warmed up: {err}");
+      return;
+    }
+  };
+  let listed = match list_langs(app).await {
+    Ok(listed) => listed,
+    Err(err) => {
+      log::info!("OCR not warmed up: {err}");
+      return;
+    }
+  };
+  // `List of available languages in "/usr/share/tessdata/" (3):`, though
+  // older versions leave the directory out.
+  let dir = listed
+    .lines()
+    .next()
+    .and_then(|line| line.split('"').nth(1));
+  let installed: Vec<&str> = listed.lines().skip(1).map(str::trim).collect();
+  for code in lang.split('+') {
+    if !installed.contains(&code) {
+      log::warn!("no Tesseract language pack for {code}");
+      continue;
+    }
+    let Some(dir) = dir else {
+      continue;
+    };
+    let pack = Path::new(dir).join(format!("{code}.traineddata"));
+    let read = fs::File::open(&pack).and_then(|mut file| io::copy(&mut file, &mut io::sink()));
+    if let Err(err) = read {
+      log::info!("cannot read {}: {err}", pack.display());
+    }
+  }
+}
+
+async fn ocr_page(
+  app: &AppHandle,
+  source: &Path,
+  number: u32,
+  lang: &str,
+  workdir: &Path,
+) -> Result<Vec<Block>, ConversionError> {
+  let image = rasterize(app, source, number, OCR_DPI, workdir).await?;
+  let text = recognize(app, &image, &["-l", lang]).await;
+  // Best effort; the whole directory is removed at the end anyway.
+  let _ = fs::remove_file(&image);
+  let text = text.map_err(|err| match err {
+    ConversionError::Ocr(message) => {
+      ConversionError::Ocr(format!("tesseract failed on page {number}: {message}"))
+    }
+    err => err,
+  })?;
+  Ok(ocr_paragraphs(&text))
+}
+
+/// Renders page `number` of `source`, which must not need a password, as a
+/// PNG in `workdir`, returning its path.
+async fn rasterize(
+  app: &AppHandle,
+  source: &Path,
+  number: u32,
+  dpi: u32,
+  workdir: &Path,
+) -> Result<PathBuf, ConversionError> {
+  let prefix = workdir.join(format!("page-{number}"));
+  let page = number.to_string();
+  let dpi = dpi.to_string();
+  let output = app
+    .shell()
+    .command("pdftoppm")
+    .args(["-f", &page, "-l", &page, "-r", &dpi, "-png", "-singlefile"])
+    .arg(source)
+    .arg(&prefix)
+    .output()
+    .await
+    .map_err(|e| missing_tool(e, "pdftoppm", "poppler"))?;
+  if !output.status.success() {
+    return Err(ConversionError::Ocr(format!(
+      "pdftoppm could not rasterize page {number}: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    )));
+  }
+
+  Ok(prefix.with_extension("png"))
+}
+
+/// What `tesseract` prints for `image` given `args`.
+async fn recognize(
+  app: &AppHandle,
+  image: &Path,
+  args: &[&str],
+) -> Result<String, ConversionError> {
+  let output = app
+    .shell()
+    .command("tesseract")
+    .arg(image)
+    .arg("stdout")
+    .args(args)
+    .output()
+    .await
+    .map_err(|e| missing_tool(e, "tesseract", "tesseract"))?;
+  if !output.status.success() {
+    return Err(ConversionError::Ocr(
+      String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ));
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Tesseract separates paragraphs with blank lines and wraps within them.
+fn ocr_paragraphs(text: &str) -> Vec<Block> {
+  text
+    .split("\n\n")
+    .map(|paragraph| {
+      paragraph
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+    })
+    .filter(|paragraph| !paragraph.is_empty())
+    .map(|paragraph| Block::Paragraph(vec![Inline::Text(paragraph)]))
+    .collect()
+}
+
+fn missing_tool(err: tauri_plugin_shell::Error, program: &str, package: &str) -> ConversionError {
+  match err {
+    tauri_plugin_shell::Error::Io(err) if err.kind() == io::ErrorKind::NotFound => {
+      ConversionError::Ocr(format!(
+        "`{program}` was not found; install {package} and make sure `{program}` is on your PATH"
+      ))
+    }
+    err => ConversionError::Ocr(format!("could not run `{program}`: {err}")),
+  }
+}
+
+/// Maps a user-supplied language to Tesseract's codes, defaulting to English.
+fn tesseract_lang(lang: &str) -> Result<String, ConversionError> {
+  let lang = lang.trim();
+  if lang.is_empty() {
+    return Ok("eng".into());
+  }
+  lang
+    .split('+')
+    .map(|code| {
+      let code = code.trim().to_ascii_lowercase();
+      let mapped = match code.as_str() {
+        "en" => "eng",
+        "de" => "deu",
+        "fr" => "fra",
+        "es" => "spa",
+        "it" => "ita",
+        "pt" => "por",
+        "nl" => "nld",
+        "ru" => "rus",
+        "pl" => "pol",
+        "ar" => "ara",
+        "he" => "heb",
+        "ja" => "jpn",
+        "ko" => "kor",
+        "zh" => "chi_sim",
+        other => other,
+      };
+      if mapped.is_empty() || !mapped.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+        return Err(ConversionError::Ocr(format!(
+          "'{code}' is not a Tesseract language code"
+        )));
+      }
+      Ok(mapped.to_string())
+    })
+    .collect::<Result<Vec<_>, _>>()
+    .map(|codes| codes.join("+"))
+}
+
+/// A scratch directory for page images and decrypted PDFs, removed when
+/// dropped.
+struct WorkDir(PathBuf);
+
+impl WorkDir {
+  fn create() -> io::Result<Self> {
+    let nanos = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |d| d.as_nanos());
+    let dir = std::env::temp_dir().join(format!("pdf2markdown-ocr-{}-{nanos}", std::process::id()));
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    // Decrypted copies of PDFs go in here too, so only their owner may look.
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dir)?;
+    Ok(WorkDir(dir))
+  }
+}
+
+impl Drop for WorkDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.0);
+  }
+}
@@ -0,0 +1,112 @@
+//! PDFs the OS asks the app to open: "Open With", double-clicking a PDF once
+//! the app is its handler, or paths on the command line.
+//!
+//! Each request is sent to the frontend as an `open-requested` event. Requests
+//! that arrive before the frontend has loaded and called
+//! `take_open_requests` are kept until it does, so none are lost to a
+//! listener that isn't there yet.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::batch::is_pdf;
+
+/// Payload of the `open-requested` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenRequested {
+  pub paths: Vec<String>,
+}
+
+/// Managed state: the paths waiting for the frontend, or `None` once it is
+/// listening.
+pub struct OpenRequests {
+  pending: Mutex<Option<Vec<String>>>,
+}
+
+impl Default for OpenRequests {
+  fn default() -> Self {
+    OpenRequests {
+      pending: Mutex::new(Some(Vec::new())),
+    }
+  }
+}
+
+/// Returns the paths requested so far. From then on requests are sent as
+/// `open-requested` events, so call this once the listener is in place.
+#[tauri::command]
+pub fn take_open_requests(requests: State<'_, OpenRequests>) -> Vec<String> {
+  let mut pending = requests.pending.lock().unwrap_or_else(|e| e.into_inner());
+  pending.take().unwrap_or_default()
+}
+
+/// Passes the PDFs among `paths` on to the frontend and brings the main
+/// window forward.
+pub fn request(app: &AppHandle, paths: Vec<PathBuf>) {
+  let paths: Vec<String> = paths
+    .into_iter()
+    .filter(|path| is_pdf(path) && path.is_file())
+    .map(|path| path.display().to_string())
+    .collect();
+  if paths.is_empty() {
+    return;
+  }
+  log::info!("open requested for {} file(s)", paths.len());
+
+  let state = app.state::<OpenRequests>();
+  let mut pending = state.pending.lock().unwrap_or_else(|e| e.into_inner());
+  match pending.as_mut() {
+    Some(queue) => queue.extend(paths),
+    None => {
+      if let Err(err) = app.emit("open-requested", OpenRequested { paths }) {
+        log::warn!("failed to emit open request: {err}");
+      }
+    }
+  }
+  drop(pending);
+
+  if let Some(window) = app.get_webview_window("main") {
+    let shown = window
+      .unminimize()
+      .and_then(|()| window.show())
+      .and_then(|()| window.set_focus());
+    if let Err(err) = shown {
+      log::warn!("cannot bring window forward: {err}");
+    }
+  }
+}
+
+/// The file paths among command-line `args` (without the program name),
+/// made absolute against `cwd`: a second instance's working directory needn't
+/// be ours.
+pub fn paths_from_args(args: &[String], cwd: &Path) -> Vec<PathBuf> {
+  args
+    .iter()
+    .filter(|arg| !arg.starts_with('-'))
+    .map(|arg| cwd.join(arg))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn args_are_resolved_against_the_working_directory() {
+    let args = [
+      "report.pdf".to_string(),
+      "--flag".to_string(),
+      "/abs/other.pdf".to_string(),
+    ];
+    assert_eq!(
+      paths_from_args(&args, Path::new("/home/me")),
+      [
+        PathBuf::from("/home/me/report.pdf"),
+        PathBuf::from("/abs/other.pdf")
+      ]
+    );
+  }
+}
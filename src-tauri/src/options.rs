@@ -0,0 +1,104 @@
+//! Options shared by the conversion commands.
+
+use serde::{Deserialize, Serialize};
+
+use crate::bidi::TextDirection;
+
+/// Tunes how a PDF is turned into Markdown.
+///
+/// Every field has a default, so the frontend only sends what it changes and
+/// commands take the whole struct as an optional argument.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ConvertOptions {
+  /// Render text laid out in aligned columns as GitHub-flavored tables.
+  pub detect_tables: bool,
+  /// How far apart, in points, the edges of two cells may be while still
+  /// counting as the same column.
+  pub table_tolerance: f32,
+  /// Read multi-column pages one column at a time instead of straight across.
+  pub detect_columns: bool,
+  /// Turn lines starting with bullets or enumerators into Markdown lists.
+  pub detect_lists: bool,
+  /// Turn paragraphs indented from the text around them into `>`
+  /// blockquotes, `>>` for those indented further. Off by default, since
+  /// indentation alone can mislead.
+  pub detect_blockquotes: bool,
+  /// How readily larger, bold or capitalized text is turned into headings.
+  /// 0 disables heading detection; values above 1 promote more text.
+  pub heading_sensitivity: f32,
+  /// Keep hyperlinks as Markdown links rather than plain text.
+  pub preserve_links: bool,
+  /// Start the output with the PDF's title, author, subject and creation
+  /// date as a YAML front matter block.
+  pub emit_frontmatter: bool,
+  /// Fonts whose name contains one of these (ignoring case) are treated as
+  /// monospace: whole lines in them become code blocks, words inline code.
+  pub monospace_fonts: Vec<String>,
+  /// Remove running headers and footers, page numbers included: lines that
+  /// recur at the top or bottom of most pages.
+  pub strip_boilerplate: bool,
+  /// The share of pages (0 to 1) a line must be found on, at the same
+  /// height, to count as a header or footer.
+  pub boilerplate_threshold: f32,
+  /// Write formulas as LaTeX: `$...$` within text, `$$...$$` for lines of
+  /// their own. Turn off for documents whose symbol fonts aren't math.
+  pub detect_math: bool,
+  /// Write text in bold and italic faces as `**bold**` and `*italic*`.
+  pub detect_emphasis: bool,
+  /// Join words hyphenated at the end of a line (`inter-` / `national`),
+  /// leaving the hyphen in compounds such as `well-known`.
+  pub dehyphenate: bool,
+  /// Turn footnotes into Markdown footnotes: superscript marks in the text
+  /// become `[^1]` references and the notes at the foot of the page
+  /// `[^1]: ...` definitions at the end, numbered through the document.
+  pub convert_footnotes: bool,
+  /// The base direction of the text, for ordering lines that mix Arabic or
+  /// Hebrew with other scripts. `auto` goes by each line's dominant script.
+  pub text_direction: TextDirection,
+  /// Put between pages instead of a blank line, e.g.
+  /// `"\n\n<!-- page {page} -->\n\n"`; `{page}` becomes the one-based
+  /// number of the page that follows.
+  pub page_separator: Option<String>,
+  /// Also drop the blank lines between list items, so lists split across
+  /// blocks or pages read as one.
+  pub tight_output: bool,
+  /// Start the document with a table of contents linking to its headings,
+  /// nested as the PDF's bookmarks are, or as the headings are without any.
+  pub generate_toc: bool,
+}
+
+impl Default for ConvertOptions {
+  fn default() -> Self {
+    ConvertOptions {
+      detect_tables: true,
+      table_tolerance: 3.0,
+      detect_columns: true,
+      detect_lists: true,
+      detect_blockquotes: false,
+      heading_sensitivity: 1.0,
+      preserve_links: true,
+      emit_frontmatter: false,
+      monospace_fonts: [
+        "courier",
+        "consolas",
+        "mono",
+        "menlo",
+        "inconsolata",
+        "cmtt",
+      ]
+      .map(String::from)
+      .to_vec(),
+      strip_boilerplate: false,
+      boilerplate_threshold: 0.6,
+      detect_math: true,
+      detect_emphasis: true,
+      dehyphenate: true,
+      convert_footnotes: true,
+      text_direction: TextDirection::default(),
+      page_separator: None,
+      tight_output: false,
+      generate_toc: false,
+    }
+  }
+}
@@ -0,0 +1,278 @@
+//! The document outline: the bookmarks PDF viewers show in their sidebar,
+//! and the pages they and the links within a document lead to.
+
+use std::collections::{HashMap, HashSet};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+/// Outlines nested deeper than this are assumed to be broken.
+const MAX_DEPTH: usize = 32;
+
+/// One entry of the outline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+  pub title: String,
+  /// 0 for top-level bookmarks, 1 for their children, and so on.
+  pub depth: usize,
+  /// The one-based page the bookmark opens, if it points into this document.
+  pub page: Option<u32>,
+}
+
+/// The outline of `doc` in reading order, parents before their children.
+/// Empty if the PDF has none.
+pub fn bookmarks(doc: &Document) -> Vec<Bookmark> {
+  let Some(destinations) = Destinations::new(doc) else {
+    return Vec::new();
+  };
+  let Some(first) = destinations
+    .catalog
+    .get_deref(b"Outlines", doc)
+    .and_then(Object::as_dict)
+    .ok()
+    .and_then(|outlines| outlines.get(b"First").ok())
+  else {
+    return Vec::new();
+  };
+  let mut walk = Walk {
+    destinations,
+    seen: HashSet::new(),
+    bookmarks: Vec::new(),
+  };
+  walk.siblings(first, 0);
+  walk.bookmarks
+}
+
+struct Walk<'a> {
+  destinations: Destinations<'a>,
+  /// Outline items visited, so a `Next` loop can't go on forever.
+  seen: HashSet<ObjectId>,
+  bookmarks: Vec<Bookmark>,
+}
+
+impl Walk<'_> {
+  /// Adds the item `first` and the items after it, with their children.
+  fn siblings(&mut self, first: &Object, depth: usize) {
+    if depth > MAX_DEPTH {
+      return;
+    }
+    let doc = self.destinations.doc;
+    let mut next = Some(first);
+    while let Some(Object::Reference(id)) = next {
+      if !self.seen.insert(*id) {
+        break;
+      }
+      let Ok(item) = doc.get_dictionary(*id) else {
+        break;
+      };
+      let title = item
+        .get_deref(b"Title", doc)
+        .ok()
+        .and_then(|title| lopdf::decode_text_string(title).ok())
+        .unwrap_or_default();
+      self.bookmarks.push(Bookmark {
+        title: title.replace('\0', "").trim().to_string(),
+        depth,
+        page: self.destinations.target(item),
+      });
+      if let Ok(child) = item.get(b"First") {
+        self.siblings(child, depth + 1);
+      }
+      next = item.get(b"Next").ok();
+    }
+  }
+}
+
+/// Finds the pages that outline items and link annotations open.
+pub struct Destinations<'a> {
+  doc: &'a Document,
+  catalog: &'a Dictionary,
+  pages: HashMap<ObjectId, u32>,
+}
+
+impl<'a> Destinations<'a> {
+  /// `None` if `doc` has no catalog, and so nothing to look names up in.
+  pub fn new(doc: &'a Document) -> Option<Self> {
+    let catalog = doc.catalog().ok()?;
+    let pages = doc
+      .get_pages()
+      .into_iter()
+      .map(|(number, id)| (id, number))
+      .collect();
+    Some(Destinations {
+      doc,
+      catalog,
+      pages,
+    })
+  }
+
+  /// The page an outline item or a link opens, from its `Dest` or its
+  /// `GoTo` action.
+  pub fn target(&self, item: &Dictionary) -> Option<u32> {
+    let destination = match item.get_deref(b"Dest", self.doc) {
+      Ok(destination) => destination,
+      Err(_) => {
+        let action = item.get_deref(b"A", self.doc).ok()?.as_dict().ok()?;
+        if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+          return None;
+        }
+        action.get_deref(b"D", self.doc).ok()?
+      }
+    };
+    self.page_of(destination, 0)
+  }
+
+  /// The page of an explicit destination (`[page /XYZ ...]`) or of a named
+  /// one, looked up in the catalog.
+  fn page_of(&self, destination: &Object, depth: usize) -> Option<u32> {
+    if depth > 2 {
+      return None;
+    }
+    match destination {
+      Object::Array(array) => match array.first()? {
+        Object::Reference(id) => self.pages.get(id).copied(),
+        _ => None,
+      },
+      // Named destinations may be wrapped in a dictionary.
+      Object::Dictionary(dict) => {
+        let (_, destination) = self.doc.dereference(dict.get(b"D").ok()?).ok()?;
+        self.page_of(destination, depth + 1)
+      }
+      Object::Name(name) | Object::String(name, _) => {
+        let destination = self.named(name)?;
+        let (_, destination) = self.doc.dereference(destination).ok()?;
+        self.page_of(destination, depth + 1)
+      }
+      _ => None,
+    }
+  }
+
+  /// A named destination: an entry of the catalog's `Dests` dictionary (PDF
+  /// 1.1) or of the `Dests` name tree.
+  fn named(&self, name: &[u8]) -> Option<&'a Object> {
+    let dests = self
+      .catalog
+      .get_deref(b"Dests", self.doc)
+      .and_then(Object::as_dict);
+    if let Ok(destination) = dests.and_then(|dests| dests.get(name)) {
+      return Some(destination);
+    }
+    let tree = self
+      .catalog
+      .get_deref(b"Names", self.doc)
+      .and_then(Object::as_dict)
+      .and_then(|names| names.get_deref(b"Dests", self.doc))
+      .and_then(Object::as_dict)
+      .ok()?;
+    self.lookup(tree, name, 0)
+  }
+
+  fn lookup(&self, node: &'a Dictionary, name: &[u8], depth: usize) -> Option<&'a Object> {
+    if depth > MAX_DEPTH {
+      return None;
+    }
+    if let Ok(names) = node
+      .get_deref(b"Names", self.doc)
+      .and_then(Object::as_array)
+    {
+      let found = names
+        .chunks(2)
+        .find(|pair| pair[0].as_str().ok() == Some(name))
+        .and_then(|pair| pair.get(1));
+      if found.is_some() {
+        return found;
+      }
+    }
+    let kids = node
+      .get_deref(b"Kids", self.doc)
+      .and_then(Object::as_array)
+      .ok()?;
+    kids.iter().find_map(|kid| {
+      let kid = self.doc.dereference(kid).ok()?.1.as_dict().ok()?;
+      self.lookup(kid, name, depth + 1)
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lopdf::dictionary;
+
+  #[test]
+  fn bookmarks_nest_and_resolve_their_pages() {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let page_ids: Vec<ObjectId> = (0..3)
+      .map(|_| doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id }))
+      .collect();
+    doc.objects.insert(
+      pages_id,
+      Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => page_ids.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+        "Count" => 3,
+      }),
+    );
+
+    let outlines_id = doc.new_object_id();
+    let [intro, methods, detail] = [0, 1, 2].map(|_| doc.new_object_id());
+    doc.objects.insert(
+      intro,
+      Object::Dictionary(dictionary! {
+        "Title" => Object::string_literal("Introduction"),
+        "Parent" => outlines_id,
+        "Next" => methods,
+        "Dest" => vec![page_ids[0].into(), "Fit".into()],
+      }),
+    );
+    doc.objects.insert(
+      methods,
+      Object::Dictionary(dictionary! {
+        "Title" => Object::string_literal("Methods"),
+        "Parent" => outlines_id,
+        "First" => detail,
+        "A" => dictionary! { "S" => "GoTo", "D" => Object::string_literal("methods") },
+      }),
+    );
+    doc.objects.insert(
+      detail,
+      Object::Dictionary(dictionary! {
+        "Title" => Object::string_literal("Detail"),
+        "Parent" => methods,
+        // A broken loop back to itself.
+        "Next" => detail,
+        "Dest" => vec![page_ids[2].into(), "XYZ".into()],
+      }),
+    );
+    doc.objects.insert(
+      outlines_id,
+      Object::Dictionary(dictionary! { "Type" => "Outlines", "First" => intro, "Last" => methods }),
+    );
+    let names = dictionary! {
+      "Dests" => dictionary! {
+        "Names" => vec![Object::string_literal("methods"), vec![page_ids[1].into(), "Fit".into()].into()],
+      },
+    };
+    let catalog_id = doc.add_object(dictionary! {
+      "Type" => "Catalog",
+      "Pages" => pages_id,
+      "Outlines" => outlines_id,
+      "Names" => names,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let bookmark = |title: &str, depth, page| Bookmark {
+      title: title.to_string(),
+      depth,
+      page: Some(page),
+    };
+    assert_eq!(
+      bookmarks(&doc),
+      [
+        bookmark("Introduction", 0, 1),
+        bookmark("Methods", 0, 2),
+        bookmark("Detail", 1, 3),
+      ]
+    );
+  }
+}
@@ -0,0 +1,183 @@
+//! Writing converted files where one of the same name may already exist.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// What to do when an output file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WriteMode {
+  /// Replace it.
+  #[default]
+  Overwrite,
+  /// Leave it alone and don't write the new one.
+  Skip,
+  /// Write the new one beside it as `name (1).md`, `name (2).md`, ...
+  Rename,
+}
+
+/// How writing an output file turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WriteOutcome {
+  /// Written where it was meant to go, replacing any file there.
+  Written,
+  /// A file was there already and was left as it was.
+  Skipped,
+  /// A file was there already, so this went next to it under a new name.
+  Renamed,
+}
+
+/// An output file and how it was written.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputFile {
+  /// Where the output is: the new name if renamed, the existing file if
+  /// skipped.
+  pub path: String,
+  pub outcome: WriteOutcome,
+}
+
+impl OutputFile {
+  pub fn new(path: &Path, outcome: WriteOutcome) -> Self {
+    OutputFile {
+      path: path.display().to_string(),
+      outcome,
+    }
+  }
+}
+
+/// Writes `contents` to `path`, or to a free name beside it, as `mode` says.
+/// Returns where it went, or where the file left in place is if skipped.
+///
+/// Files are created without replacing anything unless `mode` is
+/// [`WriteMode::Overwrite`], so two conversions picking a name at the same
+/// time can't both take it.
+pub fn write(path: &Path, contents: &[u8], mode: WriteMode) -> io::Result<(PathBuf, WriteOutcome)> {
+  if mode == WriteMode::Overwrite {
+    fs::write(path, contents)?;
+    return Ok((path.to_path_buf(), WriteOutcome::Written));
+  }
+  for (candidate, outcome) in candidates(path) {
+    match OpenOptions::new()
+      .write(true)
+      .create_new(true)
+      .open(&candidate)
+    {
+      Ok(mut file) => {
+        file.write_all(contents)?;
+        return Ok((candidate, outcome));
+      }
+      Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+        if mode == WriteMode::Skip {
+          return Ok((candidate, WriteOutcome::Skipped));
+        }
+      }
+      Err(err) => return Err(err),
+    }
+  }
+  unreachable!("the candidate names never run out")
+}
+
+/// Where the conversion of `source` to a file with `extension` goes: next to
+/// it, or with `out_dir`, in there at the same place as `source` is in
+/// `root`.
+pub fn converted_path(
+  source: &Path,
+  root: &Path,
+  out_dir: Option<&Path>,
+  extension: &str,
+) -> PathBuf {
+  let sibling = source.with_extension(extension);
+  let Some(out_dir) = out_dir else {
+    return sibling;
+  };
+  match sibling.strip_prefix(root) {
+    Ok(relative) => out_dir.join(relative),
+    Err(_) => out_dir.join(sibling.file_name().unwrap_or_default()),
+  }
+}
+
+/// Where a file meant for `path` goes under `mode`, for outputs that are moved
+/// into place rather than written directly. `None` if it should be skipped.
+pub fn destination(path: &Path, mode: WriteMode) -> Option<(PathBuf, WriteOutcome)> {
+  match mode {
+    WriteMode::Overwrite => Some((path.to_path_buf(), WriteOutcome::Written)),
+    WriteMode::Skip => (!path.exists()).then(|| (path.to_path_buf(), WriteOutcome::Written)),
+    WriteMode::Rename => candidates(path).find(|(candidate, _)| !candidate.exists()),
+  }
+}
+
+/// `path` itself, then `name (1).ext`, `name (2).ext` and so on beside it.
+fn candidates(path: &Path) -> impl Iterator<Item = (PathBuf, WriteOutcome)> + '_ {
+  let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+  let extension = path
+    .extension()
+    .map(|extension| format!(".{}", extension.to_string_lossy()))
+    .unwrap_or_default();
+  std::iter::once((path.to_path_buf(), WriteOutcome::Written)).chain((1..).map(move |n| {
+    (
+      path.with_file_name(format!("{stem} ({n}){extension}")),
+      WriteOutcome::Renamed,
+    )
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn conversions_go_beside_their_pdf_or_into_the_output_directory() {
+    let root = Path::new("papers");
+    let source = root.join("2024").join("report.pdf");
+    assert_eq!(
+      converted_path(&source, root, None, "md"),
+      root.join("2024").join("report.md")
+    );
+    let out = Path::new("converted");
+    assert_eq!(
+      converted_path(&source, root, Some(out), "md"),
+      out.join("2024").join("report.md")
+    );
+    assert_eq!(
+      converted_path(&source, Path::new("elsewhere"), Some(out), "html"),
+      out.join("report.html")
+    );
+  }
+
+  #[test]
+  fn existing_files_are_kept_skipped_or_written_beside() {
+    let dir = std::env::temp_dir().join(format!("pdf2markdown-output-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("notes.md");
+    fs::write(&path, "old").unwrap();
+
+    assert_eq!(
+      write(&path, b"new", WriteMode::Skip).unwrap(),
+      (path.clone(), WriteOutcome::Skipped)
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+    for n in 1..=2 {
+      let renamed = dir.join(format!("notes ({n}).md"));
+      assert_eq!(
+        write(&path, b"new", WriteMode::Rename).unwrap(),
+        (renamed.clone(), WriteOutcome::Renamed)
+      );
+      assert_eq!(fs::read_to_string(&renamed).unwrap(), "new");
+    }
+    assert_eq!(
+      destination(&path, WriteMode::Rename),
+      Some((dir.join("notes (3).md"), WriteOutcome::Renamed))
+    );
+    assert_eq!(
+      write(&path, b"new", WriteMode::Overwrite).unwrap(),
+      (path.clone(), WriteOutcome::Written)
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
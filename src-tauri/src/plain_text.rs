@@ -0,0 +1,99 @@
+//! Plain-text rendering of the document model: no markup, only the breaks
+//! between paragraphs.
+
+use crate::document::{self, Block, Footnote, Inline, List};
+
+/// `Key: value` lines for the metadata, a blank line, then `body`.
+pub fn document(metadata: &[(String, String)], body: &str) -> String {
+  format!("{}{body}{}", head(metadata), tail(metadata))
+}
+
+pub fn head(metadata: &[(String, String)]) -> String {
+  let mut out = String::new();
+  for (key, value) in metadata {
+    let mut chars = key.chars();
+    let key: String = chars
+      .next()
+      .map(|first| first.to_uppercase().chain(chars).collect())
+      .unwrap_or_default();
+    out.push_str(&format!("{key}: {value}\n"));
+  }
+  if !out.is_empty() {
+    out.push('\n');
+  }
+  out
+}
+
+pub fn tail(_metadata: &[(String, String)]) -> String {
+  "\n".to_string()
+}
+
+pub fn block(block: &Block) -> String {
+  match block {
+    Block::Heading { content, .. } | Block::Paragraph(content) => text(content),
+    // One line per row, cells separated by tabs.
+    Block::Table(rows) => rows
+      .iter()
+      .map(|row| row.join("\t"))
+      .collect::<Vec<_>>()
+      .join("\n"),
+    Block::List(items) => list(items, 0),
+    Block::Code(text) | Block::Math(text) => text.clone(),
+    Block::Image { .. } => String::new(),
+    Block::Quote(blocks) => blocks
+      .iter()
+      .map(self::block)
+      .collect::<Vec<_>>()
+      .join("\n\n"),
+  }
+}
+
+/// The footnotes, one per line: `[1] text`.
+pub fn footnotes(footnotes: &[Footnote]) -> String {
+  footnotes
+    .iter()
+    .map(|note| format!("[{}] {}", note.label, text(&note.content)))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// The text of `content` without markup, but for footnote references,
+/// which are bracketed: `[1]`.
+fn text(content: &[Inline]) -> String {
+  content
+    .iter()
+    .map(|inline| match inline {
+      Inline::FootnoteRef(label) => format!("[{label}]"),
+      Inline::Link { content, .. } | Inline::Strong(content) | Inline::Emphasis(content) => {
+        text(content)
+      }
+      other => document::plain(std::slice::from_ref(other)),
+    })
+    .collect()
+}
+
+/// Nested lists are indented by two spaces per level.
+fn list(list: &List, depth: usize) -> String {
+  let mut out = Vec::new();
+  for (index, item) in list.items.iter().enumerate() {
+    let indent = "  ".repeat(depth);
+    let marker = list.marker(index);
+    out.push(format!("{indent}{marker} {}", text(&item.content)));
+    for child in &item.children {
+      out.push(self::list(child, depth + 1));
+    }
+  }
+  out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn metadata_becomes_labelled_lines() {
+    let metadata = vec![("title".to_string(), "Report".to_string())];
+    assert_eq!(document(&metadata, "Text"), "Title: Report\n\nText\n");
+    assert_eq!(document(&[], "Text"), "Text\n");
+  }
+}
@@ -0,0 +1,120 @@
+//! Warming up at launch, so the first conversion doesn't pay for loading
+//! the PDF code and, if OCR is on, Tesseract's language pack from disk.
+//!
+//! The frontend is told with a `backend-ready` event when warming up is
+//! over, or straight away when the settings turn it off.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use lopdf::{dictionary, Document, Object, Stream};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::convert;
+use crate::error::ConversionError;
+use crate::jobs::CancelToken;
+use crate::ocr;
+use crate::options::ConvertOptions;
+use crate::settings;
+
+/// Whether warming up is over.
+#[derive(Default)]
+pub struct BackendReady(AtomicBool);
+
+/// Whether the backend is ready, for a frontend that starts listening after
+/// the `backend-ready` event went out.
+#[tauri::command]
+pub fn backend_ready(ready: State<'_, BackendReady>) -> bool {
+  ready.0.load(Ordering::Relaxed)
+}
+
+/// Warms up in the background if the settings ask for it, then reports the
+/// backend ready. Nothing that fails to warm up holds the app back: it is
+/// logged and left for the first conversion to load.
+pub fn start(app: &AppHandle) {
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    let settings = settings::load_settings(app.clone());
+    if settings.prewarm {
+      let started = Instant::now();
+      match tauri::async_runtime::spawn_blocking(warm_up_pdf).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => log::warn!("cannot warm up PDF conversion: {err}"),
+        Err(err) => log::warn!("PDF warm-up failed: {err}"),
+      }
+      if settings.ocr_enabled {
+        ocr::prewarm(&app, &settings.ocr_language).await;
+      }
+      log::info!("warmed up in {:?}", started.elapsed());
+    }
+    app.state::<BackendReady>().0.store(true, Ordering::Relaxed);
+    if let Err(err) = app.emit("backend-ready", ()) {
+      log::warn!("failed to emit backend-ready: {err}");
+    }
+  });
+}
+
+/// Converts a PDF of one line, made up here, as any other would be.
+fn warm_up_pdf() -> Result<(), ConversionError> {
+  let mut bytes = Vec::new();
+  sample().save_to(&mut bytes)?;
+  let doc = Document::load_mem(&bytes)?;
+  convert::convert_document(
+    &doc,
+    vec![1],
+    &ConvertOptions::default(),
+    &CancelToken::default(),
+  )?;
+  Ok(())
+}
+
+/// A page with a line of text in Helvetica.
+fn sample() -> Document {
+  let mut doc = Document::with_version("1.5");
+  let pages_id = doc.new_object_id();
+  let font_id = doc.add_object(dictionary! {
+    "Type" => "Font",
+    "Subtype" => "Type1",
+    "BaseFont" => "Helvetica",
+  });
+  let content = b"BT /F1 12 Tf 72 720 Td (Warming up) Tj ET".to_vec();
+  let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+  let page_id = doc.add_object(dictionary! {
+    "Type" => "Page",
+    "Parent" => pages_id,
+    "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+    "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+    "Contents" => content_id,
+  });
+  doc.objects.insert(
+    pages_id,
+    Object::Dictionary(dictionary! {
+      "Type" => "Pages",
+      "Kids" => vec![page_id.into()],
+      "Count" => 1,
+    }),
+  );
+  let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+  doc.trailer.set("Root", catalog_id);
+  doc
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn the_sample_converts() {
+    let mut bytes = Vec::new();
+    sample().save_to(&mut bytes).unwrap();
+    let doc = Document::load_mem(&bytes).unwrap();
+    let converted = convert::convert_document(
+      &doc,
+      vec![1],
+      &ConvertOptions::default(),
+      &CancelToken::default(),
+    )
+    .unwrap();
+    assert_eq!(converted.render(Default::default()).trim(), "Warming up");
+  }
+}
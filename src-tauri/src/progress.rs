@@ -0,0 +1,235 @@
+//! Page-by-page progress of a single conversion, sent to the frontend as
+//! `conversion-progress` events. The first, at 0%, goes out as the job starts,
+//! so the frontend learns its id even if it left the naming to the backend.
+//!
+//! Percentages count the pages actually done, so the bar only ever moves
+//! forward. Events are throttled to a few a second; the last one, at 100%,
+//! always goes out.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// Events closer together than this are dropped, except the last one.
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How many of the most recent pages the time remaining is estimated from.
+const RECENT_PAGES: usize = 8;
+
+/// Payload of the `conversion-progress` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionProgress {
+  pub job_id: String,
+  /// One-based number of the page just done; 0 in the event that announces
+  /// the job.
+  pub page: u32,
+  pub total_pages: usize,
+  /// 0 to 100, from the pages done so far.
+  pub percent: f64,
+  /// Seconds left at the pace of the last few pages; `None` until a page
+  /// has been timed.
+  pub eta_seconds: Option<f64>,
+}
+
+type Emit = Box<dyn Fn(ConversionProgress) + Send + Sync>;
+
+/// Tracks the pages a conversion has done and reports them.
+pub struct Progress {
+  job_id: String,
+  emit: Emit,
+  state: Mutex<State>,
+}
+
+impl Progress {
+  pub fn new(job_id: &str, emit: impl Fn(ConversionProgress) + Send + Sync + 'static) -> Self {
+    Progress {
+      job_id: job_id.to_string(),
+      emit: Box::new(emit),
+      state: Mutex::new(State::default()),
+    }
+  }
+
+  /// Reports the job's progress as `conversion-progress` events.
+  pub fn events(app: &AppHandle, job_id: &str) -> Self {
+    let app = app.clone();
+    Progress::new(job_id, move |progress| {
+      if let Err(err) = app.emit("conversion-progress", progress) {
+        log::warn!("failed to emit conversion progress: {err}");
+      }
+    })
+  }
+
+  /// Sends the event at 0% that announces the job, before any page is known.
+  pub fn announce(&self) {
+    self.send(Some(Event {
+      page: 0,
+      total_pages: 0,
+      percent: 0.0,
+      eta_seconds: None,
+    }));
+  }
+
+  /// Starts over with `pages` pages to do, each gone through `passes` times.
+  pub fn start(&self, pages: usize, passes: usize) {
+    *self.lock() = State {
+      total_pages: pages,
+      steps: pages * passes.max(1),
+      ..State::default()
+    };
+  }
+
+  /// Counts `steps` as done without timing them, for pages that needed no
+  /// real work.
+  pub fn skip(&self, steps: usize, page: u32) {
+    let event = self.lock().advance(steps, page, Instant::now(), false);
+    self.send(event);
+  }
+
+  /// Counts one more step as done, `page` being the page it was for.
+  pub fn page_done(&self, page: u32) {
+    let event = self.lock().advance(1, page, Instant::now(), true);
+    self.send(event);
+  }
+
+  fn send(&self, event: Option<Event>) {
+    if let Some(event) = event {
+      (self.emit)(ConversionProgress {
+        job_id: self.job_id.clone(),
+        page: event.page,
+        total_pages: event.total_pages,
+        percent: event.percent,
+        eta_seconds: event.eta_seconds,
+      });
+    }
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+struct Event {
+  page: u32,
+  total_pages: usize,
+  percent: f64,
+  eta_seconds: Option<f64>,
+}
+
+#[derive(Default)]
+struct State {
+  total_pages: usize,
+  /// Steps to do: the pages times the passes over them.
+  steps: usize,
+  done: usize,
+  /// When the previous step finished.
+  last_step: Option<Instant>,
+  last_event: Option<Instant>,
+  recent: VecDeque<Duration>,
+}
+
+impl State {
+  /// Moves `steps` steps on at `now`, returning the event to send, if one is
+  /// due. Nothing is reported before [`Progress::start`].
+  fn advance(&mut self, steps: usize, page: u32, now: Instant, timed: bool) -> Option<Event> {
+    if self.steps == 0 {
+      return None;
+    }
+    self.done = (self.done + steps).min(self.steps);
+    if let (true, Some(last)) = (timed, self.last_step) {
+      if self.recent.len() == RECENT_PAGES {
+        self.recent.pop_front();
+      }
+      self.recent.push_back(now - last);
+    }
+    self.last_step = Some(now);
+
+    let finished = self.done == self.steps;
+    if !finished
+      && self
+        .last_event
+        .is_some_and(|last| now - last < MIN_INTERVAL)
+    {
+      return None;
+    }
+    self.last_event = Some(now);
+    let eta_seconds = if finished {
+      Some(0.0)
+    } else if self.recent.is_empty() {
+      None
+    } else {
+      let average = self.recent.iter().sum::<Duration>() / self.recent.len() as u32;
+      Some((average * (self.steps - self.done) as u32).as_secs_f64())
+    };
+    Some(Event {
+      page,
+      total_pages: self.total_pages,
+      percent: self.done as f64 * 100.0 / self.steps as f64,
+      eta_seconds,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use super::*;
+
+  #[test]
+  fn events_are_throttled_but_the_last_always_goes_out() {
+    let mut state = State::default();
+    let start = Instant::now();
+    assert!(state.advance(1, 1, start, true).is_none());
+
+    state.steps = 10;
+    state.total_pages = 5;
+    let at = |ms| start + Duration::from_millis(ms);
+    let first = state.advance(1, 1, at(0), true).unwrap();
+    assert_eq!((first.percent, first.eta_seconds), (10.0, None));
+    // Steps 2 to 9 come in too quickly to all be reported.
+    let percents: Vec<f64> = (2..10)
+      .filter_map(|step| state.advance(1, step, at(step as u64 * 100), true))
+      .map(|event| event.percent)
+      .collect();
+    assert_eq!(percents, [30.0, 60.0, 90.0]);
+    let last = state.advance(1, 5, at(1000), true).unwrap();
+    assert_eq!((last.percent, last.eta_seconds), (100.0, Some(0.0)));
+  }
+
+  #[test]
+  fn the_job_is_announced_before_its_pages() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sent = Arc::clone(&events);
+    let progress = Progress::new("job-1", move |event| sent.lock().unwrap().push(event));
+    progress.announce();
+    progress.page_done(1);
+    progress.start(2, 1);
+    progress.page_done(1);
+    let events = events.lock().unwrap();
+    let seen: Vec<(&str, u32, f64)> = events
+      .iter()
+      .map(|event| (event.job_id.as_str(), event.page, event.percent))
+      .collect();
+    assert_eq!(seen, [("job-1", 0, 0.0), ("job-1", 1, 50.0)]);
+  }
+
+  #[test]
+  fn time_left_follows_the_recent_pages() {
+    let mut state = State {
+      total_pages: 20,
+      steps: 20,
+      ..State::default()
+    };
+    let start = Instant::now();
+    state.advance(10, 10, start, false);
+    // The first slow page, after ten that took no time at all.
+    let event = state
+      .advance(1, 11, start + Duration::from_secs(4), true)
+      .unwrap();
+    assert_eq!(event.eta_seconds, Some(36.0));
+  }
+}
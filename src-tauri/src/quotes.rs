@@ -0,0 +1,269 @@
+//! Blockquote detection.
+//!
+//! A paragraph whose lines start together, further right than the body text
+//! around it but within its width, is a quotation; one indented in the same
+//! way from a quotation is quoted within it. Code and lists are found before
+//! quotes are, and paragraphs that make headings or carry on the list before
+//! them are left alone, so that neither is taken for a quote. A quote of one
+//! line has to be in italics too, and off center, since a short indented line
+//! is more often the first of a paragraph or a caption; captions that say
+//! what they are (`Figure 3.`) never are.
+
+use std::iter::Peekable;
+
+use crate::document::Block;
+use crate::extract::{TextLine, TextSpan};
+use crate::headings;
+use crate::options::ConvertOptions;
+
+/// How far right of the text around it, relative to the font size, a
+/// paragraph must start to be quoted.
+const INDENT: f32 = 1.5;
+
+/// How far apart, relative to the font size, the lines of a quote may start:
+/// all but the first, which may be indented like that of any paragraph.
+const ALIGN: f32 = 0.5;
+
+/// How far a line may be off the middle of the text around it, relative to
+/// the font size, and still count as centered.
+const CENTERED: f32 = 1.0;
+
+/// The left and right edges of a paragraph.
+#[derive(Debug, Clone, Copy)]
+struct Extent {
+  left: f32,
+  right: f32,
+}
+
+/// How deeply each of `paragraphs` is quoted: 0 for body text, 1 for a
+/// quotation, 2 for one quoted within it, and so on. `list` holds the lines
+/// of the list the paragraphs follow, if any: paragraphs aligned with its
+/// text are more of its last item.
+pub fn depths(
+  paragraphs: &[&[TextLine]],
+  list: Option<&[TextLine]>,
+  options: &ConvertOptions,
+  body_size: f32,
+) -> Vec<usize> {
+  let extents: Vec<Extent> = paragraphs.iter().map(|lines| extent(lines)).collect();
+  // Paragraphs right after a list and right of its left edge carry it on.
+  let list_left = list.map(|lines| extent(lines).left);
+  let continuing = paragraphs
+    .iter()
+    .zip(&extents)
+    .take_while(|(lines, extent)| {
+      list_left.is_some_and(|left| extent.left > left + ALIGN * lines[0].font_size())
+    })
+    .count();
+  let starts: Vec<(f32, f32)> = paragraphs
+    .iter()
+    .flat_map(|lines| lines.iter())
+    .filter_map(|line| Some((line.spans.first()?.x, line.font_size())))
+    .collect();
+  let lines_at = |x: f32| {
+    starts
+      .iter()
+      .filter(|&&(start, size)| (start - x).abs() <= ALIGN * size)
+      .count()
+  };
+  let candidate = |i: usize| {
+    let lines = paragraphs[i];
+    i >= continuing
+      && aligned(lines)
+      && !is_caption(&lines[0].text())
+      && (lines.len() > 1 || lines[0].spans.iter().all(TextSpan::is_italic))
+      && headings::heading_level(lines, body_size, options.heading_sensitivity).is_none()
+  };
+
+  // A paragraph is quoted one deeper than the nearest one it is indented
+  // from, so depths are worked out from the left.
+  let mut order: Vec<usize> = (0..paragraphs.len()).collect();
+  order.sort_by(|&a, &b| extents[a].left.total_cmp(&extents[b].left));
+  let mut depths = vec![0; paragraphs.len()];
+  for i in order {
+    if !candidate(i) {
+      continue;
+    }
+    let size = paragraphs[i][0].font_size();
+    let Some(outer) = container(&extents, i, size) else {
+      continue;
+    };
+    if paragraphs[i].len() == 1 && centered(extents[i], extents[outer], size) {
+      continue;
+    }
+    // Body text is what most lines line up with; a margin note or a header
+    // further left is no measure of indentation.
+    if lines_at(extents[outer].left) < lines_at(extents[i].left) {
+      continue;
+    }
+    depths[i] = depths[outer] + 1;
+  }
+  depths
+}
+
+/// `blocks`, each with how deeply it is quoted (at least 1), as one
+/// [`Block::Quote`], deeper ones nested within it.
+pub fn quote(blocks: Vec<(usize, Block)>) -> Block {
+  Block::Quote(nest(&mut blocks.into_iter().peekable(), 1))
+}
+
+/// The blocks of a quote at `depth`: those at that depth up to the first one
+/// shallower, with runs of deeper ones quoted in turn.
+fn nest(blocks: &mut Peekable<impl Iterator<Item = (usize, Block)>>, depth: usize) -> Vec<Block> {
+  let mut out = Vec::new();
+  while let Some(&(at, _)) = blocks.peek() {
+    if at < depth {
+      break;
+    }
+    if at == depth {
+      out.extend(blocks.next().map(|(_, block)| block));
+    } else {
+      out.push(Block::Quote(nest(blocks, depth + 1)));
+    }
+  }
+  out
+}
+
+/// The nearest paragraph that paragraph `i` is indented from: one that
+/// starts well to its left and ends no further right, with only paragraphs
+/// at least as far in between. Of two as near, the one before.
+fn container(extents: &[Extent], i: usize, size: f32) -> Option<usize> {
+  let inner = extents[i];
+  // Some(true) for a paragraph `i` is indented from, Some(false) for one
+  // further in, None for any other, which ends the search.
+  let relation = |j: usize| {
+    let outer = extents[j];
+    if outer.left + INDENT * size <= inner.left && outer.right + ALIGN * size >= inner.right {
+      Some(true)
+    } else {
+      (outer.left >= inner.left - ALIGN * size).then_some(false)
+    }
+  };
+  let find = |mut range: Box<dyn Iterator<Item = usize>>| {
+    range
+      .find(|&j| relation(j) != Some(false))
+      .filter(|&j| relation(j) == Some(true))
+  };
+  let before = find(Box::new((0..i).rev()));
+  let after = find(Box::new(i + 1..extents.len()));
+  match (before, after) {
+    (Some(b), Some(a)) if a - i < i - b => Some(a),
+    (Some(b), _) => Some(b),
+    (None, a) => a,
+  }
+}
+
+/// Whether the lines after the first start at the same place: text that is
+/// centered or ragged on the left isn't a quote.
+fn aligned(lines: &[TextLine]) -> bool {
+  let rest = lines.get(1..).unwrap_or_default();
+  let starts = rest
+    .iter()
+    .filter_map(|line| line.spans.first())
+    .map(|span| span.x);
+  let (min, max) = starts.fold((f32::MAX, f32::MIN), |(min, max), x| {
+    (min.min(x), max.max(x))
+  });
+  rest.is_empty() || max - min <= ALIGN * lines[0].font_size()
+}
+
+/// Whether `text` starts like a caption, which is often set in from the
+/// text as well: `Figure 3.`, `Fig. 2`, `Table IV`.
+fn is_caption(text: &str) -> bool {
+  let Some(rest) = ["Figure", "Fig.", "Table"]
+    .iter()
+    .find_map(|label| text.strip_prefix(label))
+  else {
+    return false;
+  };
+  let number = rest.trim_start();
+  number.len() < rest.len() && number.starts_with(|c: char| c.is_ascii_digit() || "IVX".contains(c))
+}
+
+/// Whether `inner` sits in the middle of `outer`.
+fn centered(inner: Extent, outer: Extent, size: f32) -> bool {
+  let left = inner.left - outer.left;
+  let right = outer.right - inner.right;
+  (left - right).abs() <= CENTERED * size
+}
+
+fn extent(lines: &[TextLine]) -> Extent {
+  let left = lines
+    .iter()
+    .filter_map(|line| line.spans.first())
+    .map(|span| span.x)
+    .fold(f32::MAX, f32::min);
+  let right = lines
+    .iter()
+    .filter_map(|line| line.spans.last())
+    .map(TextSpan::right)
+    .fold(f32::MIN, f32::max);
+  Extent { left, right }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::document::Inline;
+  use crate::extract::FontStyle;
+
+  fn line(text: &str, x: f32, y: f32, width: f32) -> TextLine {
+    TextLine {
+      spans: vec![TextSpan {
+        text: text.to_string(),
+        x,
+        y,
+        width,
+        font_size: 10.0,
+        font_name: "Times-Roman".into(),
+        link: None,
+        style: FontStyle::default(),
+        footnote: None,
+      }],
+    }
+  }
+
+  #[test]
+  fn indented_paragraphs_are_quoted_by_how_far_in_they_are() {
+    let lines = [
+      line("Body text that runs", 72.0, 700.0, 450.0),
+      line("to the right margin.", 72.0, 688.0, 300.0),
+      line("A quotation, set in", 108.0, 664.0, 380.0),
+      line("from the body text.", 108.0, 652.0, 200.0),
+      line("Quoted within it,", 144.0, 628.0, 340.0),
+      line("further in again.", 144.0, 616.0, 150.0),
+      line("Back in the body", 72.0, 592.0, 450.0),
+      line("of the text.", 72.0, 580.0, 100.0),
+      // A second column: to the right of the body, but not within it.
+      line("The next column", 540.0, 700.0, 200.0),
+      line("of the page.", 540.0, 688.0, 90.0),
+    ];
+    let paragraphs: Vec<&[TextLine]> = lines.chunks(2).collect();
+    let options = ConvertOptions::default();
+    assert_eq!(depths(&paragraphs, None, &options, 10.0), [0, 1, 2, 0, 0]);
+    // After a list, text aligned with its items carries the last one on.
+    let list = [line("• An item", 72.0, 720.0, 100.0)];
+    assert_eq!(
+      depths(&paragraphs[1..], Some(&list), &options, 10.0),
+      [0, 0, 0, 0]
+    );
+  }
+
+  #[test]
+  fn deeper_blocks_are_nested() {
+    let paragraph = |text: &str| Block::Paragraph(vec![Inline::Text(text.into())]);
+    let quote = quote(vec![
+      (1, paragraph("a")),
+      (2, paragraph("b")),
+      (1, paragraph("c")),
+    ]);
+    assert_eq!(
+      quote,
+      Block::Quote(vec![
+        paragraph("a"),
+        Block::Quote(vec![paragraph("b")]),
+        paragraph("c"),
+      ])
+    );
+  }
+}
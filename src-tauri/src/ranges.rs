@@ -0,0 +1,123 @@
+//! Page range specs such as `"1-3,7,10-12"`.
+//!
+//! Page numbers are one-based, matching what PDF viewers show: `"1"` is the
+//! first page.
+
+use std::ops::RangeInclusive;
+
+use crate::error::ConversionError;
+
+/// Parses a comma-separated list of pages and inclusive ranges.
+///
+/// An empty or whitespace-only spec yields no ranges, which callers treat as
+/// "all pages".
+pub fn parse_ranges(spec: &str) -> Result<Vec<RangeInclusive<usize>>, ConversionError> {
+  let invalid = |reason: String| ConversionError::InvalidPageRange(reason);
+  let page = |token: &str| {
+    token
+      .trim()
+      .parse::<usize>()
+      .map_err(|_| invalid(format!("'{}' is not a page number", token.trim())))
+      .and_then(|n| match n {
+        0 => Err(invalid("page numbers start at 1".into())),
+        n => Ok(n),
+      })
+  };
+
+  let mut ranges = Vec::new();
+  for part in spec.split(',').map(str::trim) {
+    if part.is_empty() {
+      if spec.trim().is_empty() {
+        continue;
+      }
+      return Err(invalid(format!("empty entry in '{spec}'")));
+    }
+    let range = match part.split_once('-') {
+      Some((start, end)) => {
+        let (start, end) = (page(start)?, page(end)?);
+        if start > end {
+          return Err(invalid(format!("'{part}' runs backwards")));
+        }
+        start..=end
+      }
+      None => {
+        let n = page(part)?;
+        n..=n
+      }
+    };
+    ranges.push(range);
+  }
+  Ok(ranges)
+}
+
+/// Resolves ranges against a document, returning the selected one-based page
+/// numbers in document order without duplicates.
+pub fn select_pages(
+  ranges: &[RangeInclusive<usize>],
+  page_count: usize,
+) -> Result<Vec<usize>, ConversionError> {
+  if ranges.is_empty() {
+    return Ok((1..=page_count).collect());
+  }
+  if let Some(range) = ranges.iter().find(|r| *r.end() > page_count) {
+    return Err(ConversionError::InvalidPageRange(format!(
+      "page {} is out of range (the document has {page_count} pages)",
+      range.end()
+    )));
+  }
+  let mut pages: Vec<usize> = ranges.iter().cloned().flatten().collect();
+  pages.sort_unstable();
+  pages.dedup();
+  Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn one_is_the_first_page() {
+    let ranges = parse_ranges("1").unwrap();
+    assert_eq!(select_pages(&ranges, 5).unwrap(), vec![1]);
+  }
+
+  #[test]
+  fn parses_mixed_spec() {
+    let ranges = parse_ranges(" 1-3, 7 ,10-12").unwrap();
+    assert_eq!(ranges, vec![1..=3, 7..=7, 10..=12]);
+    assert_eq!(
+      select_pages(&ranges, 12).unwrap(),
+      vec![1, 2, 3, 7, 10, 11, 12]
+    );
+  }
+
+  #[test]
+  fn blank_spec_means_all_pages() {
+    for spec in ["", "   "] {
+      let ranges = parse_ranges(spec).unwrap();
+      assert_eq!(select_pages(&ranges, 3).unwrap(), vec![1, 2, 3]);
+    }
+  }
+
+  #[test]
+  fn overlapping_ranges_are_deduplicated() {
+    let ranges = parse_ranges("3-5,1-4").unwrap();
+    assert_eq!(select_pages(&ranges, 5).unwrap(), vec![1, 2, 3, 4, 5]);
+  }
+
+  #[test]
+  fn rejects_bad_specs() {
+    for spec in ["0", "3-1", "a", "1,,2", "1-", "2-x"] {
+      assert!(parse_ranges(spec).is_err(), "{spec} should be rejected");
+    }
+  }
+
+  #[test]
+  fn out_of_bounds_is_an_error() {
+    let ranges = parse_ranges("2-9").unwrap();
+    assert!(matches!(
+      select_pages(&ranges, 4),
+      Err(ConversionError::InvalidPageRange(_))
+    ));
+  }
+}
@@ -0,0 +1,320 @@
+//! Conversion on a remote server, for documents the local pipeline handles
+//! poorly.
+//!
+//! The PDF is uploaded as the `file` field of a multipart POST; the server
+//! answers with JSON carrying the Markdown in a `markdown` field.
+//!
+//! Failures that may pass (the server can't be reached, or answers with a
+//! 5xx or 429) are retried with exponential backoff; the server's
+//! `Retry-After` takes precedence over the backoff when it sends one. Each
+//! retry is announced with a `remote-retry` event.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_http::reqwest::header::{HeaderMap, RETRY_AFTER};
+use tauri_plugin_http::reqwest::{self, multipart, Client, StatusCode};
+
+use crate::error::ConversionError;
+
+/// Servers can take a while on long or scanned documents.
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
+/// Error bodies longer than this are cut short in the message.
+const MAX_ERROR_CHARS: usize = 500;
+
+/// The longest `Retry-After` that is waited out, in seconds; a server asking
+/// for more is given this.
+const MAX_RETRY_AFTER_SECS: u64 = 300;
+
+/// How often, and how patiently, a failed conversion is tried again.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RetryConfig {
+  /// Attempts in all, the first included; 1 never retries.
+  pub max_attempts: u32,
+  /// The wait before the first retry, doubling for each one after.
+  pub base_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+  fn default() -> Self {
+    RetryConfig {
+      max_attempts: 3,
+      base_delay_ms: 1000,
+    }
+  }
+}
+
+impl RetryConfig {
+  /// The wait after failed attempt number `attempt` (from 1).
+  fn backoff(&self, attempt: u32) -> Duration {
+    let factor = 1u64 << (attempt - 1).min(16);
+    Duration::from_millis(self.base_delay_ms.saturating_mul(factor))
+  }
+}
+
+/// Payload of the `remote-retry` event, sent before each retry.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteRetry {
+  pub path: String,
+  /// The attempt about to be made, from 2.
+  pub attempt: u32,
+  pub max_attempts: u32,
+  /// How long until it is made.
+  pub delay_ms: u64,
+  /// What went wrong with the one before.
+  pub reason: String,
+}
+
+/// A response, read to the end.
+struct Answer {
+  status: StatusCode,
+  retry_after: Option<Duration>,
+  body: String,
+}
+
+#[derive(Deserialize)]
+struct RemoteResponse {
+  markdown: String,
+}
+
+/// Converts the PDF at `path` on the server at `endpoint`, authenticating with
+/// `api_key` as a bearer token if given.
+///
+/// Each attempt, upload included, fails after `timeout_secs` (two minutes by
+/// default). Failures that may pass are retried as `retry` says, three
+/// attempts in all by default; client errors (4xx other than 429) are not.
+/// Error responses are reported with the server's message.
+#[tauri::command]
+pub async fn convert_remote(
+  app: AppHandle,
+  path: String,
+  endpoint: String,
+  api_key: Option<String>,
+  timeout_secs: Option<u64>,
+  retry: Option<RetryConfig>,
+) -> Result<String, ConversionError> {
+  let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+  let retry = retry.unwrap_or_default();
+  convert(
+    Path::new(&path),
+    &endpoint,
+    api_key.as_deref(),
+    timeout,
+    &retry,
+    |retry| {
+      if let Err(err) = app.emit("remote-retry", retry) {
+        log::warn!("failed to emit remote retry: {err}");
+      }
+    },
+  )
+  .await
+}
+
+async fn convert(
+  path: &Path,
+  endpoint: &str,
+  api_key: Option<&str>,
+  timeout: Duration,
+  retry: &RetryConfig,
+  mut on_retry: impl FnMut(RemoteRetry),
+) -> Result<String, ConversionError> {
+  let source = path.to_path_buf();
+  let bytes = tauri::async_runtime::spawn_blocking(move || read_pdf(&source)).await??;
+  let file_name = path
+    .file_name()
+    .map_or_else(|| "document.pdf".into(), |name| name.to_string_lossy())
+    .into_owned();
+
+  let client = Client::builder()
+    .timeout(timeout)
+    .build()
+    .map_err(|e| ConversionError::Remote(e.to_string()))?;
+  let reason = |err: &reqwest::Error| {
+    if err.is_timeout() {
+      format!("no answer within {}s", timeout.as_secs())
+    } else {
+      err.to_string()
+    }
+  };
+
+  let max_attempts = retry.max_attempts.max(1);
+  let mut attempt = 1;
+  let answer = loop {
+    let answer = send(&client, endpoint, api_key, &bytes, &file_name).await;
+    let (failure, retry_after) = match &answer {
+      Ok(reply) if !is_transient(reply.status) => break answer,
+      Ok(reply) => (
+        format!("server answered {}", reply.status),
+        reply.retry_after,
+      ),
+      Err(err) => (reason(err), None),
+    };
+    if attempt >= max_attempts {
+      break answer;
+    }
+    let delay = retry_after.unwrap_or_else(|| retry.backoff(attempt));
+    attempt += 1;
+    log::info!(
+      "retrying remote conversion of {} ({attempt}/{max_attempts}) in {}ms: {failure}",
+      path.display(),
+      delay.as_millis()
+    );
+    on_retry(RemoteRetry {
+      path: path.display().to_string(),
+      attempt,
+      max_attempts,
+      delay_ms: delay.as_millis() as u64,
+      reason: failure,
+    });
+    tokio::time::sleep(delay).await;
+  };
+  let answer = answer.map_err(|err| ConversionError::Remote(reason(&err)))?;
+  log::info!(
+    "remote conversion of {} answered {}",
+    path.display(),
+    answer.status
+  );
+  parse_response(answer.status, &answer.body)
+}
+
+/// Uploads the PDF once and reads the answer.
+async fn send(
+  client: &Client,
+  endpoint: &str,
+  api_key: Option<&str>,
+  bytes: &[u8],
+  file_name: &str,
+) -> Result<Answer, reqwest::Error> {
+  let part = multipart::Part::bytes(bytes.to_vec())
+    .file_name(file_name.to_string())
+    .mime_str("application/pdf")?;
+  let form = multipart::Form::new().part("file", part);
+  let mut request = client.post(endpoint).multipart(form);
+  if let Some(api_key) = api_key {
+    request = request.bearer_auth(api_key);
+  }
+  let response = request.send().await?;
+  let status = response.status();
+  let retry_after = retry_after(response.headers());
+  let body = response.text().await?;
+  Ok(Answer {
+    status,
+    retry_after,
+    body,
+  })
+}
+
+/// Whether a response with `status` is worth trying again: the server is
+/// overloaded or failing, not refusing the request.
+fn is_transient(status: StatusCode) -> bool {
+  status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// The wait a `Retry-After` header asks for. Only a number of seconds is
+/// understood; an HTTP date leaves the wait to the backoff.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+  let secs: u64 = headers
+    .get(RETRY_AFTER)?
+    .to_str()
+    .ok()?
+    .trim()
+    .parse()
+    .ok()?;
+  Some(Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS)))
+}
+
+fn read_pdf(path: &Path) -> Result<Vec<u8>, ConversionError> {
+  if !path.is_file() {
+    return Err(ConversionError::FileNotFound(path.to_path_buf()));
+  }
+  Ok(fs::read(path)?)
+}
+
+/// The Markdown from a successful response, or the server's error message.
+fn parse_response(status: StatusCode, body: &str) -> Result<String, ConversionError> {
+  if !status.is_success() {
+    let message = error_message(body);
+    return Err(ConversionError::Remote(if message.is_empty() {
+      format!("server answered {status}")
+    } else {
+      format!("server answered {status}: {message}")
+    }));
+  }
+  serde_json::from_str::<RemoteResponse>(body)
+    .map(|response| response.markdown)
+    .map_err(|e| ConversionError::Remote(format!("unexpected response: {e}")))
+}
+
+/// The `error`, `message` or `detail` field of a JSON error body, or the body
+/// itself.
+fn error_message(body: &str) -> String {
+  let json = serde_json::from_str::<serde_json::Value>(body).ok();
+  let field = json.as_ref().and_then(|json| {
+    ["error", "message", "detail"]
+      .iter()
+      .find_map(|key| json.get(key)?.as_str())
+  });
+  let message = field.unwrap_or(body).trim();
+  match message.char_indices().nth(MAX_ERROR_CHARS) {
+    Some((end, _)) => format!("{}…", &message[..end]),
+    None => message.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_the_markdown_from_a_successful_response() {
+    let body = r##"{"markdown": "# Title\n", "pages": 3}"##;
+    assert_eq!(parse_response(StatusCode::OK, body).unwrap(), "# Title\n");
+    assert!(parse_response(StatusCode::OK, "<html>").is_err());
+  }
+
+  #[test]
+  fn retries_back_off_unless_the_server_says_when() {
+    let retry = RetryConfig {
+      max_attempts: 4,
+      base_delay_ms: 500,
+    };
+    assert_eq!(retry.backoff(1), Duration::from_millis(500));
+    assert_eq!(retry.backoff(3), Duration::from_millis(2000));
+
+    let mut headers = HeaderMap::new();
+    headers.insert(RETRY_AFTER, "7".parse().unwrap());
+    assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+    headers.insert(
+      RETRY_AFTER,
+      "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+    );
+    assert_eq!(retry_after(&headers), None);
+
+    assert!(is_transient(StatusCode::BAD_GATEWAY));
+    assert!(is_transient(StatusCode::TOO_MANY_REQUESTS));
+    assert!(!is_transient(StatusCode::BAD_REQUEST));
+  }
+
+  #[test]
+  fn surfaces_the_servers_error() {
+    let error = |status, body| parse_response(status, body).unwrap_err().to_string();
+    assert_eq!(
+      error(StatusCode::BAD_REQUEST, r#"{"error": "not a PDF"}"#),
+      "remote conversion failed: server answered 400 Bad Request: not a PDF"
+    );
+    assert_eq!(
+      error(StatusCode::BAD_GATEWAY, "upstream down\n"),
+      "remote conversion failed: server answered 502 Bad Gateway: upstream down"
+    );
+    assert_eq!(
+      error(StatusCode::UNAUTHORIZED, ""),
+      "remote conversion failed: server answered 401 Unauthorized"
+    );
+  }
+}
@@ -0,0 +1,103 @@
+//! "Convert and save as": a PDF picked in one file dialog, converted and
+//! saved where a second one says, so the frontend needn't handle paths.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::convert;
+use crate::document::OutputFormat;
+use crate::error::ConversionError;
+use crate::jobs::ConversionRegistry;
+use crate::options::ConvertOptions;
+
+/// Asks for a PDF, converts it as `convert_pdf_to_markdown` does, then asks
+/// where to save it, offering `<name>.md` (or the extension of `format`)
+/// beside the PDF. Returns the saved path, or `None` if either dialog was
+/// cancelled.
+#[tauri::command]
+pub async fn pick_and_convert(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  job_id: Option<String>,
+) -> Result<Option<String>, ConversionError> {
+  let format = format.unwrap_or_default();
+  let Some(source) = pick_pdf(&app).await? else {
+    return Ok(None);
+  };
+  let output = convert::convert_pdf_to_markdown(
+    app.clone(),
+    registry,
+    source.display().to_string(),
+    options,
+    Some(format),
+    None,
+    job_id,
+  )
+  .await?
+  .output;
+  let Some(target) = pick_target(&app, &source, format).await? else {
+    return Ok(None);
+  };
+  fs::write(&target, output)?;
+  log::info!("saved {}", target.display());
+  Ok(Some(target.display().to_string()))
+}
+
+/// The PDF picked in an open dialog, if any.
+async fn pick_pdf(app: &AppHandle) -> Result<Option<PathBuf>, ConversionError> {
+  let dialog = app.dialog().file().add_filter("PDF", &["pdf"]);
+  // The dialog blocks until it's closed, so it's kept off the async workers.
+  let picked = tauri::async_runtime::spawn_blocking(move || dialog.blocking_pick_file()).await?;
+  Ok(picked.and_then(|picked| picked.into_path().ok()))
+}
+
+/// Where a save dialog says the conversion of `source` to `format` goes, if
+/// anywhere.
+async fn pick_target(
+  app: &AppHandle,
+  source: &Path,
+  format: OutputFormat,
+) -> Result<Option<PathBuf>, ConversionError> {
+  let extension = format.extension();
+  let mut dialog = app
+    .dialog()
+    .file()
+    .add_filter(extension, &[extension])
+    .set_file_name(output_name(source, format));
+  if let Some(parent) = source.parent() {
+    dialog = dialog.set_directory(parent);
+  }
+  let picked = tauri::async_runtime::spawn_blocking(move || dialog.blocking_save_file()).await?;
+  Ok(picked.and_then(|picked| picked.into_path().ok()))
+}
+
+/// The file name offered for the conversion of `source`: its own name with
+/// the extension of `format`.
+fn output_name(source: &Path, format: OutputFormat) -> String {
+  let stem = source
+    .file_stem()
+    .map_or_else(|| "converted".into(), |stem| stem.to_string_lossy());
+  format!("{stem}.{}", format.extension())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn the_offered_name_follows_the_pdf() {
+    assert_eq!(
+      output_name(Path::new("/docs/Annual Report.pdf"), OutputFormat::Markdown),
+      "Annual Report.md"
+    );
+    assert_eq!(
+      output_name(Path::new("notes.PDF"), OutputFormat::Html),
+      "notes.html"
+    );
+  }
+}
@@ -0,0 +1,171 @@
+//! User preferences, persisted as JSON in the app's config directory.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::ConversionError;
+use crate::window_effect::WindowEffect;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// The [`Settings::image_dpi`] of new installs.
+pub const DEFAULT_IMAGE_DPI: u32 = 150;
+
+/// Preferences that survive restarts.
+///
+/// Missing fields fall back to their defaults, so files written by older
+/// versions keep loading as settings are added.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+  /// Where PDFs converted to files go when no place is given for them;
+  /// `None` puts them next to the PDF. The PDFs of a folder converted as a
+  /// whole keep their subfolders in there.
+  pub output_dir: Option<String>,
+  /// Whether scanned pages are read with OCR.
+  pub ocr_enabled: bool,
+  /// Tesseract language used for OCR, e.g. `"eng"` or `"deu+eng"`.
+  pub ocr_language: String,
+  /// The highest resolution images are written at when a conversion doesn't
+  /// ask for one; see [`ImageOptions::max_dpi`](crate::images::ImageOptions::max_dpi).
+  pub image_dpi: u32,
+  /// Background effect behind the window, reapplied at launch.
+  pub window_effect: WindowEffect,
+  /// Whether to ask the release feed for new versions. Off until the user
+  /// opts in.
+  pub check_for_updates: bool,
+  /// Whether to load the PDF code and, with OCR on, Tesseract's language
+  /// pack in the background at launch, so the first conversion doesn't wait
+  /// for them.
+  pub prewarm: bool,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Settings {
+      output_dir: None,
+      ocr_enabled: false,
+      ocr_language: "eng".into(),
+      image_dpi: DEFAULT_IMAGE_DPI,
+      window_effect: WindowEffect::default(),
+      check_for_updates: false,
+      prewarm: false,
+    }
+  }
+}
+
+/// Returns the saved settings, or the defaults on first launch.
+#[tauri::command]
+pub fn load_settings(app: AppHandle) -> Settings {
+  match settings_path(&app) {
+    Ok(path) => read_settings(&path),
+    Err(err) => {
+      log::warn!("cannot locate settings: {err}");
+      Settings::default()
+    }
+  }
+}
+
+/// Validates and saves the settings.
+#[tauri::command]
+pub fn save_settings(app: AppHandle, settings: Settings) -> Result<(), ConversionError> {
+  validate(&settings)?;
+  let path = settings_path(&app)?;
+  write_settings(&path, &settings).map_err(|e| ConversionError::io("cannot save settings", e))
+}
+
+/// Changes one setting, leaving the others as saved.
+pub fn update(app: &AppHandle, change: impl FnOnce(&mut Settings)) -> Result<(), ConversionError> {
+  let path = settings_path(app)?;
+  let mut settings = read_settings(&path);
+  change(&mut settings);
+  write_settings(&path, &settings).map_err(|e| ConversionError::io("cannot save settings", e))
+}
+
+fn settings_path(app: &AppHandle) -> tauri::Result<PathBuf> {
+  Ok(app.path().app_config_dir()?.join(SETTINGS_FILE))
+}
+
+/// Reads settings from `path`. A missing file means defaults; an unreadable
+/// one is logged and also replaced by defaults rather than blocking startup.
+fn read_settings(path: &Path) -> Settings {
+  let text = match fs::read_to_string(path) {
+    Ok(text) => text,
+    Err(err) if err.kind() == io::ErrorKind::NotFound => return Settings::default(),
+    Err(err) => {
+      log::warn!("cannot read {}: {err}", path.display());
+      return Settings::default();
+    }
+  };
+  serde_json::from_str(&text).unwrap_or_else(|err| {
+    log::warn!("ignoring malformed {}: {err}", path.display());
+    Settings::default()
+  })
+}
+
+fn write_settings(path: &Path, settings: &Settings) -> io::Result<()> {
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+  let json = serde_json::to_string_pretty(settings).map_err(io::Error::other)?;
+  // Write then rename, so a crash mid-write can't leave a truncated file.
+  let temp = path.with_extension("json.tmp");
+  fs::write(&temp, json)?;
+  fs::rename(&temp, path)
+}
+
+fn validate(settings: &Settings) -> Result<(), ConversionError> {
+  if let Some(dir) = &settings.output_dir {
+    if !Path::new(dir).is_dir() {
+      return Err(ConversionError::InvalidSettings(format!(
+        "output directory {dir} does not exist"
+      )));
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn missing_fields_use_defaults() {
+    let settings: Settings = serde_json::from_str(r#"{ "imageDpi": 300 }"#).unwrap();
+    assert_eq!(settings.image_dpi, 300);
+    assert_eq!(settings.ocr_language, Settings::default().ocr_language);
+  }
+
+  #[test]
+  fn round_trips_through_the_file() {
+    let dir = std::env::temp_dir().join(format!("pdf2markdown-settings-{}", std::process::id()));
+    let path = dir.join(SETTINGS_FILE);
+    assert_eq!(read_settings(&path), Settings::default());
+
+    let settings = Settings {
+      output_dir: Some(dir.display().to_string()),
+      ocr_enabled: true,
+      ocr_language: "deu".into(),
+      image_dpi: 200,
+      window_effect: WindowEffect::Sidebar,
+      check_for_updates: true,
+      prewarm: true,
+    };
+    write_settings(&path, &settings).unwrap();
+    assert_eq!(read_settings(&path), settings);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn rejects_missing_output_dir() {
+    let settings = Settings {
+      output_dir: Some("/definitely/not/a/real/dir".into()),
+      ..Settings::default()
+    };
+    assert!(validate(&settings).is_err());
+  }
+}
@@ -0,0 +1,211 @@
+//! Splitting a converted PDF into one file per chapter, as its top-level
+//! bookmarks divide it.
+
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, State};
+
+use crate::cache::{self, Cache};
+use crate::convert;
+use crate::document::{self, OutputFormat};
+use crate::error::ConversionError;
+use crate::jobs::{CancelToken, ConversionRegistry};
+use crate::options::ConvertOptions;
+use crate::outline::{self, Bookmark};
+use crate::output::{self, OutputFile, WriteMode, WriteOutcome};
+
+/// File names made from bookmark titles are cut to this many characters.
+const MAX_TITLE_LEN: usize = 80;
+
+/// Converts the PDF at `path` into one file per top-level bookmark in
+/// `out_dir`, each named after its bookmark and numbered so that they sort
+/// in reading order: `01-Introduction.md`, `02-Installation.md`, ... Pages
+/// before the first bookmark go into `00-front-matter.md`. Returns the files,
+/// each with whether it was written, skipped or renamed as `write_mode` says.
+///
+/// Fails with `noOutline` if the PDF has no bookmarks.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn convert_split_by_outline(
+  app: AppHandle,
+  registry: State<'_, ConversionRegistry>,
+  path: String,
+  out_dir: String,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+  password: Option<String>,
+  job_id: Option<String>,
+  write_mode: Option<WriteMode>,
+) -> Result<Vec<OutputFile>, ConversionError> {
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  let job = registry.start(job_id).report_progress(&app);
+  let cache = Cache::open(&app).filter(|_| password.is_none());
+  tauri::async_runtime::spawn_blocking(move || {
+    let written = split_by_outline(
+      Path::new(&path),
+      Path::new(&out_dir),
+      password.as_deref(),
+      &options,
+      format,
+      write_mode.unwrap_or_default(),
+      cache.as_ref(),
+      job.token(),
+    )?;
+    Ok(
+      written
+        .iter()
+        .map(|(file, outcome)| OutputFile::new(file, *outcome))
+        .collect(),
+    )
+  })
+  .await?
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn split_by_outline(
+  path: &Path,
+  out_dir: &Path,
+  password: Option<&str>,
+  options: &ConvertOptions,
+  format: OutputFormat,
+  write_mode: WriteMode,
+  cache: Option<&Cache>,
+  cancel: &CancelToken,
+) -> Result<Vec<(PathBuf, WriteOutcome)>, ConversionError> {
+  let doc = convert::load_document(path, password)?;
+  let count = doc.get_pages().len() as u32;
+  let sections = sections(&outline::bookmarks(&doc), count);
+  if sections.is_empty() {
+    return Err(ConversionError::NoOutline);
+  }
+  // The whole document at once, so headings are measured against the same
+  // body text in every chapter.
+  let converted = cache::cached(cache, path, &("document", options), || {
+    convert::convert_document(&doc, (1..=count as usize).collect(), options, cancel)
+  })?;
+
+  fs::create_dir_all(out_dir)
+    .map_err(|e| ConversionError::io(format_args!("cannot create {}", out_dir.display()), e))?;
+  let width = sections.len().to_string().len().max(2);
+  let mut written = Vec::with_capacity(sections.len());
+  for (index, (title, pages)) in sections.iter().enumerate() {
+    // Front matter is numbered 0, so the chapters start from 1.
+    let number = index + usize::from(sections[0].0.is_some());
+    let name = title.as_deref().map_or("front-matter".into(), file_name);
+    let file = out_dir.join(format!("{number:0width$}-{name}.{}", format.extension()));
+    let section = document::Document {
+      metadata: converted.metadata.clone(),
+      pages: converted
+        .pages
+        .iter()
+        .filter(|page| pages.contains(&page.number))
+        .cloned()
+        .collect(),
+      page_separator: converted.page_separator.clone(),
+      tight_output: converted.tight_output,
+    };
+    let file = output::write(&file, section.render(format).as_bytes(), write_mode)
+      .map_err(|e| ConversionError::io(format_args!("cannot write {}", file.display()), e))?;
+    written.push(file);
+  }
+  Ok(written)
+}
+
+/// The pages of each top-level bookmark of a document with `count` pages:
+/// from its own page to the page before the next one's. Pages before the
+/// first bookmark come first, without a title. Empty if no top-level
+/// bookmark points into the document.
+fn sections(bookmarks: &[Bookmark], count: u32) -> Vec<(Option<String>, RangeInclusive<u32>)> {
+  let mut starts: Vec<(u32, &str)> = bookmarks
+    .iter()
+    .filter(|bookmark| bookmark.depth == 0)
+    .filter_map(|bookmark| {
+      Some((
+        bookmark.page.filter(|&page| page <= count)?,
+        bookmark.title.as_str(),
+      ))
+    })
+    .collect();
+  // Bookmarks needn't be in page order, and a chapter starting where
+  // another does would have no pages of its own.
+  starts.sort_by_key(|&(page, _)| page);
+  starts.dedup_by_key(|&mut (page, _)| page);
+
+  let mut sections = Vec::with_capacity(starts.len() + 1);
+  if let Some(&(first, _)) = starts.first().filter(|&&(first, _)| first > 1) {
+    sections.push((None, 1..=first - 1));
+  }
+  for (i, &(page, title)) in starts.iter().enumerate() {
+    let end = starts.get(i + 1).map_or(count, |&(next, _)| next - 1);
+    sections.push((Some(title.to_string()), page..=end));
+  }
+  sections
+}
+
+/// `title` as a file name that works on every platform: no path separators
+/// or characters Windows rejects, spaces collapsed and at most
+/// [`MAX_TITLE_LEN`] characters.
+fn file_name(title: &str) -> String {
+  let cleaned: String = title
+    .chars()
+    .map(|c| match c {
+      '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => ' ',
+      c if c.is_control() => ' ',
+      c => c,
+    })
+    .collect();
+  let name: String = cleaned
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ")
+    .chars()
+    .take(MAX_TITLE_LEN)
+    .collect();
+  // Windows drops trailing dots and spaces, which would merge names.
+  let name = name.trim_end_matches(['.', ' ']).trim_start_matches('.');
+  if name.is_empty() {
+    "untitled".to_string()
+  } else {
+    name.to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn top_level_bookmarks_divide_the_pages() {
+    let bookmark = |title: &str, depth, page| Bookmark {
+      title: title.to_string(),
+      depth,
+      page: Some(page),
+    };
+    let bookmarks = [
+      bookmark("Installation", 0, 7),
+      bookmark("Introduction", 0, 3),
+      bookmark("Scope", 1, 4),
+      bookmark("Setup", 0, 7),
+      bookmark("Index", 0, 40),
+    ];
+    assert_eq!(
+      sections(&bookmarks, 12),
+      [
+        (None, 1..=2),
+        (Some("Introduction".to_string()), 3..=6),
+        (Some("Installation".to_string()), 7..=12),
+      ]
+    );
+    assert_eq!(sections(&bookmarks[2..3], 12), []);
+  }
+
+  #[test]
+  fn titles_become_safe_file_names() {
+    assert_eq!(file_name("Part 1/2: Setup  & Use?"), "Part 1 2 Setup & Use");
+    assert_eq!(file_name("..."), "untitled");
+    assert_eq!(file_name(&"é".repeat(100)).chars().count(), MAX_TITLE_LEN);
+  }
+}
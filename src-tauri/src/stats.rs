@@ -0,0 +1,122 @@
+//! Counts for a converted document: words, characters, headings, images and
+//! the time it takes to read, worked out from the Markdown alone so they can
+//! be kept up to date as the preview is edited.
+
+use serde::Serialize;
+
+use crate::whitespace;
+
+/// Reading speed when none is given, in words per minute.
+const DEFAULT_WORDS_PER_MINUTE: u32 = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkdownStats {
+  /// Words of prose: code blocks, front matter, link targets and markup
+  /// aren't counted.
+  pub words: usize,
+  /// Every character but line breaks.
+  pub characters: usize,
+  /// Minutes it takes to read the words, rounded up.
+  pub reading_minutes: u32,
+  pub heading_count: usize,
+  pub image_count: usize,
+}
+
+/// The stats of `markdown`, read at `words_per_minute` (200 by default).
+#[tauri::command]
+pub fn markdown_stats(markdown: String, words_per_minute: Option<u32>) -> MarkdownStats {
+  stats(
+    &markdown,
+    words_per_minute
+      .filter(|&wpm| wpm > 0)
+      .unwrap_or(DEFAULT_WORDS_PER_MINUTE),
+  )
+}
+
+fn stats(markdown: &str, words_per_minute: u32) -> MarkdownStats {
+  let mut stats = MarkdownStats {
+    words: 0,
+    characters: markdown.chars().filter(|&c| c != '\n' && c != '\r').count(),
+    reading_minutes: 0,
+    heading_count: 0,
+    image_count: 0,
+  };
+  let mut lines = markdown.lines().peekable();
+  // Front matter, if the document starts with it.
+  if lines.next_if(|line| line.trim_end() == "---").is_some() {
+    for line in lines.by_ref() {
+      if line.trim_end() == "---" {
+        break;
+      }
+    }
+  }
+  let mut fence = None;
+  for line in lines {
+    if let Some(open) = fence {
+      if whitespace::closes(line, open) {
+        fence = None;
+      }
+      continue;
+    }
+    if let Some(open) = whitespace::opening(line) {
+      fence = Some(open);
+      continue;
+    }
+    if is_heading(line) {
+      stats.heading_count += 1;
+    }
+    stats.image_count += line.matches("![").count();
+    stats.words += words(line);
+  }
+  stats.reading_minutes = stats.words.div_ceil(words_per_minute as usize) as u32;
+  stats
+}
+
+/// Whether `line` is an ATX heading: one to six `#` and a space.
+fn is_heading(line: &str) -> bool {
+  let line = line.trim_start();
+  let hashes = line.len() - line.trim_start_matches('#').len();
+  (1..=6).contains(&hashes) && line[hashes..].starts_with([' ', '\t'])
+}
+
+/// The words of `line`, leaving out link and image targets and tokens that
+/// are only markup (`#`, `-`, `|`, `**`).
+fn words(line: &str) -> usize {
+  let mut count = 0;
+  let mut rest = line;
+  while !rest.is_empty() {
+    // Everything from `](` to the closing parenthesis is a target.
+    let (text, after) = match rest.split_once("](") {
+      Some((text, after)) => (text, after.split_once(')').map_or("", |(_, after)| after)),
+      None => (rest, ""),
+    };
+    count += text
+      .split_whitespace()
+      .filter(|word| word.chars().any(char::is_alphanumeric))
+      .count();
+    rest = after;
+  }
+  count
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn code_and_markup_are_left_out_of_the_words() {
+    let markdown = "---\ntitle: Report\n---\n\n# Results\n\n\
+                    See [the data](https://example.com/data) and **run** it.\n\n\
+                    ```\nfn main() {}\n```\n\n![](images/page-1-1.png)\n\n- one\n- two 2\n";
+    let stats = stats(markdown, 3);
+    assert_eq!(stats.words, 10);
+    assert_eq!(stats.reading_minutes, 4);
+    assert_eq!(stats.heading_count, 1);
+    assert_eq!(stats.image_count, 1);
+    assert_eq!(
+      stats.characters,
+      markdown.len() - markdown.matches('\n').count()
+    );
+  }
+}
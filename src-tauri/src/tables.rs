@@ -0,0 +1,374 @@
+//! Table detection.
+//!
+//! Rows are lines that break into several cells separated by wide gaps. A run
+//! of such rows whose cells line up in columns becomes a table block; when the
+//! columns don't line up well enough to trust, the rows are kept as
+//! preformatted text so the layout at least survives.
+
+use std::ops::Range;
+
+use crate::document::Block;
+use crate::extract::{TextLine, TextSpan};
+
+/// A horizontal gap wider than this, relative to the font size, separates cells.
+const CELL_GAP: f32 = 0.5;
+
+/// Runs of this many spaces inside a span also separate cells.
+const CELL_SPACES: usize = 3;
+
+/// Rows further apart than this, relative to the font size, are not one table.
+const MAX_ROW_GAP: f32 = 2.5;
+
+/// Single-cell lines (section labels, wrapped headers) a table may contain in a
+/// row before it is considered finished.
+const MAX_SINGLE_CELL_RUN: usize = 2;
+
+/// Cells averaging more characters than this are prose laid out side by side
+/// (columns of text), not table data.
+const MAX_MEAN_CELL_LEN: usize = 40;
+
+/// Share of rows whose cells must fall into distinct columns for the result to
+/// be rendered as a table rather than as preformatted text.
+const MIN_CONFIDENCE: f32 = 0.8;
+
+/// A table found among a page's lines.
+#[derive(Debug, Clone)]
+pub struct Table {
+  /// The lines the table replaces.
+  pub lines: Range<usize>,
+  /// A [`Block::Table`], or [`Block::Code`] for the preformatted fallback.
+  pub block: Block,
+}
+
+/// Finds the tables in `lines`, which must be ordered top to bottom.
+///
+/// `tolerance` is how far apart (in points) cell edges may be and still count
+/// as one column.
+pub fn find_tables(lines: &[TextLine], tolerance: f32) -> Vec<Table> {
+  let rows: Vec<Vec<Cell>> = lines.iter().map(cells).collect();
+  let close = |a: usize, b: usize| {
+    let gap = lines[a].y() - lines[b].y();
+    gap > 0.0 && gap <= MAX_ROW_GAP * lines[a].font_size().max(lines[b].font_size())
+  };
+
+  let mut tables = Vec::new();
+  let mut start = 0;
+  while start < rows.len() {
+    if rows[start].len() < 2 {
+      start += 1;
+      continue;
+    }
+    let mut end = start + 1;
+    'extend: while end < rows.len() {
+      // Take up to a few single-cell lines, but only if more rows follow them.
+      for skip in 0..=MAX_SINGLE_CELL_RUN {
+        let next = end + skip;
+        if next >= rows.len() || !close(next - 1, next) {
+          break;
+        }
+        if rows[next].len() >= 2 {
+          end = next + 1;
+          continue 'extend;
+        }
+      }
+      break;
+    }
+
+    let region = &rows[start..end];
+    if is_tabular(region) {
+      tables.push(Table {
+        lines: start..end,
+        block: render(region, tolerance),
+      });
+    }
+    start = end;
+  }
+  tables
+}
+
+/// A cell of a row, with its horizontal extent in page space.
+#[derive(Debug, Clone)]
+struct Cell {
+  text: String,
+  left: f32,
+  right: f32,
+}
+
+impl Cell {
+  fn overlap(&self, column: &(f32, f32)) -> f32 {
+    self.right.min(column.1) - self.left.max(column.0)
+  }
+}
+
+/// Splits a line into cells at wide gaps between spans and at runs of spaces.
+fn cells(line: &TextLine) -> Vec<Cell> {
+  let mut cells: Vec<Cell> = Vec::new();
+  for span in &line.spans {
+    for piece in split_at_spaces(span) {
+      match cells.last_mut() {
+        Some(cell) if piece.left - cell.right < CELL_GAP * span.font_size => {
+          cell.text.push_str(&piece.text);
+          cell.right = piece.right;
+        }
+        _ => cells.push(piece),
+      }
+    }
+  }
+  cells
+    .into_iter()
+    .filter_map(|mut cell| {
+      cell.text = cell.text.trim().to_string();
+      (!cell.text.is_empty()).then_some(cell)
+    })
+    .collect()
+}
+
+/// Breaks a span at runs of [`CELL_SPACES`] or more spaces, estimating where
+/// each piece sits from its share of the characters.
+fn split_at_spaces(span: &TextSpan) -> Vec<Cell> {
+  let chars: Vec<char> = span.text.chars().collect();
+  let advance = span.width / chars.len().max(1) as f32;
+  let mut pieces = Vec::new();
+  let mut start = 0;
+  let mut i = 0;
+  while i < chars.len() {
+    if chars[i] != ' ' {
+      i += 1;
+      continue;
+    }
+    let run_end = (i..chars.len())
+      .find(|&j| chars[j] != ' ')
+      .unwrap_or(chars.len());
+    if run_end - i >= CELL_SPACES {
+      pieces.push((start, i));
+      start = run_end;
+    }
+    i = run_end;
+  }
+  pieces.push((start, chars.len()));
+
+  pieces
+    .into_iter()
+    .filter(|(start, end)| start < end)
+    .map(|(start, end)| Cell {
+      text: chars[start..end].iter().collect(),
+      left: span.x + start as f32 * advance,
+      right: span.x + end as f32 * advance,
+    })
+    .collect()
+}
+
+/// Whether a run of rows looks like table data at all: at least two rows with
+/// several cells, and cells short enough not to be running text.
+fn is_tabular(rows: &[Vec<Cell>]) -> bool {
+  let multi_cell_rows = rows.iter().filter(|row| row.len() >= 2).count();
+  let cells: Vec<&Cell> = rows.iter().flatten().collect();
+  let chars: usize = cells.iter().map(|cell| cell.text.chars().count()).sum();
+  multi_cell_rows >= 2 && chars / cells.len() <= MAX_MEAN_CELL_LEN
+}
+
+fn render(rows: &[Vec<Cell>], tolerance: f32) -> Block {
+  let columns = columns(rows, tolerance);
+  let grid: Vec<Option<Vec<String>>> = rows.iter().map(|row| place(row, &columns)).collect();
+  let multi_cell = rows.iter().zip(&grid).filter(|(row, _)| row.len() >= 2);
+  let (clean, total) = multi_cell.fold((0, 0), |(clean, total), (_, cells)| {
+    (clean + usize::from(cells.is_some()), total + 1)
+  });
+
+  if columns.len() < 2 || (clean as f32) < MIN_CONFIDENCE * total as f32 {
+    return preformatted(rows);
+  }
+  let grid: Vec<Vec<String>> = grid
+    .into_iter()
+    .zip(rows)
+    .map(|(cells, row)| cells.unwrap_or_else(|| spill(row, columns.len())))
+    .collect();
+  Block::Table(grid)
+}
+
+/// Column bands: the extents of the cells of multi-cell rows, merged wherever
+/// they overlap or come within `tolerance` of each other. Single-cell rows are
+/// left out since they are often labels spanning several columns.
+fn columns(rows: &[Vec<Cell>], tolerance: f32) -> Vec<(f32, f32)> {
+  let mut extents: Vec<(f32, f32)> = rows
+    .iter()
+    .filter(|row| row.len() >= 2)
+    .flatten()
+    .map(|cell| (cell.left, cell.right))
+    .collect();
+  extents.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+  let mut columns: Vec<(f32, f32)> = Vec::new();
+  for (left, right) in extents {
+    match columns.last_mut() {
+      Some(column) if left <= column.1 + tolerance => column.1 = column.1.max(right),
+      _ => columns.push((left, right)),
+    }
+  }
+  columns
+}
+
+/// Puts each cell of a row in the column it overlaps most, or `None` if two
+/// cells land in the same column.
+fn place(row: &[Cell], columns: &[(f32, f32)]) -> Option<Vec<String>> {
+  let mut cells = vec![String::new(); columns.len()];
+  for cell in row {
+    let (index, _) = columns
+      .iter()
+      .enumerate()
+      .map(|(i, column)| (i, cell.overlap(column)))
+      .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if !cells[index].is_empty() {
+      return None;
+    }
+    cells[index] = cell.text.clone();
+  }
+  Some(cells)
+}
+
+/// Fallback for a row that doesn't fit the columns: cells in order, padded.
+fn spill(row: &[Cell], columns: usize) -> Vec<String> {
+  let mut cells: Vec<String> = row.iter().map(|cell| cell.text.clone()).collect();
+  if cells.len() > columns {
+    let rest = cells.split_off(columns - 1).join(" ");
+    cells.push(rest);
+  }
+  cells.resize(columns, String::new());
+  cells
+}
+
+/// Lays the rows out as preformatted text, placing each cell at roughly its
+/// horizontal position so the columns still read as columns.
+fn preformatted(rows: &[Vec<Cell>]) -> Block {
+  let origin = rows
+    .iter()
+    .flatten()
+    .map(|cell| cell.left)
+    .fold(f32::MAX, f32::min);
+  // Average glyph advance over all cells.
+  let (width, chars) = rows
+    .iter()
+    .flatten()
+    .fold((0.0, 0), |(width, chars), cell| {
+      (
+        width + cell.right - cell.left,
+        chars + cell.text.chars().count(),
+      )
+    });
+  let advance = (width / chars.max(1) as f32).max(1.0);
+
+  let mut out = Vec::new();
+  for row in rows {
+    let mut line = String::new();
+    for cell in row {
+      let column = ((cell.left - origin) / advance).round() as usize;
+      let used = line.chars().count();
+      let pad = if used == 0 {
+        column
+      } else {
+        column.saturating_sub(used).max(2)
+      };
+      line.push_str(&" ".repeat(pad));
+      line.push_str(&cell.text);
+    }
+    out.push(line);
+  }
+  Block::Code(out.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn line(y: f32, cells: &[(f32, &str)]) -> TextLine {
+    TextLine {
+      spans: cells
+        .iter()
+        .map(|&(x, text)| TextSpan {
+          text: text.to_string(),
+          x,
+          y,
+          width: 5.0 * text.len() as f32,
+          font_size: 10.0,
+          font_name: "Helvetica".into(),
+          link: None,
+          style: Default::default(),
+          footnote: None,
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn renders_aligned_rows_as_a_table() {
+    let lines = [
+      line(700.0, &[(50.0, "Name"), (200.0, "Qty")]),
+      line(686.0, &[(50.0, "Apples"), (200.0, "3")]),
+      line(672.0, &[(50.0, "Pears"), (200.0, "12")]),
+    ];
+    let tables = find_tables(&lines, 3.0);
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].lines, 0..3);
+    let rows = |rows: &[&[&str]]| {
+      rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect()
+    };
+    assert_eq!(
+      tables[0].block,
+      Block::Table(rows(&[
+        &["Name", "Qty"],
+        &["Apples", "3"],
+        &["Pears", "12"]
+      ]))
+    );
+  }
+
+  #[test]
+  fn ragged_rows_get_empty_cells() {
+    let lines = [
+      line(700.0, &[(50.0, "Item"), (200.0, "2010"), (300.0, "2009")]),
+      line(686.0, &[(50.0, "Assets")]),
+      line(672.0, &[(50.0, "Property"), (200.0, "345"), (300.0, "445")]),
+      line(658.0, &[(50.0, "Cash"), (300.0, "12")]),
+    ];
+    let tables = find_tables(&lines, 3.0);
+    assert_eq!(tables.len(), 1);
+    let Block::Table(rows) = &tables[0].block else {
+      panic!("expected a table, got {:?}", tables[0].block);
+    };
+    assert_eq!(rows[1], ["Assets", "", ""]);
+    assert_eq!(rows[3], ["Cash", "", "12"]);
+  }
+
+  #[test]
+  fn single_column_text_is_not_a_table() {
+    let lines = [
+      line(700.0, &[(50.0, "One")]),
+      line(686.0, &[(50.0, "Two")]),
+      line(672.0, &[(50.0, "Three")]),
+    ];
+    assert!(find_tables(&lines, 3.0).is_empty());
+  }
+
+  #[test]
+  fn misaligned_cells_fall_back_to_preformatted_text() {
+    // A wide cell in the second row bridges the first two columns, so the
+    // first row's cells collide.
+    let lines = [
+      line(700.0, &[(50.0, "a"), (80.0, "b"), (300.0, "c")]),
+      line(686.0, &[(50.0, "wide cell text"), (300.0, "d")]),
+    ];
+    let tables = find_tables(&lines, 3.0);
+    assert_eq!(tables.len(), 1);
+    let Block::Code(text) = &tables[0].block else {
+      panic!("expected preformatted text, got {:?}", tables[0].block);
+    };
+    let rows: Vec<&str> = text.lines().collect();
+    assert!(rows[0].starts_with("a     b   "));
+    assert!(rows[1].starts_with("wide cell text   "));
+    // The last column still lines up.
+    assert_eq!(rows[0].rfind('c'), rows[1].rfind('d'));
+  }
+}
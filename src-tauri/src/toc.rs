@@ -0,0 +1,466 @@
+//! Tables of contents: a nested list of links to the headings, built from
+//! the PDF's outline or, without one, from the headings themselves.
+//!
+//! Links from one place in the PDF to another lead to the same anchors: both
+//! are given out by [`Anchors`], in the order of the headings.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::document::{self, Block, Inline, List, ListItem, Page};
+use crate::links;
+use crate::outline::Bookmark;
+
+/// A heading of the converted document and the anchor that links to it, for
+/// the table of contents and the frontend's outline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Heading {
+  pub page: u32,
+  pub level: usize,
+  pub text: String,
+  pub anchor: String,
+}
+
+/// Hands out the anchors of a document's headings in order, as GitHub does:
+/// each heading's [`slug`], with `-1`, `-2` and so on added to repeats.
+/// Headings without a slug, all symbols, are numbered from `-1` as repeats
+/// of the empty one, which can't be linked to; see [`needs_id`].
+#[derive(Debug, Default)]
+pub struct Anchors {
+  /// The anchors given out, each with how many repeats of it have been
+  /// numbered so far.
+  given: HashMap<String, usize>,
+}
+
+impl Anchors {
+  /// The anchor of the next heading, whose text is `text`.
+  pub fn next(&mut self, text: &str) -> String {
+    let slug = slug(text);
+    let mut anchor = slug.clone();
+    // Numbers can be taken too, by a heading such as `Notes 1` before a
+    // second `Notes`; those are passed over.
+    while anchor.is_empty() || self.given.contains_key(&anchor) {
+      let repeats = self.given.entry(slug.clone()).or_default();
+      *repeats += 1;
+      anchor = format!("{slug}-{repeats}");
+    }
+    self.given.insert(anchor.clone(), 0);
+    anchor
+  }
+}
+
+/// Whether a heading with `text` needs an anchor written out in Markdown:
+/// GitHub gives the first of the headings without a slug none.
+pub fn needs_id(text: &str) -> bool {
+  slug(text).is_empty()
+}
+
+/// The headings on `page`, in order, with anchors from `anchors`.
+pub fn headings(page: &Page, anchors: &mut Anchors) -> Vec<Heading> {
+  page
+    .blocks
+    .iter()
+    .filter_map(|block| match block {
+      Block::Heading { level, content } => {
+        let text = document::plain(content).trim().to_string();
+        Some(Heading {
+          page: page.number,
+          level: *level,
+          anchor: anchors.next(&text),
+          text,
+        })
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+/// The headings of a document converted to `pages`.
+pub fn document_headings(pages: &[Page]) -> Vec<Heading> {
+  let mut anchors = Anchors::default();
+  pages
+    .iter()
+    .flat_map(|page| headings(page, &mut anchors))
+    .collect()
+}
+
+/// Puts the table of contents at the start of the converted `pages`, with
+/// `bookmarks` from the PDF's outline.
+pub fn prepend(bookmarks: Vec<Bookmark>, pages: &mut [Page]) {
+  let headings = document_headings(pages);
+  let numbers: Vec<u32> = pages.iter().map(|page| page.number).collect();
+  if let (Some(toc), Some(first)) = (generate(bookmarks, &headings, &numbers), pages.first_mut()) {
+    first.blocks.insert(0, toc);
+  }
+}
+
+/// The table of contents of a PDF with `bookmarks`, converted to `pages`
+/// with `headings` on them. Bookmarks to other pages are left out.
+pub fn generate(
+  mut bookmarks: Vec<Bookmark>,
+  headings: &[Heading],
+  pages: &[u32],
+) -> Option<Block> {
+  bookmarks.retain(|bookmark| bookmark.page.is_some_and(|page| pages.contains(&page)));
+  table_of_contents(&bookmarks, headings)
+}
+
+/// The table of contents for a document with `headings`, following
+/// `bookmarks` if there are any. `None` if there is nothing to list.
+///
+/// A bookmark links to the first heading from its page on whose text it
+/// matches, ignoring case, spacing and punctuation. One that matches none is
+/// listed without a link, as there is nothing in the output to go to.
+/// Bookmarks without a page are left out.
+fn table_of_contents(bookmarks: &[Bookmark], headings: &[Heading]) -> Option<Block> {
+  let entries: Vec<(usize, String, Option<String>)> = if bookmarks.is_empty() {
+    let top = headings.iter().map(|heading| heading.level).min()?;
+    headings
+      .iter()
+      .map(|heading| {
+        (
+          heading.level - top,
+          heading.text.clone(),
+          Some(heading.anchor.clone()),
+        )
+      })
+      .collect()
+  } else {
+    bookmarks
+      .iter()
+      .filter(|bookmark| !bookmark.title.is_empty())
+      .filter_map(|bookmark| {
+        let heading = matching(&bookmark.title, bookmark.page?, headings);
+        let anchor = heading.map(|heading| heading.anchor.clone());
+        Some((bookmark.depth, bookmark.title.clone(), anchor))
+      })
+      .collect()
+  };
+  if entries.is_empty() {
+    return None;
+  }
+  let mut entries = entries.into_iter().peekable();
+  Some(Block::List(nest(&mut entries, 0)))
+}
+
+/// The entries at `depth` or deeper as a list, deeper ones nested under the
+/// entry before them.
+fn nest(
+  entries: &mut std::iter::Peekable<impl Iterator<Item = (usize, String, Option<String>)>>,
+  depth: usize,
+) -> List {
+  let mut items: Vec<ListItem> = Vec::new();
+  while let Some(&(entry_depth, _, _)) = entries.peek() {
+    if entry_depth < depth {
+      break;
+    }
+    if let (true, Some(last)) = (entry_depth > depth, items.last_mut()) {
+      last.children.push(nest(entries, entry_depth));
+      continue;
+    }
+    let Some((_, title, anchor)) = entries.next() else {
+      break;
+    };
+    let title = Inline::Text(title);
+    items.push(ListItem {
+      content: vec![match anchor {
+        Some(anchor) => Inline::Link {
+          content: vec![title],
+          url: format!("#{anchor}"),
+        },
+        None => title,
+      }],
+      children: Vec::new(),
+    });
+  }
+  List { start: None, items }
+}
+
+/// Points the links in `pages` to other pages of the document, as
+/// extraction found them, at the `headings` they lead to: the first heading
+/// from that page on whose text the link's matches, or else the first on the
+/// page. A link to a page without headings is left as its text.
+pub fn link_headings(pages: &mut [Page], headings: &[Heading]) {
+  let anchor = |page: u32, content: &[Inline]| {
+    matching(&document::plain(content), page, headings)
+      .or_else(|| headings.iter().find(|heading| heading.page == page))
+      .map(|heading| format!("#{}", heading.anchor))
+  };
+  for page in pages {
+    point_links(&mut page.blocks, &anchor);
+    for note in &mut page.footnotes {
+      point_inline_links(&mut note.content, &anchor);
+    }
+  }
+}
+
+fn point_links(blocks: &mut [Block], anchor: &impl Fn(u32, &[Inline]) -> Option<String>) {
+  fn list(items: &mut List, anchor: &impl Fn(u32, &[Inline]) -> Option<String>) {
+    for item in &mut items.items {
+      point_inline_links(&mut item.content, anchor);
+      for child in &mut item.children {
+        list(child, anchor);
+      }
+    }
+  }
+  for block in blocks {
+    match block {
+      Block::Heading { content, .. } | Block::Paragraph(content) => {
+        point_inline_links(content, anchor)
+      }
+      Block::List(items) => list(items, anchor),
+      Block::Quote(blocks) => point_links(blocks, anchor),
+      Block::Table(_) | Block::Code(_) | Block::Image { .. } | Block::Math(_) => {}
+    }
+  }
+}
+
+fn point_inline_links(
+  content: &mut Vec<Inline>,
+  anchor: &impl Fn(u32, &[Inline]) -> Option<String>,
+) {
+  let mut i = 0;
+  while i < content.len() {
+    let unlinked = match &mut content[i] {
+      Inline::Link { content, url } => match links::linked_page(url) {
+        Some(page) => match anchor(page, content) {
+          Some(anchor) => {
+            *url = anchor;
+            None
+          }
+          None => Some(std::mem::take(content)),
+        },
+        None => None,
+      },
+      Inline::Strong(content) | Inline::Emphasis(content) => {
+        point_inline_links(content, anchor);
+        None
+      }
+      _ => None,
+    };
+    match unlinked {
+      Some(text) => {
+        let len = text.len();
+        content.splice(i..=i, text);
+        i += len;
+      }
+      None => i += 1,
+    }
+  }
+}
+
+/// The first of `headings` from `page` on whose text matches `text`.
+fn matching<'a>(text: &str, page: u32, headings: &'a [Heading]) -> Option<&'a Heading> {
+  let key = match_key(text);
+  headings
+    .iter()
+    .filter(|heading| heading.page >= page)
+    .find(|heading| match_key(&heading.text) == key)
+}
+
+/// The anchor GitHub gives a heading with `text`: lowercased, punctuation
+/// removed and spaces turned into hyphens. Letters outside ASCII are kept,
+/// as GitHub keeps them. A heading of nothing but symbols has an empty slug.
+pub fn slug(text: &str) -> String {
+  text
+    .trim()
+    .chars()
+    .filter_map(|c| match c {
+      ' ' => Some('-'),
+      '-' | '_' => Some(c),
+      c if c.is_alphanumeric() => Some(c),
+      _ => None,
+    })
+    .flat_map(char::to_lowercase)
+    .collect()
+}
+
+/// `text` reduced to what matching a bookmark to a heading looks at.
+fn match_key(text: &str) -> String {
+  text
+    .chars()
+    .filter(|c| c.is_alphanumeric())
+    .flat_map(char::to_lowercase)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::markdown;
+
+  fn heading(page: u32, level: usize, text: &str) -> Heading {
+    Heading {
+      page,
+      level,
+      text: text.to_string(),
+      anchor: slug(text),
+    }
+  }
+
+  #[test]
+  fn slugs_follow_github() {
+    assert_eq!(slug("2.1 Results & Discussion"), "21-results--discussion");
+    assert_eq!(slug(" Über_alles - Teil 1 "), "über_alles---teil-1");
+    assert_eq!(slug("Введение"), "введение");
+    assert_eq!(slug("★ ★"), "-");
+    assert_eq!(slug("★"), "");
+  }
+
+  #[test]
+  fn repeated_headings_get_numbered_anchors() {
+    let mut anchors = Anchors::default();
+    let given: Vec<String> = ["Introduction", "Notes 1", "Notes", "Introduction", "Notes"]
+      .iter()
+      .map(|text| anchors.next(text))
+      .collect();
+    assert_eq!(
+      given,
+      [
+        "introduction",
+        "notes-1",
+        "notes",
+        "introduction-1",
+        "notes-2"
+      ]
+    );
+  }
+
+  #[test]
+  fn bookmarks_nest_and_link_to_matching_headings() {
+    let bookmark = |title: &str, depth, page| Bookmark {
+      title: title.to_string(),
+      depth,
+      page: Some(page),
+    };
+    let bookmarks = [
+      bookmark("1 Introduction", 0, 1),
+      bookmark("1.1 Scope", 1, 1),
+      bookmark("2 Methods", 0, 2),
+      bookmark("Appendix", 0, 2),
+    ];
+    let headings = [
+      heading(1, 1, "1 — Introduction"),
+      heading(1, 2, "1.1 Scope"),
+      heading(2, 1, "2 Methods"),
+    ];
+    let toc = table_of_contents(&bookmarks, &headings).unwrap();
+    assert_eq!(
+      markdown::block(&toc),
+      "- [1 Introduction](#1--introduction)\n  \
+       - [1.1 Scope](#11-scope)\n\
+       - [2 Methods](#2-methods)\n\
+       - Appendix"
+    );
+  }
+
+  #[test]
+  fn links_to_headings_of_symbols_resolve() {
+    let heading = |text: &str| Block::Heading {
+      level: 1,
+      content: vec![Inline::Text(text.into())],
+    };
+    let mut document = document::Document {
+      pages: vec![Page {
+        number: 1,
+        blocks: vec![heading("★"), heading("★ ★"), heading("Intro"), heading("★")],
+        footnotes: Vec::new(),
+      }],
+      ..Default::default()
+    };
+    prepend(Vec::new(), &mut document.pages);
+    let output = document.render(document::OutputFormat::Markdown);
+    assert!(output.starts_with("- [★](#-1)\n- [★ ★](#-)\n- [Intro](#intro)\n- [★](#-2)\n"));
+
+    // The anchors GitHub gives the headings, and those written out with them.
+    let mut anchors = Vec::new();
+    let mut repeats = HashMap::new();
+    for line in output.lines().filter(|line| line.starts_with("# ")) {
+      let mut text = &line[2..];
+      if let Some((id, rest)) = text
+        .strip_prefix("<a id=\"")
+        .and_then(|rest| rest.split_once("\"></a>"))
+      {
+        anchors.push(id.to_string());
+        text = rest;
+      }
+      let slug = slug(text);
+      let n: &mut usize = repeats.entry(slug.clone()).or_default();
+      anchors.push(match *n {
+        0 => slug.clone(),
+        n => format!("{slug}-{n}"),
+      });
+      *n += 1;
+    }
+    let links = output
+      .lines()
+      .filter_map(|line| line.split_once("](#"))
+      .map(|(_, anchor)| anchor.trim_end_matches(')'));
+    for link in links {
+      assert!(
+        anchors.iter().any(|anchor| anchor == link),
+        "#{link} in {output}"
+      );
+    }
+  }
+
+  #[test]
+  fn links_within_the_document_go_to_the_headings_anchors() {
+    let link = |text: &str, page| Inline::Link {
+      content: vec![Inline::Text(text.into())],
+      url: links::page_link(page),
+    };
+    let heading = |text: &str| Block::Heading {
+      level: 1,
+      content: vec![Inline::Text(text.into())],
+    };
+    let page = |number, blocks| Page {
+      number,
+      blocks,
+      footnotes: Vec::new(),
+    };
+    let mut pages = vec![
+      page(
+        1,
+        vec![
+          heading("Introduction"),
+          Block::Paragraph(vec![
+            Inline::Text("See ".into()),
+            link("Introduction", 3),
+            Inline::Text(", ".into()),
+            link("Figure 2", 2),
+            Inline::Text(" and ".into()),
+            Inline::Strong(vec![link("the index", 4)]),
+            Inline::Text(".".into()),
+          ]),
+        ],
+      ),
+      page(2, vec![heading("Methods")]),
+      page(3, vec![heading("Results"), heading("Introduction")]),
+      page(4, Vec::new()),
+    ];
+    let headings = document_headings(&pages);
+    link_headings(&mut pages, &headings);
+    assert_eq!(
+      markdown::block(&pages[0].blocks[1]),
+      "See [Introduction](#introduction-1), [Figure 2](#methods) and **the index**."
+    );
+  }
+
+  #[test]
+  fn without_an_outline_the_headings_are_listed() {
+    let headings = [
+      heading(1, 2, "Overview"),
+      heading(1, 3, "Goals"),
+      heading(2, 2, "Design"),
+    ];
+    let toc = table_of_contents(&[], &headings).unwrap();
+    assert_eq!(
+      markdown::block(&toc),
+      "- [Overview](#overview)\n  - [Goals](#goals)\n- [Design](#design)"
+    );
+    assert_eq!(table_of_contents(&[], &[]), None);
+  }
+}
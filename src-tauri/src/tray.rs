@@ -0,0 +1,175 @@
+//! The system tray icon, which keeps the app running with its window closed.
+//!
+//! With the tray in place, closing the main window only hides it; Quit in the
+//! tray menu is the way out.
+
+use std::path::Path;
+
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager, WebviewWindow, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::batch;
+use crate::cache::Cache;
+use crate::document::OutputFormat;
+use crate::jobs::CancelToken;
+use crate::options::ConvertOptions;
+use crate::output::{self, WriteMode};
+use crate::settings;
+
+const TRAY_ID: &str = "main";
+
+const IDLE_TOOLTIP: &str = "PDF to Markdown Converter";
+
+/// Adds the tray icon and its menu.
+pub fn create(app: &AppHandle) -> tauri::Result<()> {
+  let menu = Menu::with_items(
+    app,
+    &[
+      &MenuItem::with_id(app, "open", "Open", true, None::<&str>)?,
+      &MenuItem::with_id(app, "convert", "Convert a file…", true, None::<&str>)?,
+      &PredefinedMenuItem::separator(app)?,
+      &MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?,
+    ],
+  )?;
+  let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+    .tooltip(IDLE_TOOLTIP)
+    .menu(&menu)
+    // A left click toggles the window; the menu is on the right button.
+    .show_menu_on_left_click(false)
+    .on_menu_event(on_menu_event)
+    .on_tray_icon_event(|tray, event| {
+      if let TrayIconEvent::Click {
+        button: MouseButton::Left,
+        button_state: MouseButtonState::Up,
+        ..
+      } = event
+      {
+        toggle_window(tray.app_handle());
+      }
+    });
+  if let Some(icon) = app.default_window_icon() {
+    tray = tray.icon(icon.clone());
+  }
+  tray.build(app)?;
+  Ok(())
+}
+
+/// Hides `window` instead of closing it, so the app stays in the tray.
+pub fn hide_on_close(window: &WebviewWindow) {
+  let handle = window.clone();
+  window.on_window_event(move |event| {
+    if let WindowEvent::CloseRequested { api, .. } = event {
+      api.prevent_close();
+      if let Err(err) = handle.hide() {
+        log::warn!("cannot hide window: {err}");
+      }
+    }
+  });
+}
+
+/// Shows batch progress in the tray's tooltip.
+pub fn show_progress(app: &AppHandle, current: usize, total: usize) {
+  set_tooltip(app, &format!("Converting PDFs: {current} of {total} done"));
+}
+
+/// Puts the tooltip back once a batch is over.
+pub fn show_idle(app: &AppHandle) {
+  set_tooltip(app, IDLE_TOOLTIP);
+}
+
+fn set_tooltip(app: &AppHandle, text: &str) {
+  let Some(tray) = app.tray_by_id(TRAY_ID) else {
+    return;
+  };
+  if let Err(err) = tray.set_tooltip(Some(text)) {
+    log::warn!("cannot update tray tooltip: {err}");
+  }
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+  match event.id().as_ref() {
+    "open" => show_window(app),
+    "convert" => pick_and_convert(app),
+    "quit" => app.exit(0),
+    _ => {}
+  }
+}
+
+fn main_window(app: &AppHandle) -> Option<WebviewWindow> {
+  app.get_webview_window("main")
+}
+
+fn show_window(app: &AppHandle) {
+  let Some(window) = main_window(app) else {
+    return;
+  };
+  if let Err(err) = window.show().and_then(|()| window.set_focus()) {
+    log::warn!("cannot show window: {err}");
+  }
+}
+
+fn toggle_window(app: &AppHandle) {
+  let Some(window) = main_window(app) else {
+    return;
+  };
+  if window.is_visible().unwrap_or(false) {
+    if let Err(err) = window.hide() {
+      log::warn!("cannot hide window: {err}");
+    }
+  } else {
+    show_window(app);
+  }
+}
+
+/// Asks for a PDF and converts it to Markdown next to the original, or into
+/// the output directory of the settings, with the default options, then says
+/// how it went.
+fn pick_and_convert(app: &AppHandle) {
+  let app = app.clone();
+  app
+    .dialog()
+    .file()
+    .add_filter("PDF", &["pdf"])
+    .pick_file(move |picked| {
+      let Some(path) = picked.and_then(|picked| picked.into_path().ok()) else {
+        return;
+      };
+      tauri::async_runtime::spawn_blocking(move || {
+        let out_dir = settings::load_settings(app.clone()).output_dir;
+        let output = output::converted_path(
+          &path,
+          path.parent().unwrap_or(Path::new("")),
+          out_dir.as_deref().map(Path::new),
+          OutputFormat::Markdown.extension(),
+        );
+        let result = batch::convert_to(
+          &path,
+          &output,
+          &ConvertOptions::default(),
+          OutputFormat::Markdown,
+          WriteMode::Overwrite,
+          Cache::open(&app).as_ref(),
+          &CancelToken::default(),
+        );
+        let (kind, message) = match result {
+          Ok(batch::ConversionResult {
+            output: Some(output),
+            ..
+          }) => (MessageDialogKind::Info, format!("Saved {output}")),
+          Ok(batch::ConversionResult { error, .. }) => (
+            MessageDialogKind::Error,
+            error.unwrap_or_else(|| "conversion failed".into()),
+          ),
+          Err(err) => (MessageDialogKind::Error, err.to_string()),
+        };
+        app
+          .dialog()
+          .message(message)
+          .title("Convert a file")
+          .kind(kind)
+          .show(|_| {});
+      });
+    });
+}
@@ -0,0 +1,153 @@
+//! Self-updates from the release feed configured in `tauri.conf.json`.
+//!
+//! Checks are opt-in: nothing is fetched until the user turns on
+//! `checkForUpdates` in the settings. Builds without the public key that
+//! updates are signed with offer none, since none could be verified.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_updater::UpdaterExt;
+
+use crate::error::ConversionError;
+use crate::settings;
+
+/// The outcome of an update check. Not being able to reach the feed is an
+/// outcome too, not an error: being offline shouldn't raise an alarm.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+  tag = "status",
+  rename_all = "camelCase",
+  rename_all_fields = "camelCase"
+)]
+pub enum UpdateCheck {
+  /// Update checks are turned off in the settings.
+  Disabled,
+  UpToDate {
+    current_version: String,
+  },
+  Available {
+    current_version: String,
+    version: String,
+    /// The release notes.
+    notes: Option<String>,
+  },
+  /// The feed couldn't be reached or read, or this build has no key to
+  /// verify updates with.
+  Unavailable {
+    current_version: String,
+    reason: String,
+  },
+}
+
+/// Bytes downloaded of an update so far, sent as `update-progress` events.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgress {
+  pub downloaded: u64,
+  /// The size of the update, if the server says.
+  pub total: Option<u64>,
+}
+
+const NO_PUBKEY: &str = "this build has no key to verify updates with";
+
+/// Whether `plugins.updater.pubkey` in `tauri.conf.json` is set.
+fn has_pubkey(app: &AppHandle) -> bool {
+  app
+    .config()
+    .plugins
+    .0
+    .get("updater")
+    .and_then(|updater| updater.get("pubkey"))
+    .and_then(serde_json::Value::as_str)
+    .is_some_and(|key| !key.trim().is_empty())
+}
+
+/// Asks the release feed whether there is a newer version than this one.
+#[tauri::command]
+pub async fn check_for_update(app: AppHandle) -> UpdateCheck {
+  if !settings::load_settings(app.clone()).check_for_updates {
+    return UpdateCheck::Disabled;
+  }
+  let current_version = app.package_info().version.to_string();
+  if !has_pubkey(&app) {
+    return UpdateCheck::Unavailable {
+      current_version,
+      reason: NO_PUBKEY.into(),
+    };
+  }
+  let update = match app.updater() {
+    Ok(updater) => updater.check().await,
+    Err(err) => Err(err),
+  };
+  match update {
+    Ok(None) => UpdateCheck::UpToDate { current_version },
+    Ok(Some(update)) => UpdateCheck::Available {
+      current_version,
+      version: update.version,
+      notes: update.body,
+    },
+    Err(err) => {
+      log::info!("cannot check for updates: {err}");
+      UpdateCheck::Unavailable {
+        current_version,
+        reason: err.to_string(),
+      }
+    }
+  }
+}
+
+/// Downloads and installs the newest version, reporting progress with
+/// `update-progress` events, then restarts into it. Does nothing if this
+/// version is already the newest.
+///
+/// Installing is always a deliberate request, so it works even with update
+/// checks turned off.
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> Result<(), ConversionError> {
+  if !has_pubkey(&app) {
+    return Err(ConversionError::Update(NO_PUBKEY.into()));
+  }
+  let Some(update) = app.updater()?.check().await? else {
+    return Ok(());
+  };
+  log::info!(
+    "installing update {} over {}",
+    update.version,
+    update.current_version
+  );
+  let mut downloaded = 0;
+  update
+    .download_and_install(
+      |chunk, total| {
+        downloaded += chunk as u64;
+        let progress = UpdateProgress { downloaded, total };
+        if let Err(err) = app.emit("update-progress", progress) {
+          log::warn!("failed to emit update progress: {err}");
+        }
+      },
+      || log::info!("update downloaded"),
+    )
+    .await?;
+  app.restart()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn checks_serialize_with_a_status_tag() {
+    let check = UpdateCheck::Unavailable {
+      current_version: "0.1.1".into(),
+      reason: "offline".into(),
+    };
+    assert_eq!(
+      serde_json::to_value(check).unwrap(),
+      serde_json::json!({
+        "status": "unavailable",
+        "currentVersion": "0.1.1",
+        "reason": "offline",
+      })
+    );
+  }
+}
@@ -0,0 +1,258 @@
+//! Watching a folder and converting PDFs as they appear in it.
+//!
+//! File system events are debounced per file: a PDF is converted once it has
+//! had no events for [`QUIET_PERIOD`] and its size has stopped changing, so a
+//! file still being written by a scanner or a download isn't picked up half
+//! finished. Partial downloads (`.crdownload`, `.part`, `.tmp`, ...) are
+//! ignored until they are renamed to `.pdf`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::batch;
+use crate::cache::Cache;
+use crate::document::OutputFormat;
+use crate::error::ConversionError;
+use crate::jobs::CancelToken;
+use crate::options::ConvertOptions;
+use crate::output::{self, WriteMode};
+use crate::settings;
+
+/// How long a file must go without events before it's looked at.
+const QUIET_PERIOD: Duration = Duration::from_millis(750);
+
+/// How often pending files are checked.
+const TICK: Duration = Duration::from_millis(250);
+
+/// The folder being watched, if any. Lives in Tauri's managed state.
+#[derive(Default)]
+pub struct FolderWatch(Mutex<Option<ActiveWatch>>);
+
+impl FolderWatch {
+  /// Swaps in a new watch, returning the previous one.
+  fn replace(&self, watch: Option<ActiveWatch>) -> Option<ActiveWatch> {
+    let mut current = self.0.lock().unwrap_or_else(|e| e.into_inner());
+    std::mem::replace(&mut *current, watch)
+  }
+}
+
+/// A running watch. Dropping it stops the watcher and waits for the worker
+/// thread to finish.
+pub struct ActiveWatch {
+  watcher: Option<RecommendedWatcher>,
+  cancel: CancelToken,
+  worker: Option<JoinHandle<()>>,
+}
+
+impl Drop for ActiveWatch {
+  fn drop(&mut self) {
+    self.cancel.cancel();
+    // Dropping the watcher closes the event channel, which ends the worker.
+    self.watcher.take();
+    if let Some(worker) = self.worker.take() {
+      let _ = worker.join();
+    }
+  }
+}
+
+/// Starts converting PDFs created in (or moved into) `dir`, replacing any
+/// folder watched before. Files already there are left alone.
+///
+/// Each PDF is converted to a file as `convert_directory` would, and
+/// a `file-converted` event carries its [`batch::ConversionResult`].
+#[tauri::command]
+pub fn start_watching(
+  app: AppHandle,
+  state: State<'_, FolderWatch>,
+  dir: String,
+  options: Option<ConvertOptions>,
+  format: Option<OutputFormat>,
+) -> Result<(), ConversionError> {
+  let dir = PathBuf::from(dir);
+  if !dir.is_dir() {
+    return Err(ConversionError::FileNotFound(dir));
+  }
+  let options = options.unwrap_or_default();
+  let format = format.unwrap_or_default();
+  // Stop the old watch first so it can't convert into the new one's results.
+  drop(state.replace(None));
+
+  let (sender, events) = mpsc::channel();
+  let mut watcher = notify::recommended_watcher(sender)?;
+  watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+  let cancel = CancelToken::default();
+  let token = cancel.clone();
+  let watched = dir.clone();
+  let worker = thread::spawn(move || {
+    let cache = Cache::open(&app);
+    let out_dir = settings::load_settings(app.clone()).output_dir;
+    let mut pending = Pending::default();
+    loop {
+      match events.recv_timeout(TICK) {
+        Ok(Ok(event)) => {
+          if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            for path in event.paths.iter().filter(|path| is_candidate(path)) {
+              pending.touch(path, Instant::now());
+            }
+          }
+        }
+        Ok(Err(err)) => log::warn!("folder watch error: {err}"),
+        Err(RecvTimeoutError::Timeout) => {}
+        Err(RecvTimeoutError::Disconnected) => return,
+      }
+      let size = |path: &Path| fs::metadata(path).ok().map(|meta| meta.len());
+      for path in pending.ready(Instant::now(), size) {
+        if token.check().is_err() {
+          return;
+        }
+        log::info!("converting {} from the watched folder", path.display());
+        let output = output::converted_path(
+          &path,
+          &watched,
+          out_dir.as_deref().map(Path::new),
+          format.extension(),
+        );
+        let Ok(result) = batch::convert_to(
+          &path,
+          &output,
+          &options,
+          format,
+          WriteMode::Overwrite,
+          cache.as_ref(),
+          &token,
+        ) else {
+          return;
+        };
+        if let Err(err) = app.emit("file-converted", result) {
+          log::warn!("failed to emit file-converted: {err}");
+        }
+      }
+    }
+  });
+
+  log::info!("watching {} for new PDFs", dir.display());
+  state.replace(Some(ActiveWatch {
+    watcher: Some(watcher),
+    cancel,
+    worker: Some(worker),
+  }));
+  Ok(())
+}
+
+/// Stops watching, after the conversion in progress (if any) is cancelled.
+/// Does nothing if no folder is being watched.
+#[tauri::command]
+pub fn stop_watching(state: State<'_, FolderWatch>) {
+  if state.replace(None).is_some() {
+    log::info!("stopped watching for new PDFs");
+  }
+}
+
+/// Whether an event for `path` may concern a finished PDF: it has the `.pdf`
+/// extension and isn't a hidden or lock file (`.name.pdf`, `~$name.pdf`).
+fn is_candidate(path: &Path) -> bool {
+  let hidden = path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .is_some_and(|name| name.starts_with('.') || name.starts_with("~$"));
+  batch::is_pdf(path) && !hidden
+}
+
+/// Files with recent events, waiting to settle.
+#[derive(Debug, Default)]
+struct Pending(HashMap<PathBuf, Settling>);
+
+#[derive(Debug)]
+struct Settling {
+  last_event: Instant,
+  /// Size at the last check, once the file has been quiet for a while.
+  size: Option<u64>,
+}
+
+impl Pending {
+  /// Records an event for `path`, restarting its quiet period.
+  fn touch(&mut self, path: &Path, now: Instant) {
+    let settling = self.0.entry(path.to_path_buf()).or_insert(Settling {
+      last_event: now,
+      size: None,
+    });
+    settling.last_event = now;
+  }
+
+  /// Takes the files that are done being written: quiet for [`QUIET_PERIOD`]
+  /// and with the same, non-zero size over two checks. `size` is `None` for
+  /// files that are gone, which are dropped.
+  fn ready(&mut self, now: Instant, size: impl Fn(&Path) -> Option<u64>) -> Vec<PathBuf> {
+    let mut ready = Vec::new();
+    self.0.retain(|path, settling| {
+      if now.duration_since(settling.last_event) < QUIET_PERIOD {
+        return true;
+      }
+      let Some(current) = size(path) else {
+        return false;
+      };
+      if current > 0 && settling.size == Some(current) {
+        ready.push(path.clone());
+        return false;
+      }
+      // Still growing (or empty): check again after another quiet period.
+      settling.size = Some(current);
+      settling.last_event = now;
+      true
+    });
+    ready.sort();
+    ready
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ignores_partial_and_hidden_files() {
+    assert!(is_candidate(Path::new("/scans/invoice.pdf")));
+    assert!(is_candidate(Path::new("/scans/INVOICE.PDF")));
+    assert!(!is_candidate(Path::new("/scans/invoice.pdf.crdownload")));
+    assert!(!is_candidate(Path::new("/scans/invoice.tmp")));
+    assert!(!is_candidate(Path::new("/scans/~$invoice.pdf")));
+    assert!(!is_candidate(Path::new("/scans/.invoice.pdf")));
+  }
+
+  #[test]
+  fn files_are_ready_once_quiet_and_no_longer_growing() {
+    let path = Path::new("/scans/a.pdf");
+    let start = Instant::now();
+    let mut pending = Pending::default();
+    pending.touch(path, start);
+    pending.touch(path, start + Duration::from_millis(500));
+
+    // Not quiet for long enough yet.
+    let at = |ms| start + Duration::from_millis(ms);
+    assert!(pending.ready(at(1000), |_| Some(10)).is_empty());
+    // Quiet, but this is the first size seen.
+    assert!(pending.ready(at(1300), |_| Some(10)).is_empty());
+    // Grew since the last check.
+    assert!(pending.ready(at(2100), |_| Some(20)).is_empty());
+    assert_eq!(pending.ready(at(2900), |_| Some(20)), vec![path]);
+    assert!(pending.0.is_empty());
+  }
+
+  #[test]
+  fn deleted_files_are_dropped() {
+    let start = Instant::now();
+    let mut pending = Pending::default();
+    pending.touch(Path::new("/scans/a.pdf"), start);
+    assert!(pending.ready(start + QUIET_PERIOD, |_| None).is_empty());
+    assert!(pending.0.is_empty());
+  }
+}
@@ -0,0 +1,156 @@
+//! The last pass over rendered Markdown, tidying the whitespace every stage
+//! before it may have left: spaces at the ends of lines, runs of blank lines
+//! and blank lines at the start and end.
+//!
+//! Fenced code blocks are left exactly as they are, since their whitespace
+//! is part of the code.
+
+/// Tidies Markdown a line at a time as it is rendered, so a document written
+/// page by page comes out the same as one rendered whole.
+#[derive(Debug, Default)]
+pub struct Normalizer {
+  /// Whether blank lines between list items are dropped too.
+  tight: bool,
+  /// The text after the last line break so far.
+  partial: String,
+  /// Whether anything but blank lines has been written.
+  started: bool,
+  /// Whether blank lines came after the last line written.
+  blank: bool,
+  /// Whether the last line written was in a list: an item or indented
+  /// under one.
+  in_list: bool,
+  /// The character and length of the fence of the code block the text is
+  /// in, if it is in one.
+  fence: Option<(char, usize)>,
+}
+
+impl Normalizer {
+  /// A normalizer that, if `tight`, also closes up blank lines between list
+  /// items.
+  pub fn new(tight: bool) -> Self {
+    Normalizer {
+      tight,
+      ..Normalizer::default()
+    }
+  }
+
+  /// The tidied lines `text` completes, following on from the text before.
+  /// Whatever comes after its last line break waits for the next call.
+  pub fn push(&mut self, text: &str) -> String {
+    self.partial.push_str(text);
+    let Some(end) = self.partial.rfind('\n') else {
+      return String::new();
+    };
+    let complete: String = self.partial.drain(..=end).collect();
+    let mut out = String::new();
+    for line in complete[..end].split('\n') {
+      self.line(line, &mut out);
+    }
+    out
+  }
+
+  /// The rest of the text, ending in a single line break unless there was
+  /// no text at all.
+  pub fn finish(mut self) -> String {
+    let rest = std::mem::take(&mut self.partial);
+    let mut out = String::new();
+    self.line(&rest, &mut out);
+    out
+  }
+
+  fn line(&mut self, line: &str, out: &mut String) {
+    if let Some(fence) = self.fence {
+      if closes(line, fence) {
+        self.fence = None;
+      }
+      out.push_str(line);
+      out.push('\n');
+      return;
+    }
+    let line = line.trim_end();
+    if line.is_empty() {
+      self.blank |= self.started;
+      return;
+    }
+    let item = is_list_item(line);
+    if self.blank && !(self.tight && self.in_list && item) {
+      out.push('\n');
+    }
+    out.push_str(line);
+    out.push('\n');
+    self.started = true;
+    self.blank = false;
+    self.in_list = item || (self.in_list && line.starts_with(' '));
+    self.fence = opening(line);
+  }
+}
+
+/// `text` tidied all at once.
+pub fn normalize(text: &str, tight: bool) -> String {
+  let mut normalizer = Normalizer::new(tight);
+  let mut out = normalizer.push(text);
+  out.push_str(&normalizer.finish());
+  out
+}
+
+/// The fence `line` opens a code block with: three or more backticks or
+/// tildes.
+pub fn opening(line: &str) -> Option<(char, usize)> {
+  let line = line.trim_start();
+  let c = line.chars().next().filter(|&c| c == '`' || c == '~')?;
+  let len = line.len() - line.trim_start_matches(c).len();
+  (len >= 3).then_some((c, len))
+}
+
+/// Whether `line` closes a code block opened with `fence`.
+pub fn closes(line: &str, (c, len): (char, usize)) -> bool {
+  let line = line.trim();
+  let rest = line.trim_start_matches(c);
+  line.len() - rest.len() >= len && rest.is_empty()
+}
+
+/// Whether `line` starts a list item: `-`, `*`, `+` or a number and `.` or
+/// `)`, then a space.
+fn is_list_item(line: &str) -> bool {
+  let line = line.trim_start();
+  let marker = line.trim_start_matches(|c: char| c.is_ascii_digit());
+  let marker = if marker.len() < line.len() {
+    marker.strip_prefix(['.', ')'])
+  } else {
+    marker.strip_prefix(['-', '*', '+'])
+  };
+  marker.is_some_and(|rest| rest.starts_with(' '))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn blank_lines_collapse_except_in_code() {
+    let text = "\n# Title  \n\n\n\nText\t\n\n```\na  \n\n\n\nb\n```\n\n\n- one\n\n- two\n\n\n";
+    assert_eq!(
+      normalize(text, false),
+      "# Title\n\nText\n\n```\na  \n\n\n\nb\n```\n\n- one\n\n- two\n"
+    );
+    assert_eq!(
+      normalize(text, true),
+      "# Title\n\nText\n\n```\na  \n\n\n\nb\n```\n\n- one\n- two\n"
+    );
+    assert_eq!(normalize("\n\n", false), "");
+  }
+
+  #[test]
+  fn text_pushed_in_pieces_comes_out_the_same() {
+    let text = "Intro\n\n\n1. one\n   more\n\n2. two\n\nAfter\n\n- a";
+    let mut normalizer = Normalizer::new(true);
+    let mut out = String::new();
+    for piece in text.split_inclusive(['\n', 'o']) {
+      out.push_str(&normalizer.push(piece));
+    }
+    out.push_str(&normalizer.finish());
+    assert_eq!(out, normalize(text, true));
+    assert_eq!(out, "Intro\n\n1. one\n   more\n2. two\n\nAfter\n\n- a\n");
+  }
+}
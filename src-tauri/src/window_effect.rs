@@ -0,0 +1,141 @@
+//! Translucent window backgrounds: vibrancy on macOS, Mica or acrylic on
+//! Windows.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::ConversionError;
+use crate::settings;
+
+/// A window background effect. The macOS materials are named after
+/// `NSVisualEffectMaterial`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WindowEffect {
+  /// Full-screen UI vibrancy on macOS, Mica on Windows.
+  #[default]
+  Auto,
+  None,
+  Sidebar,
+  FullScreenUi,
+  HudWindow,
+  Menu,
+  Popover,
+  WindowBackground,
+  UnderWindowBackground,
+  Mica,
+  Acrylic,
+}
+
+/// Applies `effect` (e.g. `"sidebar"`, `"mica"` or `"none"`) to the window
+/// and saves it for the next launch.
+///
+/// Returns whether the effect took: an effect this platform doesn't have,
+/// such as Mica on macOS, is saved but leaves the window opaque.
+#[tauri::command]
+pub fn set_window_effect(
+  app: AppHandle,
+  window: WebviewWindow,
+  effect: String,
+) -> Result<bool, ConversionError> {
+  let effect: WindowEffect = serde_json::from_value(serde_json::Value::String(effect))
+    .map_err(|_| ConversionError::InvalidSettings("unknown window effect".into()))?;
+  let applied = apply(&window, effect);
+  settings::update(&app, |settings| settings.window_effect = effect)?;
+  Ok(applied)
+}
+
+/// Applies the saved effect at launch.
+pub fn restore(app: &AppHandle, window: &WebviewWindow) {
+  apply(window, settings::load_settings(app.clone()).window_effect);
+}
+
+/// Replaces the window's effect with `effect`, logging any failure. Returns
+/// whether the platform supports it.
+fn apply(window: &WebviewWindow, effect: WindowEffect) -> bool {
+  match platform::apply(window, effect) {
+    Ok(applied) => applied,
+    Err(err) => {
+      log::warn!("cannot apply window effect {effect:?}: {err}");
+      false
+    }
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+  use tauri::WebviewWindow;
+  use window_vibrancy::{apply_vibrancy, clear_vibrancy, Error, NSVisualEffectMaterial};
+
+  use super::WindowEffect;
+
+  pub fn apply(window: &WebviewWindow, effect: WindowEffect) -> Result<bool, Error> {
+    clear_vibrancy(window)?;
+    let material = match effect {
+      WindowEffect::Auto | WindowEffect::FullScreenUi => NSVisualEffectMaterial::FullScreenUI,
+      WindowEffect::Sidebar => NSVisualEffectMaterial::Sidebar,
+      WindowEffect::HudWindow => NSVisualEffectMaterial::HudWindow,
+      WindowEffect::Menu => NSVisualEffectMaterial::Menu,
+      WindowEffect::Popover => NSVisualEffectMaterial::Popover,
+      WindowEffect::WindowBackground => NSVisualEffectMaterial::WindowBackground,
+      WindowEffect::UnderWindowBackground => NSVisualEffectMaterial::UnderWindowBackground,
+      WindowEffect::None => return Ok(true),
+      WindowEffect::Mica | WindowEffect::Acrylic => return Ok(false),
+    };
+    apply_vibrancy(window, material, None, None)?;
+    Ok(true)
+  }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+  use tauri::WebviewWindow;
+  use window_vibrancy::{apply_acrylic, apply_mica, clear_acrylic, clear_mica, Error};
+
+  use super::WindowEffect;
+
+  pub fn apply(window: &WebviewWindow, effect: WindowEffect) -> Result<bool, Error> {
+    // Clearing an effect the Windows version lacks fails; there is nothing to
+    // clear then.
+    let _ = clear_mica(window);
+    let _ = clear_acrylic(window);
+    match effect {
+      // Mica needs Windows 11; older versions get acrylic instead.
+      WindowEffect::Auto => apply_mica(window, None).or_else(|_| apply_acrylic(window, None))?,
+      WindowEffect::Mica => apply_mica(window, None)?,
+      WindowEffect::Acrylic => apply_acrylic(window, None)?,
+      WindowEffect::None => {}
+      _ => return Ok(false),
+    }
+    Ok(true)
+  }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+  use std::convert::Infallible;
+
+  use tauri::WebviewWindow;
+
+  use super::WindowEffect;
+
+  pub fn apply(_window: &WebviewWindow, effect: WindowEffect) -> Result<bool, Infallible> {
+    Ok(effect == WindowEffect::None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn effects_are_named_in_camel_case() {
+    let effect: WindowEffect = serde_json::from_str(r#""fullScreenUi""#).unwrap();
+    assert_eq!(effect, WindowEffect::FullScreenUi);
+    assert_eq!(
+      serde_json::to_string(&WindowEffect::None).unwrap(),
+      r#""none""#
+    );
+    assert!(serde_json::from_str::<WindowEffect>(r#""glass""#).is_err());
+  }
+}
@@ -0,0 +1,115 @@
+//! Remembers the main window's size and position between launches.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, PhysicalPosition, PhysicalSize, WebviewWindow, WindowEvent};
+
+const STATE_FILE: &str = "window-state.json";
+
+/// Geometry of the window, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+  pub width: u32,
+  pub height: u32,
+  pub x: i32,
+  pub y: i32,
+  pub maximized: bool,
+}
+
+/// Applies the saved state to `window` and saves it again when the window is
+/// closed. Failures are logged; the window just keeps its default geometry.
+pub fn track(window: &WebviewWindow) {
+  if let Some(state) = load(window) {
+    if let Err(err) = apply(window, &state) {
+      log::warn!("cannot restore window state: {err}");
+    }
+  }
+
+  let handle = window.clone();
+  window.on_window_event(move |event| {
+    if let WindowEvent::CloseRequested { .. } = event {
+      save(&handle);
+    }
+  });
+}
+
+fn state_path(window: &WebviewWindow) -> tauri::Result<PathBuf> {
+  Ok(window.path().app_config_dir()?.join(STATE_FILE))
+}
+
+fn load(window: &WebviewWindow) -> Option<WindowState> {
+  let path = state_path(window).ok()?;
+  let text = fs::read_to_string(&path).ok()?;
+  serde_json::from_str(&text)
+    .map_err(|err| log::warn!("ignoring malformed {}: {err}", path.display()))
+    .ok()
+}
+
+fn apply(window: &WebviewWindow, state: &WindowState) -> tauri::Result<()> {
+  window.set_size(PhysicalSize::new(state.width, state.height))?;
+  if is_on_screen(window, state)? {
+    window.set_position(PhysicalPosition::new(state.x, state.y))?;
+  } else {
+    // The monitor it was on is probably gone; don't restore it out of reach.
+    window.center()?;
+  }
+  if state.maximized {
+    window.maximize()?;
+  }
+  Ok(())
+}
+
+/// Whether enough of the window's title bar would be visible on some monitor
+/// to grab it.
+fn is_on_screen(window: &WebviewWindow, state: &WindowState) -> tauri::Result<bool> {
+  const GRAB: i32 = 50;
+  Ok(window.available_monitors()?.iter().any(|monitor| {
+    let (position, size) = (monitor.position(), monitor.size());
+    let (left, top) = (position.x, position.y);
+    let (right, bottom) = (left + size.width as i32, top + size.height as i32);
+    state.x + (state.width as i32) - GRAB >= left
+      && state.x + GRAB <= right
+      && state.y >= top
+      && state.y + GRAB <= bottom
+  }))
+}
+
+fn save(window: &WebviewWindow) {
+  let result = current_state(window).and_then(|state| {
+    let path = state_path(window)?;
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(&state)?;
+    fs::write(path, json)?;
+    Ok(())
+  });
+  if let Err(err) = result {
+    log::warn!("cannot save window state: {err}");
+  }
+}
+
+fn current_state(window: &WebviewWindow) -> tauri::Result<WindowState> {
+  let maximized = window.is_maximized()?;
+  if maximized || window.is_minimized()? {
+    // A maximized or minimized window doesn't report its normal geometry;
+    // keep the last one so restoring it after the next launch still works.
+    if let Some(previous) = load(window) {
+      return Ok(WindowState {
+        maximized,
+        ..previous
+      });
+    }
+  }
+  let size = window.inner_size()?;
+  let position = window.outer_position()?;
+  Ok(WindowState {
+    width: size.width,
+    height: size.height,
+    x: position.x,
+    y: position.y,
+    maximized,
+  })
+}
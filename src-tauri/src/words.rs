@@ -0,0 +1,119 @@
+//! The vocabulary of a document, which tells a word hyphenated at the end
+//! of a line (`inter-` / `national`) from a compound that happens to break
+//! at its hyphen (`well-` / `known`).
+
+use std::collections::HashSet;
+
+use crate::extract::PageText;
+
+/// First parts that make compounds rather than words: `non-trivial`,
+/// `self-contained`.
+const COMPOUND_PREFIXES: [&str; 8] = ["all", "ex", "half", "non", "quasi", "self", "so", "well"];
+
+/// Both parts of a compound are words of at least this many letters; a
+/// shorter first part is usually a syllable (`re-` / `view`).
+const MIN_PART_LEN: usize = 3;
+
+/// Every word the document uses, lowercased.
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+  words: HashSet<String>,
+}
+
+impl Vocabulary {
+  pub fn of<'a>(pages: impl IntoIterator<Item = &'a PageText>) -> Self {
+    let mut vocabulary = Vocabulary::default();
+    for page in pages {
+      vocabulary.add(page);
+    }
+    vocabulary
+  }
+
+  /// Adds the words on `page`, compounds both whole and by their parts,
+  /// leaving out the halves of words broken at the end of a line.
+  pub fn add(&mut self, page: &PageText) {
+    for line in &page.lines {
+      for token in line.text().split_whitespace() {
+        if token.ends_with('-') {
+          continue;
+        }
+        let word = token
+          .trim_matches(|c: char| !c.is_alphanumeric())
+          .to_lowercase();
+        if word.contains('-') {
+          self.words.extend(
+            word
+              .split('-')
+              .filter(|part| !part.is_empty())
+              .map(str::to_string),
+          );
+        }
+        if !word.is_empty() {
+          self.words.insert(word);
+        }
+      }
+    }
+  }
+
+  /// Whether `first-`, ending one line, and `second`, starting the next, are
+  /// one word rather than a hyphenated compound.
+  ///
+  /// Whatever the document writes elsewhere decides: `well-known` or
+  /// `international`. A word it never writes whole is taken for a
+  /// compound if both parts are words it uses on their own.
+  pub fn joins(&self, first: &str, second: &str) -> bool {
+    let first = first.to_lowercase();
+    let second = second.to_lowercase();
+    // `state-of-the-` / `art`, `up-` / `to-date`
+    if first.contains('-') || second.contains('-') {
+      return false;
+    }
+    if self.words.contains(&format!("{first}-{second}")) {
+      return false;
+    }
+    if self.words.contains(&format!("{first}{second}")) {
+      return true;
+    }
+    let word = |part: &str| part.chars().count() >= MIN_PART_LEN && self.words.contains(part);
+    !(COMPOUND_PREFIXES.contains(&first.as_str()) || word(&first) && word(&second))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::extract::{TextLine, TextSpan};
+
+  #[test]
+  fn the_document_decides_what_is_one_word() {
+    let line = |text: &str| TextLine {
+      spans: vec![TextSpan {
+        text: text.to_string(),
+        x: 0.0,
+        y: 0.0,
+        width: 0.0,
+        font_size: 10.0,
+        font_name: "Helvetica".into(),
+        link: None,
+        style: Default::default(),
+        footnote: None,
+      }],
+    };
+    let page = PageText {
+      lines: vec![
+        line("International trade, long-term loans and data-"),
+        line("driven models: cash flow, generating units and review."),
+      ],
+      images: Vec::new(),
+    };
+    let words = Vocabulary::of([&page]);
+    assert!(words.joins("inter", "national"));
+    assert!(!words.joins("long", "term"));
+    assert!(!words.joins("cash", "generating"));
+    assert!(words.joins("re", "view"));
+    assert!(words.joins("trans", "action"));
+    assert!(!words.joins("state-of-the", "art"));
+    assert!(!words.joins("non", "linear"));
+    assert!(!words.joins("up", "to-date"));
+  }
+}
@@ -1,3 +1,4 @@
+import { invoke } from '@tauri-apps/api/core';
 import { saveAs } from 'file-saver';
 import JSZip from 'jszip';
 import type { PDFFile } from '../types';
@@ -21,5 +22,5 @@ export async function downloadAllAsZip(files: PDFFile[]): Promise<void> {
 }
 
 export function copyToClipboard(text: string): Promise<void> {
-  return navigator.clipboard.writeText(text);
+  return invoke('copy_to_clipboard', { text });
 }